@@ -0,0 +1,135 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinarySource {
+    Custom,
+    Path,
+    KnownLocation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaBinary {
+    pub path: PathBuf,
+    pub source: BinarySource,
+}
+
+impl OllamaBinary {
+    // Build a Command for this binary with the extended PATH already applied
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.path);
+        command.env("PATH", crate::get_extended_path());
+        command
+    }
+
+    // Build a Command for CLI client subcommands (list, pull, run, ...), pointed at the active endpoint
+    pub fn client_command(&self) -> Command {
+        let mut command = self.command();
+        command.env("OLLAMA_HOST", crate::settings::active_endpoint().base_url());
+        command
+    }
+
+    pub fn display_path(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+}
+
+// Last successful lookup, so every command does not walk PATH and the known locations again.
+// Kept with the custom path it was made for, so a changed setting or override is picked up at once.
+static RESOLVED_BINARY: Mutex<Option<(Option<PathBuf>, OllamaBinary)>> = Mutex::new(None);
+
+fn executable_name() -> &'static str {
+    if cfg!(target_os = "windows") { "ollama.exe" } else { "ollama" }
+}
+
+// Platform-specific install locations that are not always on PATH
+pub fn known_locations() -> Vec<PathBuf> {
+    if cfg!(target_os = "windows") {
+        vec![
+            PathBuf::from("C:\\Program Files\\Ollama\\ollama.exe"),
+            PathBuf::from("C:\\Program Files (x86)\\Ollama\\ollama.exe"),
+            PathBuf::from(format!("{}\\AppData\\Local\\Programs\\Ollama\\ollama.exe",
+                std::env::var("USERPROFILE").unwrap_or_default())),
+        ]
+    } else if cfg!(target_os = "macos") {
+        vec![
+            PathBuf::from("/opt/homebrew/bin/ollama"),
+            PathBuf::from("/usr/local/bin/ollama"),
+            PathBuf::from("/Applications/Ollama.app/Contents/Resources/ollama"),
+        ]
+    } else {
+        vec![
+            PathBuf::from("/usr/local/bin/ollama"),
+            PathBuf::from("/usr/bin/ollama"),
+            PathBuf::from(format!("{}/.local/bin/ollama", std::env::var("HOME").unwrap_or_default())),
+        ]
+    }
+}
+
+// Helper function to get the saved custom binary path, if any
+pub fn custom_binary_path() -> Option<PathBuf> {
+    crate::settings::current()
+        .ollama_binary_path
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+fn search_path() -> Option<PathBuf> {
+    let separator = if cfg!(target_os = "windows") { ';' } else { ':' };
+    crate::get_extended_path()
+        .split(separator)
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| Path::new(dir).join(executable_name()))
+        .find(|candidate| candidate.is_file())
+}
+
+fn locate(custom: Option<PathBuf>) -> Result<OllamaBinary, String> {
+    // A saved custom path wins, and a broken one is reported rather than silently skipped
+    if let Some(path) = custom {
+        if path.is_file() {
+            return Ok(OllamaBinary { path, source: BinarySource::Custom });
+        }
+        return Err(format!("Configured Ollama binary not found: {}", path.display()));
+    }
+
+    if let Some(path) = search_path() {
+        return Ok(OllamaBinary { path, source: BinarySource::Path });
+    }
+
+    known_locations()
+        .into_iter()
+        .find(|path| path.is_file())
+        .map(|path| OllamaBinary { path, source: BinarySource::KnownLocation })
+        .ok_or_else(|| "Ollama executable not found on PATH or in common install locations. Make sure Ollama is installed and accessible.".to_string())
+}
+
+// Resolve the Ollama executable every backend command should use
+pub fn resolve_ollama_binary() -> Result<OllamaBinary, String> {
+    let custom = custom_binary_path();
+    let mut cached = RESOLVED_BINARY.lock().unwrap_or_else(|e| e.into_inner());
+
+    if let Some((cached_custom, binary)) = cached.as_ref() {
+        if *cached_custom == custom && binary.path.is_file() {
+            return Ok(binary.clone());
+        }
+    }
+
+    let binary = locate(custom.clone())?;
+    *cached = Some((custom, binary.clone()));
+    Ok(binary)
+}
+
+// Forget the cached lookup, e.g. after Ollama was (re)installed
+pub fn clear_binary_cache() {
+    let mut cached = RESOLVED_BINARY.lock().unwrap_or_else(|e| e.into_inner());
+    *cached = None;
+}
+
+// Helper function to build a client Command for the resolved Ollama executable
+pub fn ollama_command() -> Result<Command, String> {
+    resolve_ollama_binary().map(|binary| binary.client_command())
+}
@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::settings::Settings;
+
+// Where an effective value came from.
+//
+// Precedence, highest first: command line > environment > config file > default.
+// Command-line and environment overrides apply to the running app only and are never written
+// back to settings.json. LocalStorage values belong to the webview; they are reported as the
+// frontend passes them in and never compete with backend values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    Default,
+    LocalStorage,
+    ConfigFile,
+    Environment,
+    CommandLine,
+}
+
+pub const PRECEDENCE: [ConfigSource; 4] = [
+    ConfigSource::CommandLine,
+    ConfigSource::Environment,
+    ConfigSource::ConfigFile,
+    ConfigSource::Default,
+];
+
+// App flags, e.g. `beautifyollama --ollama-port 11500 --endpoint lab`
+const CLI_FLAGS: [&str; 3] = ["ollama-port", "endpoint", "ollama-binary"];
+
+// Matches the fallback in get_search_context
+const DEFAULT_CLOUD_SERVICE_URL: &str = "http://localhost:3000";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigCandidate {
+    pub source: ConfigSource,
+    // Variable, flag or file that supplied the value
+    pub origin: String,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: Value,
+    pub source: ConfigSource,
+    pub origin: String,
+    // Lower-precedence values that were set but lost
+    pub overridden: Vec<ConfigCandidate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigReport {
+    pub precedence: Vec<ConfigSource>,
+    pub entries: Vec<ConfigEntry>,
+}
+
+static CLI_ARGS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+// Known flags from the app's command line, accepting both `--flag value` and `--flag=value`
+fn cli_args() -> &'static HashMap<String, String> {
+    CLI_ARGS.get_or_init(|| {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let mut parsed = HashMap::new();
+        let mut index = 0;
+        while index < args.len() {
+            if let Some(flag) = args[index].strip_prefix("--") {
+                let (name, inline) = match flag.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (flag, None),
+                };
+                if CLI_FLAGS.contains(&name) {
+                    let value = inline.or_else(|| {
+                        index += 1;
+                        args.get(index).cloned()
+                    });
+                    if let Some(value) = value {
+                        parsed.insert(name.to_string(), value);
+                    }
+                }
+            }
+            index += 1;
+        }
+        parsed
+    })
+}
+
+fn cli_arg(flag: &str) -> Option<String> {
+    cli_args().get(flag).cloned()
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+fn candidate(source: ConfigSource, origin: &str, value: Value) -> ConfigCandidate {
+    ConfigCandidate { source, origin: origin.to_string(), value }
+}
+
+// Pick the highest-precedence candidate; the rest are listed as overridden
+fn resolve(key: &str, mut candidates: Vec<ConfigCandidate>) -> ConfigEntry {
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.source));
+    let winner = candidates.remove(0);
+    ConfigEntry {
+        key: key.to_string(),
+        value: winner.value,
+        source: winner.source,
+        origin: winner.origin,
+        overridden: candidates,
+    }
+}
+
+fn port_candidates() -> Vec<ConfigCandidate> {
+    let mut candidates = Vec::new();
+    if let Some(port) = env_var("OLLAMA_PORT").and_then(|v| v.trim().parse::<u16>().ok()) {
+        candidates.push(candidate(ConfigSource::Environment, "OLLAMA_PORT", json!(port)));
+    }
+    if let Some(port) = cli_arg("ollama-port").and_then(|v| v.trim().parse::<u16>().ok()) {
+        candidates.push(candidate(ConfigSource::CommandLine, "--ollama-port", json!(port)));
+    }
+    candidates
+}
+
+// Highest-precedence override of the local endpoint's port, if any
+pub fn port_override() -> Option<(ConfigSource, String, u16)> {
+    port_candidates()
+        .into_iter()
+        .max_by_key(|c| c.source)
+        .and_then(|c| c.value.as_u64().map(|port| (c.source, c.origin, port as u16)))
+}
+
+// Apply environment and command-line overrides on top of the stored settings
+pub fn apply_overrides(settings: &mut Settings) {
+    if let Some((_, _, port)) = port_override() {
+        if let Some(local) = settings.endpoints.iter_mut().find(|e| e.id == crate::endpoints::LOCAL_ENDPOINT_ID) {
+            local.port = port;
+        }
+    }
+    // An unknown --endpoint id is ignored, like in the report
+    if let Some(id) = cli_arg("endpoint").filter(|id| settings.endpoints.iter().any(|e| &e.id == id)) {
+        settings.active_endpoint = id;
+        settings.active_pool = None;
+    }
+    if let Some(path) = cli_arg("ollama-binary") {
+        settings.ollama_binary_path = Some(path);
+    }
+}
+
+// The web search script reads config.json from its working directory, which is the app's
+fn search_config_path() -> PathBuf {
+    std::env::current_dir().unwrap_or_default().join("config.json")
+}
+
+fn search_config() -> Option<serde_json::Map<String, Value>> {
+    let text = std::fs::read_to_string(search_config_path()).ok()?;
+    match serde_json::from_str(&text) {
+        Ok(Value::Object(map)) => Some(map),
+        _ => None,
+    }
+}
+
+// Build the report for every backend setting, plus any localStorage values the frontend passes in
+pub fn report(local_storage: BTreeMap<String, Value>) -> ConfigReport {
+    let stored = crate::settings::stored();
+    let effective = crate::settings::current();
+    let file_origin = crate::settings::settings_path()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| "settings.json".to_string());
+    let file = |value: Value| candidate(ConfigSource::ConfigFile, &file_origin, value);
+    let default = |value: Value| candidate(ConfigSource::Default, "built-in", value);
+
+    let mut entries = Vec::new();
+
+    // Local Ollama port
+    let stored_local_port = stored
+        .endpoints
+        .iter()
+        .find(|e| e.id == crate::endpoints::LOCAL_ENDPOINT_ID)
+        .map(|e| e.port);
+    let mut candidates = vec![default(json!(crate::DEFAULT_OLLAMA_PORT))];
+    if let Some(port) = stored_local_port {
+        candidates.push(file(json!(port)));
+    }
+    candidates.extend(port_candidates());
+    let port_entry = resolve("ollama_port", candidates);
+
+    // Active endpoint
+    let mut candidates = vec![default(json!(crate::endpoints::LOCAL_ENDPOINT_ID)), file(json!(stored.active_endpoint))];
+    if let Some(id) = cli_arg("endpoint").filter(|id| stored.endpoints.iter().any(|e| &e.id == id)) {
+        candidates.push(candidate(ConfigSource::CommandLine, "--endpoint", json!(id)));
+    }
+    let endpoint_entry = resolve("active_endpoint", candidates);
+
+    // The URL everything talks to follows whichever of the two decided it
+    let active = effective.active_endpoint();
+    let url_from = if active.id == crate::endpoints::LOCAL_ENDPOINT_ID && port_entry.source > endpoint_entry.source {
+        &port_entry
+    } else {
+        &endpoint_entry
+    };
+    let url_entry = ConfigEntry {
+        key: "ollama_url".to_string(),
+        value: json!(active.base_url()),
+        source: url_from.source,
+        origin: format!("derived from {} ({})", url_from.key, url_from.origin),
+        overridden: Vec::new(),
+    };
+    entries.push(endpoint_entry);
+    entries.push(port_entry);
+    entries.push(url_entry);
+
+    entries.push(resolve("active_pool", vec![default(Value::Null), file(json!(effective.active_pool))]));
+
+    let mut candidates = vec![candidate(ConfigSource::Default, "auto-detected", Value::Null)];
+    if let Some(path) = &stored.ollama_binary_path {
+        candidates.push(file(json!(path)));
+    }
+    if let Some(path) = cli_arg("ollama-binary") {
+        candidates.push(candidate(ConfigSource::CommandLine, "--ollama-binary", json!(path)));
+    }
+    entries.push(resolve("ollama_binary_path", candidates));
+
+    entries.push(resolve("proxy.mode", vec![default(json!(crate::proxy::ProxyMode::default())), file(json!(stored.proxy.mode))]));
+    for (name, value) in crate::proxy::detect_env_proxies() {
+        entries.push(resolve(&format!("proxy.env.{}", name), vec![candidate(ConfigSource::Environment, &name, json!(value))]));
+    }
+
+    let mut candidates = vec![default(json!(DEFAULT_CLOUD_SERVICE_URL))];
+    if let Some(url) = env_var("CLOUD_SERVICE_URL") {
+        candidates.push(candidate(ConfigSource::Environment, "CLOUD_SERVICE_URL", json!(url)));
+    }
+    entries.push(resolve("cloud_service_url", candidates));
+
+    entries.push(resolve("github_repo", vec![default(json!(crate::GITHUB_REPO))]));
+    entries.push(resolve("app_version", vec![default(json!(crate::CURRENT_VERSION))]));
+
+    // Web search script: its own defaults < its config.json < arguments the app passes from settings.json
+    let script_config = search_config();
+    let script_origin = search_config_path().display().to_string();
+    let from_settings = |flag: &str, setting: &str| format!("{} (from {} in settings.json)", flag, setting);
+    let search_values: [(&str, &str, &str, Option<Value>); 4] = [
+        ("model", "--model", "default_models.search", stored.default_models.search.as_ref().map(|m| json!(m))),
+        (
+            "searxng_instances",
+            "--instances",
+            "search.searxng_instances",
+            Some(json!(stored.search.searxng_instances)).filter(|_| !stored.search.searxng_instances.is_empty()),
+        ),
+        ("max_results", "--max-results", "search.max_results", stored.search.max_results.map(|v| json!(v))),
+        ("timeout", "--timeout", "search.timeout_secs", stored.search.timeout_secs.map(|v| json!(v))),
+    ];
+    for (key, flag, setting, passed) in search_values {
+        let mut candidates = vec![candidate(ConfigSource::Default, "built into main.py", Value::Null)];
+        if let Some(value) = script_config.as_ref().and_then(|c| c.get(key)) {
+            candidates.push(candidate(ConfigSource::ConfigFile, &script_origin, value.clone()));
+        }
+        if let Some(value) = passed {
+            candidates.push(candidate(ConfigSource::CommandLine, &from_settings(flag, setting), value));
+        }
+        entries.push(resolve(&format!("search.{}", key), candidates));
+    }
+
+    for (key, value) in local_storage {
+        entries.push(resolve(&format!("local_storage.{}", key), vec![candidate(ConfigSource::LocalStorage, &key, value)]));
+    }
+
+    ConfigReport {
+        precedence: PRECEDENCE.to_vec(),
+        entries,
+    }
+}
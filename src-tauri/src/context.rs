@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use crate::provider::{ChatMessage, ChatRequest, Provider, ProviderKind};
+
+// Ollama's context size when neither the request nor the Modelfile sets num_ctx. Servers before
+// 0.6 used 2048 and newer ones may derive a larger one from VRAM, so the size is always sent.
+const DEFAULT_NUM_CTX: u64 = 4096;
+
+// Smallest context size accepted in the settings and conversation options
+pub const MIN_NUM_CTX: u64 = 256;
+
+// Rough cost of the role markers the chat template wraps around every message
+const MESSAGE_OVERHEAD_TOKENS: u64 = 4;
+
+// Summaries of dropped turns are asked to stay under this many tokens and cut if they do not
+const SUMMARY_TOKENS: u64 = 400;
+
+const PREVIEW_CHARS: usize = 80;
+
+// Summaries are kept for regenerated replies, which trim the same turns again
+const SUMMARY_CACHE_SIZE: usize = 32;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStrategy {
+    // Drop the oldest messages first, system prompts included
+    DropOldest,
+    // Drop the oldest turns but keep system prompts and pinned messages
+    #[default]
+    KeepPinned,
+    // Like keep_pinned, with the dropped turns replaced by a summary the model writes
+    Summarize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextSettings {
+    pub strategy: ContextStrategy,
+    // Context size for requests that set none; None uses the model's own
+    pub num_ctx: Option<u64>,
+    // Tokens kept free for the reply when the request does not set num_predict
+    pub reserve_tokens: u64,
+}
+
+impl Default for ContextSettings {
+    fn default() -> Self {
+        ContextSettings { strategy: ContextStrategy::default(), num_ctx: None, reserve_tokens: 512 }
+    }
+}
+
+impl ContextSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(num_ctx) = self.num_ctx {
+            if num_ctx < MIN_NUM_CTX {
+                return Err(format!("Context size must be at least {} tokens", MIN_NUM_CTX));
+            }
+            if self.reserve_tokens >= num_ctx {
+                return Err("Reply reserve must be smaller than the context size".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+// Where the context size a request was checked against came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowSource {
+    Request,
+    Settings,
+    Modelfile,
+    // OLLAMA_CONTEXT_LENGTH in the server environment
+    ServerEnv,
+    Default,
+    // OpenAI-compatible servers do not report it
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DroppedMessage {
+    // Position in the message array the UI sent; None for a conversation system prompt the UI did not send
+    pub index: Option<usize>,
+    pub role: String,
+    pub tokens: u64,
+    pub preview: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextReport {
+    pub strategy: ContextStrategy,
+    // Context size in tokens; None when it is unknown and nothing was checked
+    pub window: Option<u64>,
+    pub window_source: WindowSource,
+    // Tokens kept free for the reply
+    pub reserved: u64,
+    // Estimated prompt size before and after trimming
+    pub estimated_tokens: u64,
+    pub sent_tokens: u64,
+    pub dropped: Vec<DroppedMessage>,
+    // Text of the system message that stands in for the dropped turns
+    pub summary: Option<String>,
+    // Still too large after trimming, e.g. because the last message alone exceeds the window
+    pub overflow: bool,
+    pub warnings: Vec<String>,
+}
+
+impl ContextReport {
+    // Point the dropped indices back at the UI's array when `inserted` messages were put in front of it
+    pub fn skip_inserted(&mut self, inserted: usize) {
+        for message in &mut self.dropped {
+            message.index = message.index.and_then(|index| index.checked_sub(inserted));
+        }
+    }
+}
+
+static SUMMARIES: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+fn summary_cache() -> std::sync::MutexGuard<'static, HashMap<String, String>> {
+    SUMMARIES
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+// Without the model's tokenizer: about four characters per token for English and code,
+// and close to one per character for most other scripts
+pub fn estimate_tokens(text: &str) -> u64 {
+    let (ascii, other) = text
+        .chars()
+        .fold((0u64, 0u64), |(ascii, other), c| if c.is_ascii() { (ascii + 1, other) } else { (ascii, other + 1) });
+    ascii.div_ceil(4) + other
+}
+
+fn message_tokens(message: &ChatMessage) -> u64 {
+    MESSAGE_OVERHEAD_TOKENS + estimate_tokens(&message.content)
+}
+
+fn preview(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= PREVIEW_CHARS {
+        return text;
+    }
+    format!("{}…", text.chars().take(PREVIEW_CHARS).collect::<String>())
+}
+
+// The context size to check against, in the order Ollama itself applies them
+async fn window(provider: &dyn Provider, request: &ChatRequest, settings: &ContextSettings, warnings: &mut Vec<String>) -> (Option<u64>, WindowSource) {
+    if let Some(num_ctx) = request.options.get("num_ctx").and_then(|v| v.as_u64()) {
+        return (Some(num_ctx), WindowSource::Request);
+    }
+    if let Some(num_ctx) = settings.num_ctx {
+        return (Some(num_ctx), WindowSource::Settings);
+    }
+    if provider.kind() != ProviderKind::Ollama {
+        return (None, WindowSource::Unknown);
+    }
+
+    let model = provider.model_context(&request.model).await.unwrap_or_else(|e| {
+        warnings.push(format!("Could not read the context size of {}: {}", request.model, e));
+        Default::default()
+    });
+    if let Some(num_ctx) = model.num_ctx {
+        return (Some(num_ctx), WindowSource::Modelfile);
+    }
+    let server_env = crate::settings::current()
+        .server_env
+        .get("OLLAMA_CONTEXT_LENGTH")
+        .and_then(|value| value.trim().parse::<u64>().ok());
+    let (num_ctx, source) = match server_env {
+        Some(num_ctx) => (num_ctx, WindowSource::ServerEnv),
+        None => (DEFAULT_NUM_CTX, WindowSource::Default),
+    };
+    // A model trained for less than the server default cannot use more
+    (Some(model.context_length.map_or(num_ctx, |length| length.min(num_ctx))), source)
+}
+
+// Indices to leave out so the rest fits in `budget`. Whole turns go, oldest first: a user message
+// together with the replies that follow it. The last message is always kept, and with anything
+// but drop_oldest so are system prompts and pinned messages.
+fn trim(messages: &[ChatMessage], tokens: &[u64], budget: u64, strategy: ContextStrategy) -> Vec<usize> {
+    let last = messages.len().saturating_sub(1);
+    let protected = |index: usize| {
+        let message = &messages[index];
+        index == last || (strategy != ContextStrategy::DropOldest && (message.role == "system" || message.pinned))
+    };
+
+    let mut total: u64 = tokens.iter().sum();
+    let mut dropped = Vec::new();
+    let mut index = 0;
+    while index < last && total > budget {
+        let end = (index + 1..messages.len())
+            .find(|&i| messages[i].role == "user")
+            .unwrap_or(messages.len());
+        for i in (index..end).filter(|&i| !protected(i)) {
+            total -= tokens[i];
+            dropped.push(i);
+        }
+        index = end;
+    }
+    dropped
+}
+
+// Cut `text` to about `limit` tokens, keeping its start or its end
+fn truncate_to_tokens(text: &str, limit: u64, keep_end: bool) -> String {
+    if estimate_tokens(text) <= limit {
+        return text.to_string();
+    }
+    // In quarter tokens, matching estimate_tokens
+    let mut budget = limit * 4;
+    let fits = |c: &char| {
+        let cost = if c.is_ascii() { 1 } else { 4 };
+        let fits = cost <= budget;
+        budget = budget.saturating_sub(cost);
+        fits
+    };
+    if keep_end {
+        let kept: Vec<char> = text.chars().rev().take_while(fits).collect();
+        format!("…{}", kept.into_iter().rev().collect::<String>())
+    } else {
+        format!("{}…", text.chars().take_while(fits).collect::<String>())
+    }
+}
+
+// Ask the model for a summary of the dropped turns
+async fn summarize(provider: &dyn Provider, request: &ChatRequest, dropped: &[&ChatMessage], window: u64) -> Result<String, String> {
+    let key = crate::conversations::fingerprint(
+        std::iter::once(("model", request.model.as_str())).chain(dropped.iter().map(|m| (m.role.as_str(), m.content.as_str()))),
+    );
+    if let Some(summary) = summary_cache().get(&key) {
+        return Ok(summary.clone());
+    }
+
+    let transcript = dropped
+        .iter()
+        .map(|message| format!("{}: {}", message.role, message.content.trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    // The most recent of the dropped turns matter most if they do not all fit
+    let transcript = truncate_to_tokens(&transcript, window.saturating_sub(SUMMARY_TOKENS + 256), true);
+    let prompt = format!(
+        "Summarize the earlier part of this conversation in at most {} words. Keep names, facts, decisions \
+         and open questions the rest of the conversation may refer to. Reply with the summary only.\n\n{}",
+        SUMMARY_TOKENS * 3 / 4,
+        transcript
+    );
+
+    let mut summary_request = ChatRequest { think: Some(false), ..ChatRequest::prompt(request.model.clone(), prompt) };
+    summary_request.options.insert("num_predict".to_string(), json!(SUMMARY_TOKENS));
+    if let Some(num_ctx) = request.options.get("num_ctx") {
+        summary_request.options.insert("num_ctx".to_string(), num_ctx.clone());
+    }
+    let result = provider.chat(&summary_request, &mut |_| {}).await?;
+    let summary = truncate_to_tokens(result.content.trim(), SUMMARY_TOKENS, false);
+    if summary.is_empty() {
+        return Err("the model returned an empty summary".to_string());
+    }
+
+    let mut cache = summary_cache();
+    if cache.len() >= SUMMARY_CACHE_SIZE {
+        cache.clear();
+    }
+    cache.insert(key, summary.clone());
+    Ok(summary)
+}
+
+async fn run(provider: &dyn Provider, request: &mut ChatRequest, apply: bool) -> ContextReport {
+    let settings = crate::settings::current().context;
+    let mut warnings = Vec::new();
+    let (window, window_source) = window(provider, request, &settings, &mut warnings).await;
+
+    let tokens: Vec<u64> = request.messages.iter().map(message_tokens).collect();
+    let estimated_tokens: u64 = tokens.iter().sum();
+    let mut report = ContextReport {
+        strategy: settings.strategy,
+        window,
+        window_source,
+        reserved: 0,
+        estimated_tokens,
+        sent_tokens: estimated_tokens,
+        dropped: Vec::new(),
+        summary: None,
+        overflow: false,
+        warnings: Vec::new(),
+    };
+    let Some(window) = window else {
+        report.warnings = warnings;
+        return report;
+    };
+
+    // Pin the size that was checked, so the server cannot fall back to a smaller default
+    if apply && provider.kind() == ProviderKind::Ollama && !request.options.contains_key("num_ctx") {
+        request.options.insert("num_ctx".to_string(), json!(window));
+    }
+
+    let num_predict = request.options.get("num_predict").and_then(|v| v.as_i64()).filter(|n| *n > 0);
+    report.reserved = num_predict.map_or(settings.reserve_tokens, |n| n as u64).min(window / 2);
+    let budget = window - report.reserved;
+    if estimated_tokens <= budget {
+        report.warnings = warnings;
+        return report;
+    }
+
+    let mut dropped = match settings.strategy {
+        ContextStrategy::Summarize => {
+            let summary_budget = budget.saturating_sub(SUMMARY_TOKENS + MESSAGE_OVERHEAD_TOKENS);
+            trim(&request.messages, &tokens, summary_budget, ContextStrategy::KeepPinned)
+        }
+        strategy => trim(&request.messages, &tokens, budget, strategy),
+    };
+
+    let mut summary = None;
+    if apply && settings.strategy == ContextStrategy::Summarize && !dropped.is_empty() {
+        let turns: Vec<&ChatMessage> = dropped.iter().map(|&i| &request.messages[i]).collect();
+        match summarize(provider, request, &turns, window).await {
+            Ok(text) => summary = Some(text),
+            Err(e) => {
+                // Plain trimming still beats letting the server cut off the start
+                warnings.push(format!("Could not summarize the dropped turns ({}); they were left out instead", e));
+                dropped = trim(&request.messages, &tokens, budget, ContextStrategy::KeepPinned);
+            }
+        }
+    }
+
+    report.dropped = dropped
+        .iter()
+        .map(|&index| DroppedMessage {
+            index: Some(index),
+            role: request.messages[index].role.clone(),
+            tokens: tokens[index],
+            preview: preview(&request.messages[index].content),
+        })
+        .collect();
+    if apply {
+        let first_dropped = dropped.first().copied();
+        let messages = std::mem::take(&mut request.messages);
+        for (index, message) in messages.into_iter().enumerate() {
+            if Some(index) == first_dropped {
+                if let Some(summary) = &summary {
+                    request.messages.push(ChatMessage {
+                        role: "system".to_string(),
+                        content: format!("Summary of the earlier conversation:\n{}", summary),
+                        pinned: false,
+                    });
+                }
+            }
+            if !dropped.contains(&index) {
+                request.messages.push(message);
+            }
+        }
+        report.sent_tokens = request.messages.iter().map(message_tokens).sum();
+    } else {
+        report.sent_tokens = estimated_tokens - dropped.iter().map(|&i| tokens[i]).sum::<u64>();
+    }
+    report.overflow = report.sent_tokens > budget;
+    report.summary = summary;
+    report.warnings = warnings;
+    report
+}
+
+// Check a chat request against the model's context window and, when it does not fit, trim it
+// with the configured strategy. The report tells the UI what was left out.
+pub async fn fit(provider: &dyn Provider, request: &mut ChatRequest) -> ContextReport {
+    run(provider, request, true).await
+}
+
+// What `fit` would leave out, without asking the model for a summary or changing the request
+pub async fn check(provider: &dyn Provider, request: &ChatRequest) -> ContextReport {
+    run(provider, &mut request.clone(), false).await
+}
@@ -0,0 +1,1592 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use rusqlite::{params, Connection, DatabaseName, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use crate::encryption::{self, DataKey, KeyFile};
+use crate::generation::GenerationOptions;
+
+// Lives in the app data directory, next to nothing the webview can reset
+const DATABASE_FILE: &str = "conversations.db";
+
+// Wrapped key of an encrypted database; without it the conversations cannot be read
+const KEY_FILE: &str = "conversations.key.json";
+
+// Copy being written while the database is encrypted or decrypted
+const CONVERTING_SUFFIX: &str = ".converting";
+
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+const LOCKED_ERROR: &str = "The conversation store is locked; unlock it with your passphrase first";
+const NOT_ENCRYPTED_ERROR: &str = "The conversation store is not encrypted";
+
+// Key in the app_state table recording when the localStorage copy was imported
+const LOCAL_STORAGE_IMPORT_KEY: &str = "local_storage_import";
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+
+pub const DEFAULT_TITLE: &str = "New Conversation";
+
+const ROLES: [&str; 3] = ["system", "user", "assistant"];
+
+// Schema migrations, applied in order inside a transaction each.
+// PRAGMA user_version records how many have run; never edit one that has shipped, append a new one.
+const MIGRATIONS: &[&str] = &[
+    // 1: conversations, their messages and app bookkeeping
+    "CREATE TABLE conversations (
+        id TEXT PRIMARY KEY,
+        title TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE INDEX conversations_updated_at ON conversations (updated_at DESC);
+
+    CREATE TABLE messages (
+        id TEXT PRIMARY KEY,
+        conversation_id TEXT NOT NULL REFERENCES conversations (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        role TEXT NOT NULL,
+        content TEXT NOT NULL,
+        model TEXT,
+        parameters TEXT,
+        metadata TEXT,
+        created_at INTEGER NOT NULL
+    );
+    CREATE INDEX messages_conversation_position ON messages (conversation_id, position);
+
+    CREATE TABLE app_state (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );",
+    // 2: full-text index over message content. The index refers to rows by an integer key, so
+    // messages is rebuilt with one; the implicit rowid of a table with a TEXT key is not stable.
+    "CREATE TABLE messages_v2 (
+        seq INTEGER PRIMARY KEY,
+        id TEXT NOT NULL UNIQUE,
+        conversation_id TEXT NOT NULL REFERENCES conversations (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        role TEXT NOT NULL,
+        content TEXT NOT NULL,
+        model TEXT,
+        parameters TEXT,
+        metadata TEXT,
+        created_at INTEGER NOT NULL
+    );
+    INSERT INTO messages_v2 (id, conversation_id, position, role, content, model, parameters, metadata, created_at)
+        SELECT id, conversation_id, position, role, content, model, parameters, metadata, created_at
+        FROM messages ORDER BY conversation_id, position;
+    DROP TABLE messages;
+    ALTER TABLE messages_v2 RENAME TO messages;
+    CREATE INDEX messages_conversation_position ON messages (conversation_id, position);
+    CREATE INDEX messages_created_at ON messages (created_at);
+
+    CREATE VIRTUAL TABLE message_search USING fts5(
+        content,
+        content = 'messages',
+        content_rowid = 'seq',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    INSERT INTO message_search (message_search) VALUES ('rebuild');
+
+    CREATE TRIGGER message_search_insert AFTER INSERT ON messages BEGIN
+        INSERT INTO message_search (rowid, content) VALUES (new.seq, new.content);
+    END;
+    CREATE TRIGGER message_search_delete AFTER DELETE ON messages BEGIN
+        INSERT INTO message_search (message_search, rowid, content) VALUES ('delete', old.seq, old.content);
+    END;
+    CREATE TRIGGER message_search_update AFTER UPDATE OF content ON messages BEGIN
+        INSERT INTO message_search (message_search, rowid, content) VALUES ('delete', old.seq, old.content);
+        INSERT INTO message_search (rowid, content) VALUES (new.seq, new.content);
+    END;",
+    // 3: messages form a tree. Edits and regenerated replies are siblings under the same parent,
+    // and each conversation remembers the leaf of the branch it shows.
+    "ALTER TABLE messages ADD COLUMN parent_id TEXT REFERENCES messages (id) ON DELETE CASCADE;
+    ALTER TABLE conversations ADD COLUMN active_leaf_id TEXT;
+    UPDATE messages SET parent_id = (
+        SELECT p.id FROM messages p
+        WHERE p.conversation_id = messages.conversation_id AND p.position < messages.position
+        ORDER BY p.position DESC LIMIT 1
+    );
+    UPDATE conversations SET active_leaf_id = (
+        SELECT m.id FROM messages m WHERE m.conversation_id = conversations.id ORDER BY m.position DESC LIMIT 1
+    );
+    CREATE INDEX messages_parent ON messages (parent_id);",
+    // 4: pinned messages are kept when older turns are trimmed to fit the model's context
+    "ALTER TABLE messages ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;",
+    // 5: generation options (system prompt, temperature, ...) per conversation, as JSON
+    "ALTER TABLE conversations ADD COLUMN options TEXT;",
+    // 6: the model a conversation uses and the persona it was started from
+    "ALTER TABLE conversations ADD COLUMN model TEXT;
+    ALTER TABLE conversations ADD COLUMN persona_id TEXT;",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    // Milliseconds since the Unix epoch, like JavaScript's Date.now()
+    pub created_at: i64,
+    pub updated_at: i64,
+    // Messages in every branch
+    pub message_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: String,
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub active_leaf_id: Option<String>,
+    // Used for requests that name the conversation but no model
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub persona_id: Option<String>,
+    #[serde(default)]
+    pub options: GenerationOptions,
+    // The active branch from the first message to the leaf, or the whole tree where noted
+    pub messages: Vec<Message>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub id: String,
+    pub conversation_id: String,
+    // Message this one answers or follows; None for the first message of a branch tree
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    pub role: String,
+    pub content: String,
+    // Model that produced an assistant reply
+    pub model: Option<String>,
+    // Generation options the reply was requested with (temperature, num_ctx, ...)
+    pub parameters: Option<Value>,
+    // Everything else the UI keeps with a message: verbose stats, web search sources, ...
+    pub metadata: Option<Value>,
+    pub created_at: i64,
+    // Kept in the request even when older turns no longer fit the context window
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+// A message as the frontend sends it; the backend fills in what is missing
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NewMessage {
+    #[serde(default)]
+    pub id: Option<String>,
+    // Where to attach the message; defaults to the end of the active branch.
+    // Pointing at a message that already has replies starts a new branch.
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    pub role: String,
+    pub content: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub parameters: Option<Value>,
+    #[serde(default)]
+    pub metadata: Option<Value>,
+    #[serde(default)]
+    pub created_at: Option<i64>,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+// A complete conversation coming from an importer, written in one go
+#[derive(Debug, Clone)]
+pub struct NewConversation {
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    // Parents before children. `id` and `parent_id` are the source's keys; new ids are assigned on insert.
+    pub messages: Vec<NewMessage>,
+    // Source key of the leaf to show; defaults to the last message
+    pub active_leaf: Option<String>,
+    pub options: GenerationOptions,
+}
+
+// The messages that share a parent, i.e. the alternatives at one point of a conversation
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Branches {
+    pub parent_id: Option<String>,
+    pub siblings: Vec<Message>,
+    // Which sibling lies on the active branch
+    pub active_index: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchFilters {
+    pub model: Option<String>,
+    pub role: Option<String>,
+    // Inclusive range in milliseconds since the Unix epoch
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+// Part of a snippet; `highlight` marks text that matched the query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlight: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub conversation_id: String,
+    pub conversation_title: String,
+    pub message_id: String,
+    pub role: String,
+    pub model: Option<String>,
+    pub snippet: Vec<SnippetPart>,
+    pub created_at: i64,
+    // bm25 relevance, lower is better
+    pub rank: f64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub conversations: usize,
+    pub messages: usize,
+    // Entries that could not be read or whose id already exists
+    pub skipped: usize,
+    // The import had already run; nothing was changed
+    pub already_imported: bool,
+}
+
+// The open database, or the means to open it once an encrypted store is unlocked
+struct Database {
+    path: PathBuf,
+    key_path: PathBuf,
+    // Present when the store is encrypted
+    key_file: Option<KeyFile>,
+    // None while an encrypted store is locked
+    connection: Option<Connection>,
+    // Kept while unlocked so a new recovery key can be issued without asking for the passphrase
+    data_key: Option<DataKey>,
+    last_used: Instant,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct EncryptionStatus {
+    pub encrypted: bool,
+    pub locked: bool,
+}
+
+static DATABASE: OnceLock<Mutex<Database>> = OnceLock::new();
+
+fn sql_error(e: rusqlite::Error) -> String {
+    format!("Conversation database error: {}", e)
+}
+
+fn json_error(e: serde_json::Error) -> String {
+    format!("Failed to serialize message data: {}", e)
+}
+
+// Unencrypted SQLite files start with this; SQLCipher files start with a random salt
+fn is_plaintext(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    match std::fs::File::open(path).and_then(|mut file| file.read_exact(&mut header)) {
+        Ok(()) => &header == SQLITE_HEADER,
+        // Missing or empty: a new database
+        Err(_) => true,
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), String> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Failed to remove {}: {}", path.display(), e))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Move a converted copy over the database once the old connection is closed
+fn replace_database(path: &Path, converted: &Path) -> Result<(), String> {
+    std::fs::rename(converted, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+    // Left over only if the old connection did not close cleanly; they belong to the old file
+    remove_if_exists(&with_suffix(path, "-wal"))?;
+    remove_if_exists(&with_suffix(path, "-shm"))
+}
+
+// Finish or roll back a switch between encrypted and plain that was interrupted.
+// Encrypting writes the copy, then the key file, then swaps the files; decrypting writes the copy,
+// swaps the files, then removes the key file.
+fn recover_conversion(path: &Path, key_path: &Path) -> Result<(), String> {
+    let converted = with_suffix(path, CONVERTING_SUFFIX);
+    if converted.exists() {
+        if !is_plaintext(&converted) && key_path.exists() && is_plaintext(path) {
+            println!("Finishing conversation database encryption that was interrupted");
+            replace_database(path, &converted)?;
+        } else {
+            remove_if_exists(&converted)?;
+        }
+    }
+    if key_path.exists() && is_plaintext(path) {
+        println!("Conversation database is not encrypted; removing the stale key file");
+        remove_if_exists(key_path)?;
+    }
+    Ok(())
+}
+
+fn open(path: &Path, data_key: Option<&DataKey>) -> Result<Connection, String> {
+    let mut connection = Connection::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    if let Some(data_key) = data_key {
+        let key = encryption::sqlcipher_key(data_key);
+        connection.pragma_update(None, "key", &*key).map_err(sql_error)?;
+        // SQLCipher only notices a wrong key on the first read
+        connection
+            .query_row("SELECT count(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+            .map_err(|_| "The conversation database could not be decrypted; the key file does not belong to it".to_string())?;
+        // Keep sorting and index builds off disk, where SQLCipher does not encrypt them
+        connection.execute_batch("PRAGMA temp_store = MEMORY;").map_err(sql_error)?;
+    }
+    connection
+        .execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")
+        .map_err(sql_error)?;
+    migrate(&mut connection)?;
+    Ok(connection)
+}
+
+// Open (or create) the database in the app data directory and bring its schema up to date.
+// An encrypted database stays closed until unlock is called. Called once from the Tauri setup hook.
+pub fn init(data_dir: PathBuf) -> Result<(), String> {
+    std::fs::create_dir_all(&data_dir)
+        .map_err(|e| format!("Failed to create data directory {}: {}", data_dir.display(), e))?;
+    let path = data_dir.join(DATABASE_FILE);
+    let key_path = data_dir.join(KEY_FILE);
+    recover_conversion(&path, &key_path)?;
+
+    let key_file = encryption::read_key_file(&key_path)?;
+    let connection = match key_file {
+        Some(_) => {
+            println!("Conversation database is encrypted; it opens once unlocked");
+            None
+        }
+        None => Some(open(&path, None)?),
+    };
+
+    let database = Database {
+        path,
+        key_path,
+        key_file,
+        connection,
+        data_key: None,
+        last_used: Instant::now(),
+    };
+    DATABASE
+        .set(Mutex::new(database))
+        .map_err(|_| "Conversation database is already open".to_string())
+}
+
+fn migrate(connection: &mut Connection) -> Result<(), String> {
+    let version = connection
+        .query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))
+        .map_err(sql_error)? as usize;
+
+    if version > MIGRATIONS.len() {
+        // Written by a newer build; the tables we know are still there, so keep going
+        eprintln!(
+            "Conversation database has schema version {}, this build knows up to {}",
+            version,
+            MIGRATIONS.len()
+        );
+        return Ok(());
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let target = index + 1;
+        let transaction = connection.transaction().map_err(sql_error)?;
+        transaction
+            .execute_batch(migration)
+            .map_err(|e| format!("Failed to migrate conversation database to version {}: {}", target, e))?;
+        transaction
+            .pragma_update(None, "user_version", target as i64)
+            .map_err(sql_error)?;
+        transaction.commit().map_err(sql_error)?;
+        println!("Migrated conversation database to version {}", target);
+    }
+    Ok(())
+}
+
+fn database() -> Result<MutexGuard<'static, Database>, String> {
+    let database = DATABASE
+        .get()
+        .ok_or_else(|| "Conversation database is not available".to_string())?;
+    Ok(database.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+fn with_connection<T, F>(f: F) -> Result<T, String>
+where
+    F: FnOnce(&mut Connection) -> Result<T, String>,
+{
+    let mut database = database()?;
+    database.last_used = Instant::now();
+    let locked = database.key_file.is_some();
+    let connection = database.connection.as_mut().ok_or_else(|| {
+        if locked {
+            LOCKED_ERROR.to_string()
+        } else {
+            // Only after a failed switch that could not reopen the file
+            "Conversation database is not available".to_string()
+        }
+    })?;
+    f(connection)
+}
+
+pub fn encryption_status() -> Result<EncryptionStatus, String> {
+    let database = database()?;
+    Ok(EncryptionStatus {
+        encrypted: database.key_file.is_some(),
+        locked: database.connection.is_none(),
+    })
+}
+
+// Copy everything into a database at `target` under `key` ("" for none) with SQLCipher's export
+fn export_copy(connection: &Connection, target: &Path, key: &str) -> Result<(), String> {
+    remove_if_exists(target)?;
+    let version = connection
+        .query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))
+        .map_err(sql_error)?;
+    connection
+        .execute("ATTACH DATABASE ?1 AS converted KEY ?2", params![target.to_string_lossy(), key])
+        .map_err(sql_error)?;
+    let exported = connection
+        .query_row("SELECT sqlcipher_export('converted')", [], |_| Ok(()))
+        .and_then(|_| connection.pragma_update(Some(DatabaseName::Attached("converted")), "user_version", version));
+    let detached = connection.execute_batch("DETACH DATABASE converted");
+    exported.and(detached).map_err(|e| {
+        let _ = std::fs::remove_file(target);
+        format!("Failed to convert the conversation database: {}", e)
+    })
+}
+
+// Encrypt the store under a passphrase. Returns the recovery key, which is shown only this once.
+pub fn enable_encryption(passphrase: &str) -> Result<String, String> {
+    let data_key = encryption::new_data_key();
+    let (key_file, recovery_key) = KeyFile::create(&data_key, passphrase)?;
+
+    let mut database = database()?;
+    if database.key_file.is_some() {
+        return Err("The conversation store is already encrypted".to_string());
+    }
+    let connection = database.connection.take().ok_or_else(|| LOCKED_ERROR.to_string())?;
+    let converted = with_suffix(&database.path, CONVERTING_SUFFIX);
+    if let Err(e) = export_copy(&connection, &converted, &encryption::sqlcipher_key(&data_key)) {
+        database.connection = Some(connection);
+        return Err(e);
+    }
+    if let Err(e) = encryption::write_key_file(&database.key_path, &key_file) {
+        let _ = std::fs::remove_file(&converted);
+        database.connection = Some(connection);
+        return Err(e);
+    }
+    drop(connection);
+
+    let switched = replace_database(&database.path, &converted).and_then(|_| open(&database.path, Some(&data_key)));
+    let connection = match switched {
+        Ok(connection) => connection,
+        Err(e) => {
+            restore(&mut database, data_key);
+            return Err(e);
+        }
+    };
+    database.connection = Some(connection);
+    database.key_file = Some(key_file);
+    database.data_key = Some(data_key);
+    database.last_used = Instant::now();
+    println!("Conversation database is now encrypted");
+    Ok(recovery_key)
+}
+
+// Decrypt the store for good; needs the passphrase even while unlocked
+pub fn disable_encryption(passphrase: &str) -> Result<(), String> {
+    let key_file = database()?
+        .key_file
+        .clone()
+        .ok_or_else(|| NOT_ENCRYPTED_ERROR.to_string())?;
+    let data_key = key_file.unlock(passphrase)?;
+
+    let mut database = database()?;
+    let connection = match database.connection.take() {
+        Some(connection) => connection,
+        None => open(&database.path, Some(&data_key))?,
+    };
+    let converted = with_suffix(&database.path, CONVERTING_SUFFIX);
+    if let Err(e) = export_copy(&connection, &converted, "") {
+        database.connection = Some(connection);
+        return Err(e);
+    }
+    drop(connection);
+
+    let switched = replace_database(&database.path, &converted)
+        .and_then(|_| remove_if_exists(&database.key_path))
+        .and_then(|_| open(&database.path, None));
+    let connection = match switched {
+        Ok(connection) => connection,
+        Err(e) => {
+            restore(&mut database, data_key);
+            return Err(e);
+        }
+    };
+    database.connection = Some(connection);
+    database.key_file = None;
+    database.data_key = None;
+    database.last_used = Instant::now();
+    println!("Conversation database is no longer encrypted");
+    Ok(())
+}
+
+// Bring the in-memory state back in line with the files after a switch failed part way:
+// whichever database is on disk is reopened with the key that belongs to it
+fn restore(database: &mut Database, data_key: DataKey) {
+    let _ = std::fs::remove_file(with_suffix(&database.path, CONVERTING_SUFFIX));
+    let reopened = if is_plaintext(&database.path) {
+        // Written for an encrypted copy that never took the database's place, or not yet removed
+        let _ = std::fs::remove_file(&database.key_path);
+        database.key_file = None;
+        database.data_key = None;
+        open(&database.path, None)
+    } else {
+        database.key_file = encryption::read_key_file(&database.key_path).ok().flatten();
+        open(&database.path, Some(&data_key)).inspect(|_| database.data_key = Some(data_key))
+    };
+    database.connection = reopened
+        .map_err(|e| eprintln!("Failed to reopen the conversation database: {}", e))
+        .ok();
+}
+
+fn open_unlocked(database: &mut Database, data_key: DataKey) -> Result<(), String> {
+    database.connection = Some(open(&database.path, Some(&data_key))?);
+    database.data_key = Some(data_key);
+    database.last_used = Instant::now();
+    Ok(())
+}
+
+pub fn unlock(passphrase: &str) -> Result<(), String> {
+    let key_file = database()?
+        .key_file
+        .clone()
+        .ok_or_else(|| NOT_ENCRYPTED_ERROR.to_string())?;
+    // Derived before taking the lock: it is deliberately slow
+    let data_key = key_file.unlock(passphrase)?;
+    let mut database = database()?;
+    if database.connection.is_some() {
+        return Ok(());
+    }
+    open_unlocked(&mut database, data_key)
+}
+
+// For a forgotten passphrase: unlock with the recovery key and set a new passphrase
+pub fn unlock_with_recovery_key(recovery_key: &str, new_passphrase: &str) -> Result<(), String> {
+    let mut key_file = database()?
+        .key_file
+        .clone()
+        .ok_or_else(|| NOT_ENCRYPTED_ERROR.to_string())?;
+    let data_key = key_file.unlock_with_recovery_key(recovery_key)?;
+    key_file.set_passphrase(&data_key, new_passphrase)?;
+
+    let mut database = database()?;
+    if database.connection.is_none() {
+        open_unlocked(&mut database, data_key)?;
+    }
+    encryption::write_key_file(&database.key_path, &key_file)?;
+    database.key_file = Some(key_file);
+    Ok(())
+}
+
+pub fn change_passphrase(current: &str, new_passphrase: &str) -> Result<(), String> {
+    let mut key_file = database()?
+        .key_file
+        .clone()
+        .ok_or_else(|| NOT_ENCRYPTED_ERROR.to_string())?;
+    let data_key = key_file.unlock(current)?;
+    key_file.set_passphrase(&data_key, new_passphrase)?;
+
+    let mut database = database()?;
+    encryption::write_key_file(&database.key_path, &key_file)?;
+    database.key_file = Some(key_file);
+    Ok(())
+}
+
+// Replace the recovery key with a new one, which `save` gets before the old one stops working
+pub fn reissue_recovery_key<F>(save: F) -> Result<(), String>
+where
+    F: FnOnce(&str) -> Result<(), String>,
+{
+    let mut database = database()?;
+    let mut key_file = database.key_file.clone().ok_or_else(|| NOT_ENCRYPTED_ERROR.to_string())?;
+    let data_key = database.data_key.as_ref().ok_or_else(|| LOCKED_ERROR.to_string())?;
+    let recovery_key = key_file.reissue_recovery_key(data_key)?;
+    save(&recovery_key)?;
+    encryption::write_key_file(&database.key_path, &key_file)?;
+    database.key_file = Some(key_file);
+    database.last_used = Instant::now();
+    Ok(())
+}
+
+// Close the database and forget the key until the next unlock
+pub fn lock() -> Result<(), String> {
+    let mut database = database()?;
+    if database.key_file.is_none() {
+        return Err(NOT_ENCRYPTED_ERROR.to_string());
+    }
+    database.connection = None;
+    database.data_key = None;
+    Ok(())
+}
+
+// Lock an unlocked encrypted store that has not been used for `idle`. Returns whether it locked.
+pub fn lock_if_idle(idle: Duration) -> bool {
+    let Ok(mut database) = database() else {
+        return false;
+    };
+    if database.key_file.is_none() || database.connection.is_none() || database.last_used.elapsed() < idle {
+        return false;
+    }
+    database.connection = None;
+    database.data_key = None;
+    true
+}
+
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+fn page_bounds(offset: Option<u32>, limit: Option<u32>) -> (u32, u32) {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    (offset.unwrap_or(0), limit)
+}
+
+fn clean_title(title: Option<String>) -> String {
+    title
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| DEFAULT_TITLE.to_string())
+}
+
+fn to_json_text(value: &Option<Value>) -> Result<Option<String>, String> {
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => serde_json::to_string(value).map(Some).map_err(json_error),
+    }
+}
+
+fn from_json_text(text: Option<String>) -> Option<Value> {
+    text.and_then(|text| serde_json::from_str(&text).ok())
+}
+
+const SUMMARY_COLUMNS: &str = "c.id, c.title, c.created_at, c.updated_at,
+    (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id)";
+
+fn summary_from_row(row: &Row) -> rusqlite::Result<ConversationSummary> {
+    Ok(ConversationSummary {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        created_at: row.get(2)?,
+        updated_at: row.get(3)?,
+        message_count: row.get(4)?,
+    })
+}
+
+const MESSAGE_COLUMNS: &str = "id, conversation_id, role, content, model, parameters, metadata, created_at, parent_id, pinned";
+
+fn message_from_row(row: &Row) -> rusqlite::Result<Message> {
+    Ok(Message {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        role: row.get(2)?,
+        content: row.get(3)?,
+        model: row.get(4)?,
+        parameters: from_json_text(row.get(5)?),
+        metadata: from_json_text(row.get(6)?),
+        created_at: row.get(7)?,
+        parent_id: row.get(8)?,
+        pinned: row.get(9)?,
+    })
+}
+
+fn summary(connection: &Connection, id: &str) -> Result<ConversationSummary, String> {
+    connection
+        .query_row(
+            &format!("SELECT {} FROM conversations c WHERE c.id = ?1", SUMMARY_COLUMNS),
+            params![id],
+            summary_from_row,
+        )
+        .optional()
+        .map_err(sql_error)?
+        .ok_or_else(|| format!("Conversation '{}' does not exist", id))
+}
+
+fn message(connection: &Connection, id: &str) -> Result<Message, String> {
+    connection
+        .query_row(
+            &format!("SELECT {} FROM messages WHERE id = ?1", MESSAGE_COLUMNS),
+            params![id],
+            message_from_row,
+        )
+        .optional()
+        .map_err(sql_error)?
+        .ok_or_else(|| format!("Message '{}' does not exist", id))
+}
+
+fn touch(connection: &Connection, conversation_id: &str, at: i64) -> Result<(), String> {
+    connection
+        .execute(
+            "UPDATE conversations SET updated_at = MAX(updated_at, ?2) WHERE id = ?1",
+            params![conversation_id, at],
+        )
+        .map_err(sql_error)?;
+    Ok(())
+}
+
+fn active_leaf(connection: &Connection, conversation_id: &str) -> Result<Option<String>, String> {
+    connection
+        .query_row(
+            "SELECT active_leaf_id FROM conversations WHERE id = ?1",
+            params![conversation_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(sql_error)?
+        .ok_or_else(|| format!("Conversation '{}' does not exist", conversation_id))
+}
+
+fn options(connection: &Connection, conversation_id: &str) -> Result<GenerationOptions, String> {
+    let text: Option<String> = connection
+        .query_row(
+            "SELECT options FROM conversations WHERE id = ?1",
+            params![conversation_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(sql_error)?
+        .ok_or_else(|| format!("Conversation '{}' does not exist", conversation_id))?;
+    Ok(from_json_text(text)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default())
+}
+
+fn set_options(connection: &Connection, conversation_id: &str, options: &GenerationOptions) -> Result<(), String> {
+    let text = serde_json::to_string(options)
+        .map_err(|e| format!("Failed to serialize generation options: {}", e))?;
+    connection
+        .execute(
+            "UPDATE conversations SET options = ?2 WHERE id = ?1",
+            params![conversation_id, text],
+        )
+        .map_err(sql_error)?;
+    Ok(())
+}
+
+fn model_and_persona(connection: &Connection, conversation_id: &str) -> Result<(Option<String>, Option<String>), String> {
+    connection
+        .query_row(
+            "SELECT model, persona_id FROM conversations WHERE id = ?1",
+            params![conversation_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(sql_error)?
+        .ok_or_else(|| format!("Conversation '{}' does not exist", conversation_id))
+}
+
+fn set_active_leaf(connection: &Connection, conversation_id: &str, leaf: Option<&str>) -> Result<(), String> {
+    connection
+        .execute(
+            "UPDATE conversations SET active_leaf_id = ?2 WHERE id = ?1",
+            params![conversation_id, leaf],
+        )
+        .map_err(sql_error)?;
+    Ok(())
+}
+
+// Messages from the root down to `leaf`
+fn path(connection: &Connection, leaf: &str) -> Result<Vec<Message>, String> {
+    let columns: Vec<String> = MESSAGE_COLUMNS.split(", ").map(|c| format!("m.{}", c)).collect();
+    let mut statement = connection
+        .prepare(&format!(
+            "WITH RECURSIVE path (id, depth) AS (
+                 SELECT ?1, 0
+                 UNION ALL
+                 SELECT m.parent_id, path.depth + 1 FROM messages m JOIN path ON m.id = path.id
+                 WHERE m.parent_id IS NOT NULL
+             )
+             SELECT {} FROM path JOIN messages m ON m.id = path.id ORDER BY path.depth DESC",
+            columns.join(", ")
+        ))
+        .map_err(sql_error)?;
+    statement
+        .query_map(params![leaf], message_from_row)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(sql_error)
+}
+
+fn active_path(connection: &Connection, conversation_id: &str) -> Result<Vec<Message>, String> {
+    match active_leaf(connection, conversation_id)? {
+        Some(leaf) => path(connection, &leaf),
+        None => Ok(Vec::new()),
+    }
+}
+
+// Follow the most recent reply down from `from` until reaching a leaf
+fn latest_leaf(connection: &Connection, from: &str) -> Result<String, String> {
+    let mut current = from.to_string();
+    loop {
+        let child: Option<String> = connection
+            .query_row(
+                "SELECT id FROM messages WHERE parent_id = ?1 ORDER BY position DESC LIMIT 1",
+                params![current],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sql_error)?;
+        match child {
+            Some(child) => current = child,
+            None => return Ok(current),
+        }
+    }
+}
+
+// The newest root-level message's latest leaf, for when the active one is gone
+fn fallback_leaf(connection: &Connection, conversation_id: &str) -> Result<Option<String>, String> {
+    let root: Option<String> = connection
+        .query_row(
+            "SELECT id FROM messages WHERE conversation_id = ?1 AND parent_id IS NULL ORDER BY position DESC LIMIT 1",
+            params![conversation_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(sql_error)?;
+    root.map(|root| latest_leaf(connection, &root)).transpose()
+}
+
+fn conversation(connection: &Connection, id: &str, messages: Vec<Message>) -> Result<Conversation, String> {
+    let summary = summary(connection, id)?;
+    let (model, persona_id) = model_and_persona(connection, id)?;
+    Ok(Conversation {
+        id: summary.id,
+        title: summary.title,
+        created_at: summary.created_at,
+        updated_at: summary.updated_at,
+        active_leaf_id: active_leaf(connection, id)?,
+        model,
+        persona_id,
+        options: options(connection, id)?,
+        messages,
+    })
+}
+
+// Conversations, most recently updated first
+pub fn list(offset: Option<u32>, limit: Option<u32>) -> Result<Page<ConversationSummary>, String> {
+    let (offset, limit) = page_bounds(offset, limit);
+    with_connection(|connection| {
+        let total: u64 = connection
+            .query_row("SELECT COUNT(*) FROM conversations", [], |row| row.get(0))
+            .map_err(sql_error)?;
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT {} FROM conversations c ORDER BY c.updated_at DESC, c.id LIMIT ?1 OFFSET ?2",
+                SUMMARY_COLUMNS
+            ))
+            .map_err(sql_error)?;
+        let items = statement
+            .query_map(params![limit, offset], summary_from_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(sql_error)?;
+        Ok(Page { items, total, offset, limit })
+    })
+}
+
+// A conversation with the messages of its active branch
+pub fn get(id: &str) -> Result<Conversation, String> {
+    with_connection(|connection| {
+        let messages = active_path(connection, id)?;
+        conversation(connection, id, messages)
+    })
+}
+
+// A conversation with every message of every branch, parents before children
+pub fn get_tree(id: &str) -> Result<Conversation, String> {
+    with_connection(|connection| {
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT {} FROM messages WHERE conversation_id = ?1 ORDER BY position",
+                MESSAGE_COLUMNS
+            ))
+            .map_err(sql_error)?;
+        let messages = statement
+            .query_map(params![id], message_from_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(sql_error)?;
+        conversation(connection, id, messages)
+    })
+}
+
+pub fn create(title: Option<String>) -> Result<ConversationSummary, String> {
+    with_connection(|connection| {
+        let id = insert_conversation(connection, title, None, None)?;
+        summary(connection, &id)
+    })
+}
+
+fn insert_conversation(connection: &Connection, title: Option<String>, model: Option<&str>, persona_id: Option<&str>) -> Result<String, String> {
+    let id = new_id();
+    connection
+        .execute(
+            "INSERT INTO conversations (id, title, created_at, updated_at, model, persona_id) VALUES (?1, ?2, ?3, ?3, ?4, ?5)",
+            params![id, clean_title(title), now_millis(), model, persona_id],
+        )
+        .map_err(sql_error)?;
+    Ok(id)
+}
+
+// A new conversation set up from a persona: its model, options and the persona it came from
+pub fn create_from_persona(title: Option<String>, persona_id: &str, model: &str, options: GenerationOptions) -> Result<Conversation, String> {
+    options.validate()?;
+    with_connection(|connection| {
+        let id = insert_conversation(connection, title, Some(model), Some(persona_id))?;
+        set_options(connection, &id, &options)?;
+        conversation(connection, &id, Vec::new())
+    })
+}
+
+pub fn model(id: &str) -> Result<Option<String>, String> {
+    with_connection(|connection| Ok(model_and_persona(connection, id)?.0))
+}
+
+// Change the model later requests default to; None leaves it to the request
+pub fn set_model(id: &str, model: Option<String>) -> Result<ConversationSummary, String> {
+    let model = model.map(|m| m.trim().to_string()).filter(|m| !m.is_empty());
+    with_connection(|connection| {
+        let changed = connection
+            .execute("UPDATE conversations SET model = ?2 WHERE id = ?1", params![id, model])
+            .map_err(sql_error)?;
+        if changed == 0 {
+            return Err(format!("Conversation '{}' does not exist", id));
+        }
+        summary(connection, id)
+    })
+}
+
+pub fn generation_options(id: &str) -> Result<GenerationOptions, String> {
+    with_connection(|connection| options(connection, id))
+}
+
+// Validate and store the options every chat in this conversation is sent with
+pub fn set_generation_options(id: &str, options: GenerationOptions) -> Result<GenerationOptions, String> {
+    options.validate()?;
+    with_connection(|connection| {
+        self::options(connection, id)?;
+        set_options(connection, id, &options)?;
+        touch(connection, id, now_millis())?;
+        Ok(options)
+    })
+}
+
+pub fn rename(id: &str, title: String) -> Result<ConversationSummary, String> {
+    with_connection(|connection| {
+        let changed = connection
+            .execute(
+                "UPDATE conversations SET title = ?2, updated_at = ?3 WHERE id = ?1",
+                params![id, clean_title(Some(title)), now_millis()],
+            )
+            .map_err(sql_error)?;
+        if changed == 0 {
+            return Err(format!("Conversation '{}' does not exist", id));
+        }
+        summary(connection, id)
+    })
+}
+
+// Messages go with their conversation (ON DELETE CASCADE)
+pub fn delete(id: &str) -> Result<(), String> {
+    with_connection(|connection| {
+        let changed = connection
+            .execute("DELETE FROM conversations WHERE id = ?1", params![id])
+            .map_err(sql_error)?;
+        if changed == 0 {
+            return Err(format!("Conversation '{}' does not exist", id));
+        }
+        Ok(())
+    })
+}
+
+// Returns how many conversations were removed
+pub fn delete_all() -> Result<usize, String> {
+    with_connection(|connection| connection.execute("DELETE FROM conversations", []).map_err(sql_error))
+}
+
+// Messages of the active branch, oldest first
+pub fn messages(conversation_id: &str, offset: Option<u32>, limit: Option<u32>) -> Result<Page<Message>, String> {
+    let (offset, limit) = page_bounds(offset, limit);
+    with_connection(|connection| {
+        let path = active_path(connection, conversation_id)?;
+        let total = path.len() as u64;
+        let items = path.into_iter().skip(offset as usize).take(limit as usize).collect();
+        Ok(Page { items, total, offset, limit })
+    })
+}
+
+// Insert a message under `parent` (None for a new root) and make it the active leaf
+fn insert_message(connection: &Connection, conversation_id: &str, message: NewMessage, parent: Option<&str>) -> Result<Message, String> {
+    if !ROLES.contains(&message.role.as_str()) {
+        return Err(format!("Unknown message role '{}'", message.role));
+    }
+    let id = message.id.filter(|id| !id.trim().is_empty()).unwrap_or_else(new_id);
+    let created_at = message.created_at.unwrap_or_else(now_millis);
+
+    connection
+        .execute(
+            "INSERT INTO messages (id, conversation_id, position, role, content, model, parameters, metadata, created_at, parent_id, pinned)
+             VALUES (?1, ?2, (SELECT COALESCE(MAX(position), -1) + 1 FROM messages WHERE conversation_id = ?2), ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                id,
+                conversation_id,
+                message.role,
+                message.content,
+                message.model,
+                to_json_text(&message.parameters)?,
+                to_json_text(&message.metadata)?,
+                created_at,
+                parent,
+                message.pinned,
+            ],
+        )
+        .map_err(sql_error)?;
+    set_active_leaf(connection, conversation_id, Some(&id))?;
+    touch(connection, conversation_id, created_at)?;
+    self::message(connection, &id)
+}
+
+// The parent a new message gets: the given one if it belongs to the conversation, else the active leaf
+fn attach_point(connection: &Connection, conversation_id: &str, parent_id: Option<&str>) -> Result<Option<String>, String> {
+    match parent_id {
+        Some(parent_id) => {
+            let parent = message(connection, parent_id)?;
+            if parent.conversation_id != conversation_id {
+                return Err(format!("Message '{}' belongs to another conversation", parent_id));
+            }
+            Ok(Some(parent.id))
+        }
+        None => active_leaf(connection, conversation_id),
+    }
+}
+
+// Add a message at the end of the active branch, or under `parent_id`.
+// Regenerating a reply adds the new reply under the same user message as the old one.
+pub fn add_message(conversation_id: &str, message: NewMessage) -> Result<Message, String> {
+    with_connection(|connection| {
+        let parent = attach_point(connection, conversation_id, message.parent_id.as_deref())?;
+        insert_message(connection, conversation_id, message, parent.as_deref())
+    })
+}
+
+// Replace the content and/or metadata of a message in place, e.g. once a streamed reply has finished
+pub fn update_message(id: &str, content: Option<String>, metadata: Option<Value>) -> Result<Message, String> {
+    with_connection(|connection| {
+        let existing = message(connection, id)?;
+        let content = content.unwrap_or(existing.content);
+        let metadata = match metadata {
+            Some(metadata) => to_json_text(&Some(metadata))?,
+            None => to_json_text(&existing.metadata)?,
+        };
+        connection
+            .execute(
+                "UPDATE messages SET content = ?2, metadata = ?3 WHERE id = ?1",
+                params![id, content, metadata],
+            )
+            .map_err(sql_error)?;
+        touch(connection, &existing.conversation_id, now_millis())?;
+        message(connection, id)
+    })
+}
+
+// Pin or unpin a message; pinned messages survive context trimming
+pub fn set_pinned(id: &str, pinned: bool) -> Result<Message, String> {
+    with_connection(|connection| {
+        connection
+            .execute("UPDATE messages SET pinned = ?2 WHERE id = ?1", params![id, pinned])
+            .map_err(sql_error)?;
+        message(connection, id)
+    })
+}
+
+// Edit a message by adding the new text as a sibling branch; the original and its replies stay reachable
+pub fn edit_message(id: &str, content: String) -> Result<Message, String> {
+    with_connection(|connection| {
+        let original = message(connection, id)?;
+        let edited = NewMessage {
+            role: original.role,
+            content,
+            model: original.model,
+            parameters: original.parameters,
+            pinned: original.pinned,
+            ..NewMessage::default()
+        };
+        insert_message(connection, &original.conversation_id, edited, original.parent_id.as_deref())
+    })
+}
+
+// A message together with its siblings
+pub fn branches(message_id: &str) -> Result<Branches, String> {
+    with_connection(|connection| {
+        let target = message(connection, message_id)?;
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT {} FROM messages WHERE conversation_id = ?1 AND parent_id IS ?2 ORDER BY position",
+                MESSAGE_COLUMNS
+            ))
+            .map_err(sql_error)?;
+        let siblings = statement
+            .query_map(params![target.conversation_id, target.parent_id], message_from_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(sql_error)?;
+
+        let on_path: Vec<String> = active_path(connection, &target.conversation_id)?
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        let active_index = siblings.iter().position(|s| on_path.contains(&s.id));
+        Ok(Branches { parent_id: target.parent_id, siblings, active_index })
+    })
+}
+
+// Make the branch through `message_id` active, continuing down its most recent replies
+pub fn switch_branch(message_id: &str) -> Result<Conversation, String> {
+    with_connection(|connection| {
+        let target = message(connection, message_id)?;
+        let leaf = latest_leaf(connection, &target.id)?;
+        set_active_leaf(connection, &target.conversation_id, Some(&leaf))?;
+        let messages = path(connection, &leaf)?;
+        conversation(connection, &target.conversation_id, messages)
+    })
+}
+
+// The /api/chat message array for the active branch, or for the branch ending at `leaf_id`
+// (e.g. the user message whose reply is being regenerated)
+pub fn chat_messages(conversation_id: &str, leaf_id: Option<&str>) -> Result<Vec<crate::provider::ChatMessage>, String> {
+    with_connection(|connection| {
+        let messages = match leaf_id {
+            Some(leaf_id) => {
+                let leaf = message(connection, leaf_id)?;
+                if leaf.conversation_id != conversation_id {
+                    return Err(format!("Message '{}' belongs to another conversation", leaf_id));
+                }
+                path(connection, leaf_id)?
+            }
+            None => active_path(connection, conversation_id)?,
+        };
+        Ok(messages
+            .into_iter()
+            .map(|m| crate::provider::ChatMessage { role: m.role, content: m.content, pinned: m.pinned })
+            .collect())
+    })
+}
+
+// Delete a message and every reply below it
+pub fn delete_message(id: &str) -> Result<(), String> {
+    with_connection(|connection| {
+        let target = message(connection, id)?;
+        let on_path = active_path(connection, &target.conversation_id)?.iter().any(|m| m.id == target.id);
+
+        connection
+            .execute("DELETE FROM messages WHERE id = ?1", params![id])
+            .map_err(sql_error)?;
+
+        // The active branch lost its tail; continue from the parent, or another root if there is none
+        if on_path {
+            let leaf = match &target.parent_id {
+                Some(parent) => Some(latest_leaf(connection, parent)?),
+                None => fallback_leaf(connection, &target.conversation_id)?,
+            };
+            set_active_leaf(connection, &target.conversation_id, leaf.as_deref())?;
+        }
+        Ok(())
+    })
+}
+
+// Write imported conversations in a single transaction, keeping their original timestamps and branches.
+// Message ids are always generated here; ids from other apps may clash with ours.
+pub fn insert_conversations(conversations: Vec<NewConversation>) -> Result<Vec<ConversationSummary>, String> {
+    with_connection(|connection| {
+        let transaction = connection.transaction().map_err(sql_error)?;
+        let mut inserted = Vec::new();
+        for conversation in conversations {
+            let id = new_id();
+            transaction
+                .execute(
+                    "INSERT INTO conversations (id, title, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+                    params![id, clean_title(Some(conversation.title)), conversation.created_at, conversation.updated_at],
+                )
+                .map_err(sql_error)?;
+            let mut ids: HashMap<String, String> = HashMap::new();
+            for message in conversation.messages {
+                let parent = message.parent_id.as_ref().and_then(|key| ids.get(key)).cloned();
+                let key = message.id.clone();
+                let inserted = insert_message(&transaction, &id, NewMessage { id: None, parent_id: None, ..message }, parent.as_deref())?;
+                if let Some(key) = key {
+                    ids.insert(key, inserted.id);
+                }
+            }
+            if let Some(leaf) = conversation.active_leaf.as_ref().and_then(|key| ids.get(key)) {
+                set_active_leaf(&transaction, &id, Some(leaf))?;
+            }
+            if conversation.options != GenerationOptions::default() {
+                set_options(&transaction, &id, &conversation.options)?;
+            }
+            // Adding messages moves updated_at forward; keep the source's value
+            transaction
+                .execute(
+                    "UPDATE conversations SET updated_at = ?2 WHERE id = ?1",
+                    params![id, conversation.updated_at],
+                )
+                .map_err(sql_error)?;
+            inserted.push(summary(&transaction, &id)?);
+        }
+        transaction.commit().map_err(sql_error)?;
+        Ok(inserted)
+    })
+}
+
+// Hash of a message sequence (role and trimmed content), used to recognise the same chat on import
+pub fn fingerprint<'a, I>(messages: I) -> String
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut hasher = Sha256::new();
+    for (role, content) in messages {
+        hasher.update(role.as_bytes());
+        hasher.update([0]);
+        hasher.update(content.trim().as_bytes());
+        hasher.update([0x1e]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+// Fingerprints of every stored conversation's active branch, mapped to the conversation they belong to
+pub fn fingerprints() -> Result<HashMap<String, ConversationSummary>, String> {
+    with_connection(|connection| {
+        let mut statement = connection
+            .prepare(&format!("SELECT {} FROM conversations c", SUMMARY_COLUMNS))
+            .map_err(sql_error)?;
+        let summaries = statement
+            .query_map([], summary_from_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(sql_error)?;
+
+        let mut fingerprints = HashMap::new();
+        for summary in summaries {
+            let messages = active_path(connection, &summary.id)?;
+            let key = fingerprint(messages.iter().map(|m| (m.role.as_str(), m.content.as_str())));
+            fingerprints.insert(key, summary);
+        }
+        Ok(fingerprints)
+    })
+}
+
+// Dates in localStorage went through JSON.stringify, i.e. Date.toISOString: 2025-07-01T12:34:56.789Z.
+// Numbers are taken as milliseconds already.
+pub fn parse_js_date(value: Option<&Value>) -> Option<i64> {
+    let text = match value? {
+        Value::Number(n) => return n.as_i64(),
+        Value::String(s) => s.trim(),
+        _ => return None,
+    };
+    let (date, time) = text.strip_suffix('Z')?.split_once('T')?;
+    let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date_parts.next()??, date_parts.next()??, date_parts.next()??);
+    let (clock, fraction) = time.split_once('.').unwrap_or((time, "0"));
+    let mut clock_parts = clock.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let (hour, minute, second) = (clock_parts.next()??, clock_parts.next()??, clock_parts.next()??);
+    let millis: i64 = format!("{:0<3}", fraction).get(..3)?.parse().ok()?;
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    Some(((days * 24 + hour) * 60 + minute) * 60_000 + second * 1000 + millis)
+}
+
+fn json_string(object: &Map<String, Value>, key: &str) -> Option<String> {
+    match object.get(key)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+// Fields of a stored frontend message worth keeping; display state such as hasAnimated is dropped
+const IMPORTED_MESSAGE_FIELDS: [(&str, &str); 2] = [("verboseInfo", "verbose_info"), ("webSearchSources", "web_search_sources")];
+
+fn imported_message(object: &Map<String, Value>, fallback_time: i64) -> Option<NewMessage> {
+    let role = json_string(object, "role").filter(|role| ROLES.contains(&role.as_str()))?;
+    let metadata: Map<String, Value> = IMPORTED_MESSAGE_FIELDS
+        .iter()
+        .filter_map(|(from, to)| object.get(*from).filter(|v| !v.is_null()).map(|v| (to.to_string(), v.clone())))
+        .collect();
+
+    Some(NewMessage {
+        id: json_string(object, "id"),
+        parent_id: None,
+        role,
+        content: json_string(object, "content").unwrap_or_default(),
+        model: json_string(object, "model"),
+        parameters: None,
+        metadata: Some(Value::Object(metadata)).filter(|m| m.as_object().is_some_and(|m| !m.is_empty())),
+        created_at: Some(parse_js_date(object.get("timestamp")).unwrap_or(fallback_time)),
+        pinned: false,
+    })
+}
+
+// One-time import of the webview's `ollama-conversations` localStorage value.
+// Runs at most once per database; ids that already exist are skipped, so nothing is duplicated.
+pub fn import_local_storage(data: &str) -> Result<ImportSummary, String> {
+    let parsed: Value = serde_json::from_str(data)
+        .map_err(|e| format!("Stored conversations are not valid JSON: {}", e))?;
+    let entries = parsed
+        .as_array()
+        .ok_or_else(|| "Stored conversations must be a JSON array".to_string())?;
+
+    with_connection(|connection| {
+        let done: Option<String> = connection
+            .query_row(
+                "SELECT value FROM app_state WHERE key = ?1",
+                params![LOCAL_STORAGE_IMPORT_KEY],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sql_error)?;
+        if done.is_some() {
+            return Ok(ImportSummary { already_imported: true, ..ImportSummary::default() });
+        }
+
+        let transaction = connection.transaction().map_err(sql_error)?;
+        let mut summary = ImportSummary::default();
+        let now = now_millis();
+
+        for entry in entries {
+            let Some(object) = entry.as_object() else {
+                summary.skipped += 1;
+                continue;
+            };
+            let id = json_string(object, "id").filter(|id| !id.trim().is_empty()).unwrap_or_else(new_id);
+            let raw_messages = object.get("messages").and_then(|m| m.as_array()).cloned().unwrap_or_default();
+            let updated_at = parse_js_date(object.get("updatedAt")).unwrap_or(now);
+            // Conversations never stored a creation time; the first message is the closest thing
+            let created_at = raw_messages
+                .iter()
+                .filter_map(|m| parse_js_date(m.get("timestamp")))
+                .min()
+                .unwrap_or(updated_at)
+                .min(updated_at);
+
+            let inserted = transaction
+                .execute(
+                    "INSERT OR IGNORE INTO conversations (id, title, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+                    params![id, clean_title(json_string(object, "title")), created_at, updated_at],
+                )
+                .map_err(sql_error)?;
+            if inserted == 0 {
+                summary.skipped += 1;
+                continue;
+            }
+            summary.conversations += 1;
+
+            for raw in &raw_messages {
+                let Some(mut message) = raw.as_object().and_then(|m| imported_message(m, updated_at)) else {
+                    summary.skipped += 1;
+                    continue;
+                };
+                // The frontend used Date.now() for ids, so two messages can share one
+                let exists = match &message.id {
+                    Some(id) => transaction
+                        .query_row("SELECT 1 FROM messages WHERE id = ?1", params![id], |_| Ok(()))
+                        .optional()
+                        .map_err(sql_error)?
+                        .is_some(),
+                    None => false,
+                };
+                if exists {
+                    message.id = None;
+                }
+                let parent = active_leaf(&transaction, &id)?;
+                insert_message(&transaction, &id, message, parent.as_deref())?;
+                summary.messages += 1;
+            }
+            // Adding messages moves updated_at forward; keep the time the frontend recorded
+            transaction
+                .execute("UPDATE conversations SET updated_at = ?2 WHERE id = ?1", params![id, updated_at])
+                .map_err(sql_error)?;
+        }
+
+        transaction
+            .execute(
+                "INSERT INTO app_state (key, value) VALUES (?1, ?2)",
+                params![LOCAL_STORAGE_IMPORT_KEY, now.to_string()],
+            )
+            .map_err(sql_error)?;
+        transaction.commit().map_err(sql_error)?;
+
+        println!(
+            "Imported {} conversations ({} messages) from localStorage, skipped {}",
+            summary.conversations, summary.messages, summary.skipped
+        );
+        Ok(summary)
+    })
+}
+
+// Markers around matched terms in FTS snippets; control characters never appear in typed text
+const HIGHLIGHT_START: char = '\u{2}';
+const HIGHLIGHT_END: char = '\u{3}';
+const SNIPPET_TOKENS: i64 = 24;
+
+// Turn what the user typed into an FTS5 query: every word must appear, the last one may be
+// incomplete. Words are quoted so punctuation and FTS operators are searched for literally.
+fn match_expression(query: &str) -> Option<String> {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+    let last = words.last()?.clone();
+    let mut terms = words[..words.len() - 1].to_vec();
+    terms.push(format!("{}*", last));
+    Some(terms.join(" "))
+}
+
+fn snippet_parts(snippet: &str) -> Vec<SnippetPart> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut highlight = false;
+    for c in snippet.chars() {
+        if c == HIGHLIGHT_START || c == HIGHLIGHT_END {
+            if !text.is_empty() {
+                parts.push(SnippetPart { text: std::mem::take(&mut text), highlight });
+            }
+            highlight = c == HIGHLIGHT_START;
+        } else {
+            text.push(c);
+        }
+    }
+    if !text.is_empty() {
+        parts.push(SnippetPart { text, highlight });
+    }
+    parts
+}
+
+// Ranked full-text search over every stored message
+pub fn search(query: &str, filters: SearchFilters, offset: Option<u32>, limit: Option<u32>) -> Result<Page<SearchHit>, String> {
+    // Also refused while locked when the query is empty and nothing would be looked up
+    with_connection(|_| Ok(()))?;
+    let (offset, limit) = page_bounds(offset, limit);
+    let Some(expression) = match_expression(query) else {
+        return Ok(Page { items: Vec::new(), total: 0, offset, limit });
+    };
+    if let Some(role) = &filters.role {
+        if !ROLES.contains(&role.as_str()) {
+            return Err(format!("Unknown message role '{}'", role));
+        }
+    }
+
+    const FILTERS: &str = "message_search MATCH ?1
+        AND (?2 IS NULL OR m.model = ?2)
+        AND (?3 IS NULL OR m.role = ?3)
+        AND (?4 IS NULL OR m.created_at >= ?4)
+        AND (?5 IS NULL OR m.created_at <= ?5)";
+
+    with_connection(|connection| {
+        let filter_params = params![expression, filters.model, filters.role, filters.from, filters.to];
+        let total: u64 = connection
+            .query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM message_search JOIN messages m ON m.seq = message_search.rowid WHERE {}",
+                    FILTERS
+                ),
+                filter_params,
+                |row| row.get(0),
+            )
+            .map_err(sql_error)?;
+
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT m.conversation_id, c.title, m.id, m.role, m.model, m.created_at,
+                        snippet(message_search, 0, ?6, ?7, '…', ?8), bm25(message_search)
+                 FROM message_search
+                 JOIN messages m ON m.seq = message_search.rowid
+                 JOIN conversations c ON c.id = m.conversation_id
+                 WHERE {}
+                 ORDER BY bm25(message_search), m.created_at DESC
+                 LIMIT ?9 OFFSET ?10",
+                FILTERS
+            ))
+            .map_err(sql_error)?;
+        let items = statement
+            .query_map(
+                params![
+                    expression,
+                    filters.model,
+                    filters.role,
+                    filters.from,
+                    filters.to,
+                    HIGHLIGHT_START.to_string(),
+                    HIGHLIGHT_END.to_string(),
+                    SNIPPET_TOKENS,
+                    limit,
+                    offset,
+                ],
+                |row| {
+                    Ok(SearchHit {
+                        conversation_id: row.get(0)?,
+                        conversation_title: row.get(1)?,
+                        message_id: row.get(2)?,
+                        role: row.get(3)?,
+                        model: row.get(4)?,
+                        created_at: row.get(5)?,
+                        snippet: snippet_parts(&row.get::<_, String>(6)?),
+                        rank: row.get(7)?,
+                    })
+                },
+            )
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Search failed: {}", e))?;
+        Ok(Page { items, total, offset, limit })
+    })
+}
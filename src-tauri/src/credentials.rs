@@ -0,0 +1,127 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+use base64::Engine;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use serde::{Deserialize, Serialize};
+
+// Kept next to settings.json but in its own file, so settings can be shared or exported without secrets
+const CREDENTIALS_FILE: &str = "credentials.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthType {
+    #[default]
+    None,
+    Bearer,
+    Basic,
+    Headers,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EndpointCredentials {
+    Bearer { token: String },
+    Basic { username: String, password: String },
+    Headers { headers: BTreeMap<String, String> },
+}
+
+impl EndpointCredentials {
+    pub fn auth_type(&self) -> AuthType {
+        match self {
+            EndpointCredentials::Bearer { .. } => AuthType::Bearer,
+            EndpointCredentials::Basic { .. } => AuthType::Basic,
+            EndpointCredentials::Headers { .. } => AuthType::Headers,
+        }
+    }
+
+    // Headers to attach to every request sent to the endpoint
+    pub fn header_map(&self) -> Result<HeaderMap, String> {
+        let mut headers = HeaderMap::new();
+        match self {
+            EndpointCredentials::Bearer { token } => {
+                headers.insert(AUTHORIZATION, sensitive_value(&format!("Bearer {}", token.trim()))?);
+            }
+            EndpointCredentials::Basic { username, password } => {
+                let encoded = base64::engine::general_purpose::STANDARD
+                    .encode(format!("{}:{}", username, password));
+                headers.insert(AUTHORIZATION, sensitive_value(&format!("Basic {}", encoded))?);
+            }
+            EndpointCredentials::Headers { headers: custom } => {
+                for (name, value) in custom {
+                    let name = HeaderName::from_bytes(name.trim().as_bytes())
+                        .map_err(|_| format!("Invalid header name '{}'", name))?;
+                    headers.insert(name, sensitive_value(value)?);
+                }
+            }
+        }
+        Ok(headers)
+    }
+}
+
+// Header values marked sensitive are left out of reqwest's debug output
+fn sensitive_value(value: &str) -> Result<HeaderValue, String> {
+    let mut value = HeaderValue::from_str(value)
+        .map_err(|_| "Credential contains characters that are not allowed in HTTP headers".to_string())?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+static CREDENTIALS: OnceLock<RwLock<HashMap<String, EndpointCredentials>>> = OnceLock::new();
+static CREDENTIALS_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+fn store() -> &'static RwLock<HashMap<String, EndpointCredentials>> {
+    CREDENTIALS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+// Load stored credentials from the app config directory
+pub fn init(config_dir: PathBuf) -> Result<(), String> {
+    let path = config_dir.join(CREDENTIALS_FILE);
+    let _ = CREDENTIALS_PATH.set(path.clone());
+
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read credentials from {}: {}", path.display(), e))?;
+    let loaded: HashMap<String, EndpointCredentials> = serde_json::from_str(&text)
+        .map_err(|e| format!("Failed to parse credentials from {}: {}", path.display(), e))?;
+
+    *store().write().unwrap_or_else(|e| e.into_inner()) = loaded;
+    Ok(())
+}
+
+pub fn get(endpoint_id: &str) -> Option<EndpointCredentials> {
+    store().read().unwrap_or_else(|e| e.into_inner()).get(endpoint_id).cloned()
+}
+
+// Store or (with None) remove the credentials of an endpoint
+pub fn set(endpoint_id: &str, credentials: Option<EndpointCredentials>) -> Result<(), String> {
+    if let Some(credentials) = &credentials {
+        // Reject values that could never be sent before saving them
+        credentials.header_map()?;
+    }
+
+    let mut guard = store().write().unwrap_or_else(|e| e.into_inner());
+    let mut updated = guard.clone();
+    match credentials {
+        Some(credentials) => updated.insert(endpoint_id.to_string(), credentials),
+        None => updated.remove(endpoint_id),
+    };
+
+    if let Some(path) = CREDENTIALS_PATH.get() {
+        crate::settings::write_json_atomic(path, &updated, true)?;
+    }
+
+    *guard = updated;
+    Ok(())
+}
+
+// Headers for an endpoint, empty when it has no credentials
+pub fn headers_for(endpoint_id: &str) -> Result<HeaderMap, String> {
+    match get(endpoint_id) {
+        Some(credentials) => credentials.header_map(),
+        None => Ok(HeaderMap::new()),
+    }
+}
@@ -0,0 +1,285 @@
+use std::fmt::Write;
+use std::path::Path;
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+// Layout version of the key file kept next to an encrypted conversation database
+pub const KEY_FILE_VERSION: u32 = 1;
+
+// Marker of exported recovery key files
+pub const RECOVERY_FILE_FORMAT: &str = "beautifyollama-recovery-key";
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const MIN_PASSPHRASE_CHARS: usize = 8;
+const MAX_AUTO_LOCK_MINUTES: u32 = 24 * 60;
+
+// Argon2id cost for new key files; the values used are stored, so raising them later keeps old files readable
+const KDF_MEMORY_KIB: u32 = 64 * 1024;
+const KDF_ITERATIONS: u32 = 3;
+const KDF_PARALLELISM: u32 = 1;
+
+// Bound into every wrapped key so one wrapping cannot be passed off as the other
+const PASSPHRASE_AAD: &[u8] = b"beautifyollama conversations: passphrase";
+const RECOVERY_AAD: &[u8] = b"beautifyollama conversations: recovery key";
+
+// The key the database itself is encrypted with. It never changes, so a new passphrase or
+// recovery key only rewraps it instead of re-encrypting every page.
+pub type DataKey = Zeroizing<[u8; KEY_LEN]>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncryptionSettings {
+    // Lock an encrypted conversation store after this long without use; 0 keeps it unlocked until the app quits
+    pub auto_lock_minutes: u32,
+}
+
+impl Default for EncryptionSettings {
+    fn default() -> Self {
+        EncryptionSettings { auto_lock_minutes: 15 }
+    }
+}
+
+impl EncryptionSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.auto_lock_minutes > MAX_AUTO_LOCK_MINUTES {
+            return Err(format!("Auto-lock time must be at most {} minutes", MAX_AUTO_LOCK_MINUTES));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KdfParams {
+    algorithm: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: String,
+}
+
+impl KdfParams {
+    // Current cost settings with a fresh salt
+    fn new() -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        KdfParams {
+            algorithm: "argon2id".to_string(),
+            memory_kib: KDF_MEMORY_KIB,
+            iterations: KDF_ITERATIONS,
+            parallelism: KDF_PARALLELISM,
+            salt: encode(&salt),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WrappedKey {
+    nonce: String,
+    ciphertext: String,
+}
+
+// The data key, wrapped once under the passphrase and once under the recovery key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyFile {
+    version: u32,
+    kdf: KdfParams,
+    passphrase: WrappedKey,
+    recovery: WrappedKey,
+}
+
+#[derive(Debug, Serialize)]
+struct RecoveryKeyFile<'a> {
+    format: &'a str,
+    created_at: i64,
+    recovery_key: &'a str,
+    note: &'a str,
+}
+
+fn encode(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, String> {
+    base64::engine::general_purpose::STANDARD
+        .decode(value)
+        .map_err(|e| format!("Conversation key file is damaged: {}", e))
+}
+
+fn random_key() -> DataKey {
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    OsRng.fill_bytes(&mut key[..]);
+    key
+}
+
+pub fn new_data_key() -> DataKey {
+    random_key()
+}
+
+fn check_passphrase(passphrase: &str) -> Result<(), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(format!("The passphrase must be at least {} characters long", MIN_PASSPHRASE_CHARS));
+    }
+    Ok(())
+}
+
+fn derive(passphrase: &str, kdf: &KdfParams) -> Result<DataKey, String> {
+    if kdf.algorithm != "argon2id" {
+        return Err(format!("Unsupported key derivation '{}' in the conversation key file", kdf.algorithm));
+    }
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(KEY_LEN))
+        .map_err(|e| format!("Invalid key derivation settings in the conversation key file: {}", e))?;
+    let salt = decode(&kdf.salt)?;
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key[..])
+        .map_err(|e| format!("Failed to derive the conversation key: {}", e))?;
+    Ok(key)
+}
+
+fn wrap(wrapping_key: &DataKey, data_key: &DataKey, aad: &[u8]) -> Result<WrappedKey, String> {
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&wrapping_key[..]));
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: &data_key[..], aad })
+        .map_err(|_| "Failed to encrypt the conversation key".to_string())?;
+    Ok(WrappedKey { nonce: encode(&nonce), ciphertext: encode(&ciphertext) })
+}
+
+// None when the wrapping key is wrong (or the file was tampered with)
+fn unwrap(wrapping_key: &DataKey, wrapped: &WrappedKey, aad: &[u8]) -> Result<Option<DataKey>, String> {
+    let nonce = decode(&wrapped.nonce)?;
+    let ciphertext = decode(&wrapped.ciphertext)?;
+    if nonce.len() != XNonce::default().len() {
+        return Err("Conversation key file is damaged: bad nonce length".to_string());
+    }
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&wrapping_key[..]));
+    let Ok(plain) = cipher.decrypt(XNonce::from_slice(&nonce), Payload { msg: &ciphertext, aad }) else {
+        return Ok(None);
+    };
+    let plain = Zeroizing::new(plain);
+    if plain.len() != KEY_LEN {
+        return Err("Conversation key file is damaged: bad key length".to_string());
+    }
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    key.copy_from_slice(&plain);
+    Ok(Some(key))
+}
+
+// Recovery keys are shown as 16 groups of four hex digits so they can be written down
+fn format_recovery_key(key: &DataKey) -> String {
+    key.chunks(2)
+        .map(|pair| format!("{:02X}{:02X}", pair[0], pair[1]))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn parse_recovery_key(text: &str) -> Result<DataKey, String> {
+    let digits: Zeroizing<String> = Zeroizing::new(
+        text.chars().filter(|c| !c.is_whitespace() && *c != '-').collect(),
+    );
+    let invalid = || "That is not a recovery key: expected 64 hex digits".to_string();
+    if digits.len() != KEY_LEN * 2 || !digits.is_ascii() {
+        return Err(invalid());
+    }
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    for (index, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[index * 2..index * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(key)
+}
+
+impl KeyFile {
+    // Wrap a data key for a new passphrase. Returns the key file and the recovery key to show the user.
+    pub fn create(data_key: &DataKey, passphrase: &str) -> Result<(KeyFile, String), String> {
+        check_passphrase(passphrase)?;
+        let recovery_key = random_key();
+        let kdf = KdfParams::new();
+        let passphrase_key = derive(passphrase, &kdf)?;
+        let key_file = KeyFile {
+            version: KEY_FILE_VERSION,
+            passphrase: wrap(&passphrase_key, data_key, PASSPHRASE_AAD)?,
+            recovery: wrap(&recovery_key, data_key, RECOVERY_AAD)?,
+            kdf,
+        };
+        Ok((key_file, format_recovery_key(&recovery_key)))
+    }
+
+    pub fn unlock(&self, passphrase: &str) -> Result<DataKey, String> {
+        let passphrase_key = derive(passphrase, &self.kdf)?;
+        unwrap(&passphrase_key, &self.passphrase, PASSPHRASE_AAD)?
+            .ok_or_else(|| "Wrong passphrase".to_string())
+    }
+
+    pub fn unlock_with_recovery_key(&self, recovery_key: &str) -> Result<DataKey, String> {
+        let recovery_key = parse_recovery_key(recovery_key)?;
+        unwrap(&recovery_key, &self.recovery, RECOVERY_AAD)?
+            .ok_or_else(|| "Wrong recovery key".to_string())
+    }
+
+    // Rewrap under a new passphrase with a fresh salt; the recovery key keeps working
+    pub fn set_passphrase(&mut self, data_key: &DataKey, passphrase: &str) -> Result<(), String> {
+        check_passphrase(passphrase)?;
+        let kdf = KdfParams::new();
+        self.passphrase = wrap(&derive(passphrase, &kdf)?, data_key, PASSPHRASE_AAD)?;
+        self.kdf = kdf;
+        Ok(())
+    }
+
+    // Replace the recovery key; the old one stops working once the key file is written
+    pub fn reissue_recovery_key(&mut self, data_key: &DataKey) -> Result<String, String> {
+        let recovery_key = random_key();
+        self.recovery = wrap(&recovery_key, data_key, RECOVERY_AAD)?;
+        Ok(format_recovery_key(&recovery_key))
+    }
+}
+
+pub fn read_key_file(path: &Path) -> Result<Option<KeyFile>, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let key_file: KeyFile = serde_json::from_str(&text)
+        .map_err(|e| format!("Conversation key file {} is damaged: {}", path.display(), e))?;
+    if key_file.version > KEY_FILE_VERSION {
+        return Err(format!(
+            "{} was written by a newer version (key file format {}, this build reads up to {})",
+            path.display(),
+            key_file.version,
+            KEY_FILE_VERSION
+        ));
+    }
+    Ok(Some(key_file))
+}
+
+pub fn write_key_file(path: &Path, key_file: &KeyFile) -> Result<(), String> {
+    crate::settings::write_json_atomic(path, key_file, true)
+}
+
+// Save a recovery key where the user chose, readable by the owner only
+pub fn export_recovery_key(recovery_key: &str, path: &Path) -> Result<(), String> {
+    let file = RecoveryKeyFile {
+        format: RECOVERY_FILE_FORMAT,
+        created_at: crate::conversations::now_millis(),
+        recovery_key,
+        note: "Unlocks the BeautifyOllama conversation store if the passphrase is lost. Keep it somewhere safe and offline.",
+    };
+    crate::settings::write_json_atomic(path, &file, true)
+}
+
+// SQLCipher's raw key syntax, which skips its own key derivation
+pub fn sqlcipher_key(data_key: &DataKey) -> Zeroizing<String> {
+    let mut key = Zeroizing::new(String::with_capacity(KEY_LEN * 2 + 3));
+    key.push_str("x'");
+    for byte in data_key.iter() {
+        let _ = write!(key, "{:02x}", byte);
+    }
+    key.push('\'');
+    key
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use crate::credentials::AuthType;
+use crate::provider::ProviderKind;
+use crate::tls::TlsOptions;
+
+// Id of the endpoint created for the local Ollama install
+pub const LOCAL_ENDPOINT_ID: &str = "local";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Endpoint {
+    pub id: String,
+    pub name: String,
+    pub scheme: String,
+    pub host: String,
+    pub port: u16,
+    // Optional prefix for servers mounted under a sub-path behind a reverse proxy, e.g. "/ollama"
+    #[serde(default)]
+    pub base_path: String,
+    // Which kind of credentials are stored for this endpoint; the secrets live in credentials.json
+    #[serde(default)]
+    pub auth: AuthType,
+    // CA bundle, pinned fingerprints and insecure mode for https endpoints
+    #[serde(default)]
+    pub tls: TlsOptions,
+    // Ollama, or an OpenAI-compatible server such as llama-server or LM Studio
+    #[serde(default)]
+    pub provider: ProviderKind,
+}
+
+impl Endpoint {
+    pub fn local(port: u16) -> Self {
+        Endpoint {
+            id: LOCAL_ENDPOINT_ID.to_string(),
+            name: "Local".to_string(),
+            scheme: "http".to_string(),
+            host: "localhost".to_string(),
+            port,
+            base_path: String::new(),
+            auth: AuthType::None,
+            tls: TlsOptions::default(),
+            provider: ProviderKind::Ollama,
+        }
+    }
+
+    // Base URL without a trailing slash, e.g. "https://gpu-box:11434/ollama"
+    pub fn base_url(&self) -> String {
+        format!("{}://{}:{}{}", self.scheme, self.url_host(), self.port, normalize_base_path(&self.base_path))
+    }
+
+    pub fn api_url(&self, endpoint: &str) -> String {
+        format!("{}/api/{}", self.base_url(), endpoint)
+    }
+
+    // IPv6 literals need brackets inside URLs
+    fn url_host(&self) -> String {
+        if self.host.contains(':') && !self.host.starts_with('[') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        }
+    }
+
+    // Whether this endpoint points at the machine the app is running on
+    pub fn is_local(&self) -> bool {
+        let host = self.host.trim_start_matches('[').trim_end_matches(']').to_lowercase();
+        host == "localhost" || host == "::1" || host.starts_with("127.")
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.trim().is_empty() {
+            return Err("Endpoint id must not be empty".to_string());
+        }
+        if self.name.trim().is_empty() {
+            return Err("Endpoint name must not be empty".to_string());
+        }
+        if self.scheme != "http" && self.scheme != "https" {
+            return Err(format!("Endpoint '{}': scheme must be http or https", self.name));
+        }
+        if self.host.trim().is_empty() || self.host.contains('/') || self.host.contains(char::is_whitespace) {
+            return Err(format!("Endpoint '{}': invalid host '{}'", self.name, self.host));
+        }
+        if self.port == 0 {
+            return Err(format!("Endpoint '{}': port must be between 1 and 65535", self.name));
+        }
+        if self.base_path.contains(char::is_whitespace) || self.base_path.contains('?') || self.base_path.contains('#') {
+            return Err(format!("Endpoint '{}': invalid base path '{}'", self.name, self.base_path));
+        }
+        self.tls
+            .validate()
+            .map_err(|e| format!("Endpoint '{}': {}", self.name, e))?;
+        Ok(())
+    }
+}
+
+// "ollama/" and "/ollama" both become "/ollama"; an empty path stays empty
+pub fn normalize_base_path(path: &str) -> String {
+    let trimmed = path.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", trimmed)
+    }
+}
+
+// Derive a unique id for a new endpoint or pool from its name
+pub fn unique_id(name: &str, existing_ids: &[String]) -> String {
+    let slug: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let base = if slug.is_empty() { "endpoint".to_string() } else { slug };
+
+    let mut candidate = base.clone();
+    let mut counter = 2;
+    while existing_ids.contains(&candidate) {
+        candidate = format!("{}-{}", base, counter);
+        counter += 1;
+    }
+    candidate
+}
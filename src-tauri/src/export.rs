@@ -0,0 +1,479 @@
+use std::path::Path;
+use std::sync::OnceLock;
+use pulldown_cmark::{html, CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use crate::conversations::{Conversation, Message};
+
+// Marker and layout version of lossless JSON exports
+pub const EXPORT_FILE_FORMAT: &str = "beautifyollama-conversations";
+// 2: messages carry parent ids and every branch is included
+pub const EXPORT_FILE_VERSION: u32 = 2;
+
+// Syntax highlighting theme for code blocks in HTML exports
+const CODE_THEME: &str = "InspiredGitHub";
+
+// Same patterns ThinkingRenderer.tsx recognises in message content
+const THINKING_MARKERS: [(&str, &str); 3] = [
+    ("<think>", "</think>"),
+    ("Thinking...", "...done thinking."),
+    ("[THINKING]", "[/THINKING]"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    // Markdown with YAML front matter; the active branch only
+    Markdown,
+    // Standalone page: styles and highlighted code are inlined, nothing is loaded from the network.
+    // The active branch only.
+    Html,
+    // Everything the database holds including all branches, readable by the importer
+    Json,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportFile {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: i64,
+    pub app_version: String,
+    pub conversations: Vec<Conversation>,
+}
+
+// A piece of message content: either the answer or reasoning shown collapsed
+enum ContentPart<'a> {
+    Text(&'a str),
+    Thinking(&'a str),
+}
+
+// Split content on the earliest thinking marker, repeatedly; an unclosed block runs to the end
+fn content_parts(content: &str) -> Vec<ContentPart<'_>> {
+    let mut parts = Vec::new();
+    let mut rest = content;
+    loop {
+        let next = THINKING_MARKERS
+            .iter()
+            .filter_map(|(open, close)| rest.find(open).map(|index| (index, *open, *close)))
+            .min_by_key(|(index, _, _)| *index);
+        let Some((index, open, close)) = next else {
+            break;
+        };
+        if index > 0 {
+            parts.push(ContentPart::Text(&rest[..index]));
+        }
+        let inner = &rest[index + open.len()..];
+        match inner.find(close) {
+            Some(end) => {
+                parts.push(ContentPart::Thinking(&inner[..end]));
+                rest = &inner[end + close.len()..];
+            }
+            None => {
+                parts.push(ContentPart::Thinking(inner));
+                rest = "";
+            }
+        }
+    }
+    if !rest.is_empty() {
+        parts.push(ContentPart::Text(rest));
+    }
+    parts
+}
+
+// Reasoning the backend stored separately from the content (see provider::ChatResult)
+fn stored_thinking(message: &Message) -> Option<&str> {
+    message
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("thinking"))
+        .and_then(|t| t.as_str())
+        .filter(|t| !t.trim().is_empty())
+}
+
+fn verbose_info(message: &Message) -> Option<&str> {
+    message
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("verbose_info"))
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.trim().is_empty())
+}
+
+fn sources(message: &Message) -> Vec<String> {
+    message
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("web_search_sources"))
+        .and_then(|s| s.as_array())
+        .map(|s| s.iter().filter_map(|v| v.as_str().map(|v| v.to_string())).collect())
+        .unwrap_or_default()
+}
+
+fn models(conversation: &Conversation) -> Vec<String> {
+    let mut models: Vec<String> = Vec::new();
+    for model in conversation.messages.iter().filter_map(|m| m.model.clone()) {
+        if !models.contains(&model) {
+            models.push(model);
+        }
+    }
+    models
+}
+
+fn role_label(role: &str) -> &str {
+    match role {
+        "user" => "User",
+        "assistant" => "Assistant",
+        "system" => "System",
+        other => other,
+    }
+}
+
+// Milliseconds since the epoch as an RFC 3339 UTC time, e.g. 2025-07-01T12:34:56Z
+fn iso_timestamp(millis: i64) -> String {
+    let seconds = millis.div_euclid(1000);
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+
+    // Civil date from days since 1970-01-01 in the proleptic Gregorian calendar
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+// A JSON string is also a valid double-quoted YAML scalar
+fn yaml_string(value: &str) -> String {
+    Value::from(value).to_string()
+}
+
+fn export_title(conversations: &[Conversation]) -> String {
+    match conversations {
+        [single] => single.title.clone(),
+        many => format!("{} conversations", many.len()),
+    }
+}
+
+fn to_markdown(conversations: &[Conversation], exported_at: i64) -> String {
+    let mut out = String::from("---\n");
+    out.push_str(&format!("title: {}\n", yaml_string(&export_title(conversations))));
+    out.push_str(&format!("exported_at: {}\n", iso_timestamp(exported_at)));
+    out.push_str(&format!("app_version: {}\n", yaml_string(crate::CURRENT_VERSION)));
+    out.push_str("conversations:\n");
+    for conversation in conversations {
+        let models: Vec<String> = models(conversation).iter().map(|m| yaml_string(m)).collect();
+        out.push_str(&format!("  - id: {}\n", yaml_string(&conversation.id)));
+        out.push_str(&format!("    title: {}\n", yaml_string(&conversation.title)));
+        out.push_str(&format!("    created_at: {}\n", iso_timestamp(conversation.created_at)));
+        out.push_str(&format!("    updated_at: {}\n", iso_timestamp(conversation.updated_at)));
+        out.push_str(&format!("    models: [{}]\n", models.join(", ")));
+        out.push_str(&format!("    messages: {}\n", conversation.messages.len()));
+    }
+    out.push_str("---\n");
+
+    for conversation in conversations {
+        out.push_str(&format!("\n# {}\n", conversation.title));
+        for message in &conversation.messages {
+            let mut heading = role_label(&message.role).to_string();
+            if let Some(model) = &message.model {
+                heading.push_str(&format!(" ({})", model));
+            }
+            out.push_str(&format!("\n## {} · {}\n\n", heading, iso_timestamp(message.created_at)));
+
+            if let Some(thinking) = stored_thinking(message) {
+                out.push_str(&format!("<details>\n<summary>Thinking</summary>\n\n{}\n\n</details>\n\n", thinking.trim()));
+            }
+            for part in content_parts(&message.content) {
+                match part {
+                    ContentPart::Text(text) if !text.trim().is_empty() => {
+                        out.push_str(text.trim());
+                        out.push_str("\n\n");
+                    }
+                    ContentPart::Text(_) => {}
+                    ContentPart::Thinking(thinking) => {
+                        out.push_str(&format!("<details>\n<summary>Thinking</summary>\n\n{}\n\n</details>\n\n", thinking.trim()));
+                    }
+                }
+            }
+
+            let sources = sources(message);
+            if !sources.is_empty() {
+                out.push_str("**Sources:**\n\n");
+                for source in sources {
+                    out.push_str(&format!("- {}\n", source));
+                }
+                out.push('\n');
+            }
+            if let Some(info) = verbose_info(message) {
+                out.push_str(&format!("<details>\n<summary>Generation stats</summary>\n\n```\n{}\n```\n\n</details>\n\n", info.trim()));
+            }
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Loading the bundled syntax definitions takes a moment, so it happens once
+fn highlighting() -> &'static (SyntaxSet, Theme) {
+    static HIGHLIGHTING: OnceLock<(SyntaxSet, Theme)> = OnceLock::new();
+    HIGHLIGHTING.get_or_init(|| {
+        let mut themes = ThemeSet::load_defaults();
+        let theme = themes.themes.remove(CODE_THEME).unwrap_or_default();
+        (SyntaxSet::load_defaults_newlines(), theme)
+    })
+}
+
+fn highlight_code(code: &str, language: &str) -> String {
+    let (syntaxes, theme) = highlighting();
+    let syntax = syntaxes
+        .find_syntax_by_token(language)
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+    syntect::html::highlighted_html_for_string(code, syntaxes, syntax, theme)
+        .unwrap_or_else(|_| format!("<pre><code>{}</code></pre>", escape_html(code)))
+}
+
+// Links the exported page may keep; anything else (javascript:, data:, file paths) is dropped
+const LINK_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+fn safe_link(destination: &str) -> bool {
+    destination
+        .trim()
+        .split_once(':')
+        .is_some_and(|(scheme, _)| LINK_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()))
+}
+
+// Render message Markdown to HTML. Raw HTML from the model is shown as text, and fenced
+// code blocks are highlighted with inline styles so the page needs no scripts or stylesheets.
+// Links to unsafe destinations keep only their text, and images become links to the image
+// (or their alt text) so opening the file loads nothing from the network.
+fn markdown_to_html(markdown: &str) -> String {
+    let mut events = Vec::new();
+    let mut code: Option<(String, String)> = None;
+    // Whether each open link or image was rendered as a link, for its end event
+    let mut links: Vec<bool> = Vec::new();
+    let mut images: Vec<bool> = Vec::new();
+
+    for event in Parser::new_ext(markdown, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or("").to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((language, String::new()));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((language, text)) = code.take() {
+                    events.push(Event::Html(highlight_code(&text, &language).into()));
+                }
+            }
+            Event::Text(text) if code.is_some() => {
+                if let Some((_, buffer)) = code.as_mut() {
+                    buffer.push_str(&text);
+                }
+            }
+            Event::Html(raw) | Event::InlineHtml(raw) => events.push(Event::Text(raw)),
+            Event::Start(Tag::Link { link_type, dest_url, title, id }) => {
+                let keep = safe_link(&dest_url);
+                if keep {
+                    events.push(Event::Start(Tag::Link { link_type, dest_url, title, id }));
+                }
+                links.push(keep);
+            }
+            Event::End(TagEnd::Link) => {
+                if links.pop().unwrap_or(false) {
+                    events.push(Event::End(TagEnd::Link));
+                }
+            }
+            Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+                // Inside a link the alt text already sits in one; links cannot nest
+                let keep = safe_link(&dest_url) && !links.contains(&true);
+                if keep {
+                    events.push(Event::Start(Tag::Link { link_type, dest_url, title, id }));
+                }
+                images.push(keep);
+            }
+            Event::End(TagEnd::Image) => {
+                if images.pop().unwrap_or(false) {
+                    events.push(Event::End(TagEnd::Link));
+                }
+            }
+            event => events.push(event),
+        }
+    }
+
+    let mut out = String::new();
+    html::push_html(&mut out, events.into_iter());
+    out
+}
+
+const HTML_STYLE: &str = "
+body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; max-width: 860px; margin: 2rem auto; padding: 0 1rem; line-height: 1.6; color: #1f2328; background: #fff; }
+header.export { border-bottom: 1px solid #d0d7de; margin-bottom: 1.5rem; }
+section.conversation { margin-bottom: 3rem; }
+.meta { color: #656d76; font-size: 0.85rem; }
+article.message { border: 1px solid #d0d7de; border-radius: 8px; padding: 0.75rem 1rem; margin: 1rem 0; }
+article.message.user { background: #f6f8fa; }
+article.message > h3 { margin: 0 0 0.5rem; font-size: 0.95rem; }
+details { margin: 0.5rem 0; }
+details > summary { cursor: pointer; color: #656d76; }
+details.thinking > div { border-left: 3px solid #d0d7de; padding-left: 0.75rem; color: #57606a; }
+pre { padding: 0.75rem; border-radius: 6px; overflow-x: auto; font-size: 0.85rem; }
+code { font-family: ui-monospace, SFMono-Regular, Menlo, monospace; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #d0d7de; padding: 0.25rem 0.5rem; }
+";
+
+fn thinking_html(thinking: &str) -> String {
+    format!(
+        "<details class=\"thinking\"><summary>Thinking</summary><div>{}</div></details>\n",
+        markdown_to_html(thinking.trim())
+    )
+}
+
+fn message_html(message: &Message) -> String {
+    let mut out = format!("<article class=\"message {}\">\n<h3>{}", escape_html(&message.role), role_label(&message.role));
+    if let Some(model) = &message.model {
+        out.push_str(&format!(" <span class=\"meta\">{}</span>", escape_html(model)));
+    }
+    out.push_str(&format!(
+        " <time class=\"meta\" datetime=\"{0}\">{0}</time></h3>\n",
+        iso_timestamp(message.created_at)
+    ));
+
+    if let Some(thinking) = stored_thinking(message) {
+        out.push_str(&thinking_html(thinking));
+    }
+    for part in content_parts(&message.content) {
+        match part {
+            ContentPart::Text(text) => out.push_str(&markdown_to_html(text)),
+            ContentPart::Thinking(thinking) => out.push_str(&thinking_html(thinking)),
+        }
+    }
+
+    let sources = sources(message);
+    if !sources.is_empty() {
+        out.push_str("<details><summary>Sources</summary><ol>");
+        for source in sources {
+            let escaped = escape_html(&source);
+            if source.starts_with("http://") || source.starts_with("https://") {
+                out.push_str(&format!("<li><a href=\"{0}\">{0}</a></li>", escaped));
+            } else {
+                out.push_str(&format!("<li>{}</li>", escaped));
+            }
+        }
+        out.push_str("</ol></details>\n");
+    }
+    if let Some(info) = verbose_info(message) {
+        out.push_str(&format!(
+            "<details><summary>Generation stats</summary><pre>{}</pre></details>\n",
+            escape_html(info.trim())
+        ));
+    }
+    if let Some(parameters) = message.parameters.as_ref().filter(|p| p.as_object().is_some_and(|p| !p.is_empty())) {
+        out.push_str(&format!(
+            "<details><summary>Parameters</summary><pre>{}</pre></details>\n",
+            escape_html(&serde_json::to_string_pretty(parameters).unwrap_or_default())
+        ));
+    }
+    out.push_str("</article>\n");
+    out
+}
+
+fn to_html(conversations: &[Conversation], exported_at: i64) -> String {
+    let title = escape_html(&export_title(conversations));
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        title, HTML_STYLE
+    );
+    out.push_str(&format!(
+        "<header class=\"export\"><h1>{}</h1><p class=\"meta\">Exported {} from BeautifyOllama {}</p></header>\n",
+        title,
+        iso_timestamp(exported_at),
+        escape_html(crate::CURRENT_VERSION)
+    ));
+
+    for conversation in conversations {
+        out.push_str(&format!("<section class=\"conversation\" id=\"{}\">\n", escape_html(&conversation.id)));
+        if conversations.len() > 1 {
+            out.push_str(&format!("<h2>{}</h2>\n", escape_html(&conversation.title)));
+        }
+        let models = models(conversation);
+        out.push_str(&format!(
+            "<p class=\"meta\">Started {} · updated {}{}</p>\n",
+            iso_timestamp(conversation.created_at),
+            iso_timestamp(conversation.updated_at),
+            if models.is_empty() { String::new() } else { format!(" · {}", escape_html(&models.join(", "))) }
+        ));
+        for message in &conversation.messages {
+            out.push_str(&message_html(message));
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn to_json(conversations: &[Conversation], exported_at: i64) -> Result<String, String> {
+    let file = ExportFile {
+        format: EXPORT_FILE_FORMAT.to_string(),
+        version: EXPORT_FILE_VERSION,
+        exported_at,
+        app_version: crate::CURRENT_VERSION.to_string(),
+        conversations: conversations.to_vec(),
+    };
+    serde_json::to_string_pretty(&file).map_err(|e| format!("Failed to serialize conversations: {}", e))
+}
+
+// Write the conversations with the given ids to `path` in the given format
+pub fn export_to(ids: &[String], format: ExportFormat, path: &Path) -> Result<usize, String> {
+    if ids.is_empty() {
+        return Err("Select at least one conversation to export".to_string());
+    }
+    let conversations = ids
+        .iter()
+        .map(|id| match format {
+            ExportFormat::Json => crate::conversations::get_tree(id),
+            ExportFormat::Markdown | ExportFormat::Html => crate::conversations::get(id),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let exported_at = crate::conversations::now_millis();
+    let contents = match format {
+        ExportFormat::Markdown => to_markdown(&conversations, exported_at),
+        ExportFormat::Html => to_html(&conversations, exported_at),
+        ExportFormat::Json => to_json(&conversations, exported_at)?,
+    };
+    std::fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(conversations.len())
+}
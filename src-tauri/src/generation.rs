@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use crate::context::MIN_NUM_CTX;
+use crate::provider::{ChatMessage, ChatRequest};
+
+const MAX_NUM_CTX: u64 = 1 << 20;
+const MAX_STOP_SEQUENCES: usize = 16;
+
+// Units Ollama accepts in a keep_alive duration such as "5m" or "1h30m", two-letter ones first
+const DURATION_UNITS: [&str; 6] = ["ns", "us", "ms", "s", "m", "h"];
+
+// Generation options a conversation is sent with; unset values leave the model's defaults alone
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationOptions {
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub top_k: Option<u32>,
+    pub repeat_penalty: Option<f64>,
+    pub seed: Option<i64>,
+    pub num_ctx: Option<u64>,
+    // -1 generates until the model stops, -2 until the context is full
+    pub num_predict: Option<i64>,
+    pub stop: Vec<String>,
+    // How long the model stays loaded afterwards: a duration ("10m", "1h30m"), seconds, or -1 for ever
+    pub keep_alive: Option<String>,
+}
+
+fn check_range(name: &str, value: Option<f64>, min: f64, max: f64) -> Result<(), String> {
+    match value {
+        Some(value) if !value.is_finite() || value < min || value > max => {
+            Err(format!("{} must be between {} and {}", name, min, max))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Whole seconds, or a Go duration made of number-unit pairs, optionally negative
+fn valid_keep_alive(value: &str) -> bool {
+    let value = value.strip_prefix('-').unwrap_or(value);
+    if value.parse::<u64>().is_ok() {
+        return true;
+    }
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        if rest[..number_len].parse::<f64>().is_err() {
+            return false;
+        }
+        rest = &rest[number_len..];
+        let Some(unit) = DURATION_UNITS.iter().find(|unit| rest.starts_with(*unit)) else {
+            return false;
+        };
+        rest = &rest[unit.len()..];
+    }
+    !value.is_empty()
+}
+
+impl GenerationOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        check_range("Temperature", self.temperature, 0.0, 2.0)?;
+        check_range("top_p", self.top_p, 0.0, 1.0)?;
+        check_range("Repeat penalty", self.repeat_penalty, 0.0, 2.0)?;
+        if self.top_k == Some(0) {
+            return Err("top_k must be at least 1".to_string());
+        }
+        if let Some(num_ctx) = self.num_ctx {
+            if !(MIN_NUM_CTX..=MAX_NUM_CTX).contains(&num_ctx) {
+                return Err(format!("num_ctx must be between {} and {}", MIN_NUM_CTX, MAX_NUM_CTX));
+            }
+        }
+        if let Some(num_predict) = self.num_predict {
+            if num_predict == 0 || num_predict < -2 {
+                return Err("num_predict must be positive, -1 (no limit) or -2 (fill the context)".to_string());
+            }
+        }
+        if self.stop.len() > MAX_STOP_SEQUENCES {
+            return Err(format!("At most {} stop sequences are allowed", MAX_STOP_SEQUENCES));
+        }
+        for (index, stop) in self.stop.iter().enumerate() {
+            if stop.is_empty() {
+                return Err("Stop sequences must not be empty".to_string());
+            }
+            if self.stop[..index].contains(stop) {
+                return Err(format!("Stop sequence '{}' is listed twice", stop));
+            }
+        }
+        if let Some(keep_alive) = &self.keep_alive {
+            if !valid_keep_alive(keep_alive.trim()) {
+                return Err(format!("Invalid keep_alive '{}': use seconds or a duration such as 10m or 1h30m", keep_alive));
+            }
+        }
+        Ok(())
+    }
+
+    // The options under Ollama's names
+    pub fn to_options(&self) -> Map<String, Value> {
+        let mut options = Map::new();
+        let mut set = |name: &str, value: Option<Value>| {
+            if let Some(value) = value {
+                options.insert(name.to_string(), value);
+            }
+        };
+        set("temperature", self.temperature.map(|v| json!(v)));
+        set("top_p", self.top_p.map(|v| json!(v)));
+        set("top_k", self.top_k.map(|v| json!(v)));
+        set("repeat_penalty", self.repeat_penalty.map(|v| json!(v)));
+        set("seed", self.seed.map(|v| json!(v)));
+        set("num_ctx", self.num_ctx.map(|v| json!(v)));
+        set("num_predict", self.num_predict.map(|v| json!(v)));
+        set("stop", Some(json!(self.stop)).filter(|_| !self.stop.is_empty()));
+        // Ollama reads a string as a duration and a number as seconds, so plain seconds go out as a number
+        set(
+            "keep_alive",
+            self.keep_alive.as_deref().map(str::trim).map(|v| v.parse::<i64>().map(|s| json!(s)).unwrap_or_else(|_| json!(v))),
+        );
+        options
+    }
+
+    // Fill in what the request leaves unset; the system prompt replaces a leading system message.
+    // Returns whether a system message was put in front of the request's messages.
+    pub fn apply(&self, request: &mut ChatRequest) -> bool {
+        for (name, value) in self.to_options() {
+            request.options.entry(name).or_insert(value);
+        }
+        let Some(system_prompt) = self.system_prompt.as_deref().filter(|p| !p.trim().is_empty()) else {
+            return false;
+        };
+        match request.messages.first_mut() {
+            Some(first) if first.role == "system" => {
+                first.content = system_prompt.to_string();
+                false
+            }
+            _ => {
+                request.messages.insert(0, ChatMessage {
+                    role: "system".to_string(),
+                    content: system_prompt.to_string(),
+                    pinned: false,
+                });
+                true
+            }
+        }
+    }
+}
@@ -0,0 +1,704 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use crate::conversations::{self, NewConversation, NewMessage};
+use crate::export::{ExportFile, EXPORT_FILE_FORMAT};
+use crate::generation::GenerationOptions;
+
+// Characters of the first message shown in the preview
+const PREVIEW_LENGTH: usize = 160;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportSource {
+    // conversations.json from a ChatGPT data export
+    #[serde(rename = "chatgpt")]
+    ChatGpt,
+    // JSON export of one or all chats from Open WebUI
+    #[serde(rename = "open_webui")]
+    OpenWebUi,
+    // One message object per line
+    #[serde(rename = "jsonl")]
+    Jsonl,
+    // This app's own JSON export (export.rs)
+    #[serde(rename = "beautifyollama")]
+    BeautifyOllama,
+}
+
+// A message in the source's tree; `key` and `parent` are the source's own ids
+#[derive(Debug, Clone)]
+struct ImportedMessage {
+    key: String,
+    parent: Option<String>,
+    role: String,
+    content: String,
+    model: Option<String>,
+    parameters: Option<Value>,
+    metadata: Option<Value>,
+    created_at: Option<i64>,
+    pinned: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ImportedConversation {
+    title: String,
+    created_at: Option<i64>,
+    updated_at: Option<i64>,
+    messages: Vec<ImportedMessage>,
+    // Leaf of the branch the source app showed last
+    current: Option<String>,
+    options: GenerationOptions,
+}
+
+impl ImportedConversation {
+    fn by_key(&self) -> HashMap<&str, &ImportedMessage> {
+        self.messages.iter().map(|m| (m.key.as_str(), m)).collect()
+    }
+
+    // Messages nothing else answers, in source order
+    fn leaves(&self) -> Vec<&str> {
+        let parents: HashSet<&str> = self.messages.iter().filter_map(|m| m.parent.as_deref()).collect();
+        self.messages
+            .iter()
+            .map(|m| m.key.as_str())
+            .filter(|key| !parents.contains(key))
+            .collect()
+    }
+
+    // Root-to-leaf messages ending at `leaf`
+    fn path_to(&self, leaf: &str) -> Vec<&ImportedMessage> {
+        let by_key = self.by_key();
+        let mut path = Vec::new();
+        let mut seen = HashSet::new();
+        let mut next = Some(leaf);
+        while let Some(key) = next {
+            // A broken export can contain a parent cycle
+            if !seen.insert(key) {
+                break;
+            }
+            let Some(message) = by_key.get(key) else {
+                break;
+            };
+            path.push(*message);
+            next = message.parent.as_deref();
+        }
+        path.reverse();
+        path
+    }
+
+    fn active_leaf(&self) -> Option<&str> {
+        let leaves = self.leaves();
+        self.current
+            .as_deref()
+            .filter(|current| leaves.contains(current))
+            .or_else(|| leaves.last().copied())
+    }
+
+    // Every message with parents before their replies, siblings in source order.
+    // Messages caught in a parent cycle are unreachable and left out.
+    fn ordered(&self) -> Vec<&ImportedMessage> {
+        let by_key = self.by_key();
+        let mut children: HashMap<Option<&str>, Vec<&ImportedMessage>> = HashMap::new();
+        for message in &self.messages {
+            let parent = message.parent.as_deref().filter(|p| by_key.contains_key(p));
+            children.entry(parent).or_default().push(message);
+        }
+
+        let mut ordered = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack: Vec<&ImportedMessage> = children.get(&None).map(|c| c.iter().rev().copied().collect()).unwrap_or_default();
+        while let Some(message) = stack.pop() {
+            if !seen.insert(message.key.as_str()) {
+                continue;
+            }
+            ordered.push(message);
+            if let Some(replies) = children.get(&Some(message.key.as_str())) {
+                stack.extend(replies.iter().rev().copied());
+            }
+        }
+        ordered
+    }
+
+    // Every branch as a message list, the active one first
+    fn branches(&self) -> Vec<Vec<&ImportedMessage>> {
+        let active = self.active_leaf();
+        let mut branches: Vec<Vec<&ImportedMessage>> = active.map(|leaf| vec![self.path_to(leaf)]).unwrap_or_default();
+        for leaf in self.leaves() {
+            if Some(leaf) != active {
+                branches.push(self.path_to(leaf));
+            }
+        }
+        branches
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Duplicate {
+    // Same messages as a conversation already in the database
+    Existing { conversation_id: String, title: String },
+    // Same messages as an earlier entry of this file
+    InFile { index: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewEntry {
+    pub index: usize,
+    pub title: String,
+    pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    // Messages on the active branch
+    pub message_count: usize,
+    pub branch_count: usize,
+    pub models: Vec<String>,
+    pub first_message: Option<String>,
+    pub duplicate: Option<Duplicate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportPreview {
+    pub source: ImportSource,
+    pub entries: Vec<PreviewEntry>,
+    // Lines or entries that could not be read
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImportResult {
+    pub conversations: usize,
+    pub messages: usize,
+    // Branches besides the active one, kept in the message tree
+    pub branches: usize,
+    pub skipped_duplicates: usize,
+    pub skipped_empty: usize,
+}
+
+// Seconds or milliseconds since the epoch, a numeric string, or an ISO 8601 UTC time
+fn timestamp(value: Option<&Value>) -> Option<i64> {
+    let seconds_or_millis = |v: f64| if v.abs() < 1e11 { (v * 1000.0) as i64 } else { v as i64 };
+    match value? {
+        Value::Number(n) => n.as_f64().map(seconds_or_millis),
+        Value::String(s) => match s.trim().parse::<f64>() {
+            Ok(v) => Some(seconds_or_millis(v)),
+            Err(_) => conversations::parse_js_date(value),
+        },
+        _ => None,
+    }
+}
+
+fn string(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+// Plain text of a content field: a string, or OpenAI-style parts ({"type": "text", "text": ...} or strings)
+fn text_content(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| match part {
+                Value::String(s) => Some(s.clone()),
+                Value::Object(object) => string(object.get("text")),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn normalize_role(role: &str) -> Option<&'static str> {
+    match role.trim().to_lowercase().as_str() {
+        "user" | "human" => Some("user"),
+        "assistant" | "ai" | "bot" | "model" => Some("assistant"),
+        "system" => Some("system"),
+        _ => None,
+    }
+}
+
+// Give each message the nearest ancestor that was kept as its parent.
+// `parents` maps every source node (kept or not) to its parent.
+fn reparent(messages: &mut [ImportedMessage], parents: &HashMap<String, Option<String>>) {
+    let kept: HashSet<String> = messages.iter().map(|m| m.key.clone()).collect();
+    for message in messages.iter_mut() {
+        let mut parent = message.parent.clone();
+        let mut steps = 0;
+        while let Some(key) = parent.clone() {
+            if kept.contains(&key) || steps > parents.len() {
+                break;
+            }
+            parent = parents.get(&key).cloned().flatten();
+            steps += 1;
+        }
+        message.parent = parent.filter(|p| kept.contains(p));
+    }
+}
+
+// Follow `current` up to the nearest kept message
+fn kept_current(current: Option<String>, messages: &[ImportedMessage], parents: &HashMap<String, Option<String>>) -> Option<String> {
+    let kept: HashSet<&str> = messages.iter().map(|m| m.key.as_str()).collect();
+    let mut node = current;
+    let mut steps = 0;
+    while let Some(key) = node.clone() {
+        if kept.contains(key.as_str()) || steps > parents.len() {
+            break;
+        }
+        node = parents.get(&key).cloned().flatten();
+        steps += 1;
+    }
+    node.filter(|key| kept.contains(key.as_str()))
+}
+
+// ChatGPT: a `mapping` of nodes with parent/children links and `current_node` as the visible leaf.
+// Tool calls, hidden system messages and non-text content are left out.
+fn parse_chatgpt(entry: &Map<String, Value>) -> ImportedConversation {
+    let mapping = entry.get("mapping").and_then(|m| m.as_object()).cloned().unwrap_or_default();
+    let mut parents = HashMap::new();
+    let mut messages = Vec::new();
+
+    for (key, node) in &mapping {
+        parents.insert(key.clone(), string(node.get("parent")));
+        let Some(message) = node.get("message").and_then(|m| m.as_object()) else {
+            continue;
+        };
+        let Some(role) = message
+            .get("author")
+            .and_then(|a| a.get("role"))
+            .and_then(|r| r.as_str())
+            .and_then(normalize_role)
+        else {
+            continue;
+        };
+        let metadata = message.get("metadata").and_then(|m| m.as_object());
+        if metadata.and_then(|m| m.get("is_visually_hidden_from_conversation")).and_then(|h| h.as_bool()) == Some(true) {
+            continue;
+        }
+
+        let content = message.get("content");
+        let content_type = content.and_then(|c| c.get("content_type")).and_then(|t| t.as_str()).unwrap_or("text");
+        let text = match content_type {
+            "text" | "multimodal_text" => text_content(content.and_then(|c| c.get("parts"))),
+            "code" => {
+                let language = content.and_then(|c| c.get("language")).and_then(|l| l.as_str()).unwrap_or("");
+                let code = string(content.and_then(|c| c.get("text"))).unwrap_or_default();
+                format!("```{}\n{}\n```", language, code)
+            }
+            _ => String::new(),
+        };
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        messages.push(ImportedMessage {
+            key: key.clone(),
+            parent: string(node.get("parent")),
+            role: role.to_string(),
+            content: text,
+            model: metadata.and_then(|m| string(m.get("model_slug"))),
+            parameters: None,
+            metadata: None,
+            created_at: timestamp(message.get("create_time")),
+            pinned: false,
+        });
+    }
+
+    // The mapping is an object, so restore conversation order
+    messages.sort_by_key(|m| m.created_at.unwrap_or(i64::MAX));
+    reparent(&mut messages, &parents);
+    let current = kept_current(string(entry.get("current_node")), &messages, &parents);
+
+    ImportedConversation {
+        title: string(entry.get("title")).unwrap_or_default(),
+        created_at: timestamp(entry.get("create_time")),
+        updated_at: timestamp(entry.get("update_time")),
+        messages,
+        current,
+        options: GenerationOptions::default(),
+    }
+}
+
+// Open WebUI: `chat.history.messages` holds the tree (parentId) and `chat.history.currentId` the
+// visible leaf. Older exports only have the flat `chat.messages` list.
+fn parse_open_webui(entry: &Map<String, Value>) -> ImportedConversation {
+    let chat = entry.get("chat").and_then(|c| c.as_object()).cloned().unwrap_or_default();
+    let history = chat.get("history").and_then(|h| h.as_object());
+    let tree = history.and_then(|h| h.get("messages")).and_then(|m| m.as_object());
+
+    let mut parents = HashMap::new();
+    let mut messages = Vec::new();
+    let raw: Vec<(String, &Map<String, Value>)> = match tree {
+        Some(tree) => tree
+            .iter()
+            .filter_map(|(key, m)| m.as_object().map(|m| (key.clone(), m)))
+            .collect(),
+        None => chat
+            .get("messages")
+            .and_then(|m| m.as_array())
+            .map(|list| {
+                list.iter()
+                    .enumerate()
+                    .filter_map(|(index, m)| m.as_object().map(|m| (string(m.get("id")).unwrap_or_else(|| index.to_string()), m)))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+
+    let mut previous: Option<String> = None;
+    for (key, message) in raw {
+        let parent = if tree.is_some() { string(message.get("parentId")) } else { previous.clone() };
+        parents.insert(key.clone(), parent.clone());
+        previous = Some(key.clone());
+
+        let Some(role) = message.get("role").and_then(|r| r.as_str()).and_then(normalize_role) else {
+            continue;
+        };
+        let content = text_content(message.get("content"));
+        if content.trim().is_empty() {
+            continue;
+        }
+        let stats = message.get("usage").or_else(|| message.get("info")).filter(|s| s.is_object());
+
+        messages.push(ImportedMessage {
+            key,
+            parent,
+            role: role.to_string(),
+            content,
+            model: string(message.get("model")),
+            parameters: None,
+            metadata: stats.map(|stats| json!({ "stats": stats })),
+            created_at: timestamp(message.get("timestamp")),
+            pinned: false,
+        });
+    }
+
+    if tree.is_some() {
+        messages.sort_by_key(|m| m.created_at.unwrap_or(i64::MAX));
+    }
+    reparent(&mut messages, &parents);
+    let current = kept_current(history.and_then(|h| string(h.get("currentId"))), &messages, &parents);
+
+    ImportedConversation {
+        title: string(entry.get("title")).or_else(|| string(chat.get("title"))).unwrap_or_default(),
+        created_at: timestamp(entry.get("created_at")).or_else(|| timestamp(chat.get("timestamp"))),
+        updated_at: timestamp(entry.get("updated_at")),
+        messages,
+        current,
+        options: GenerationOptions::default(),
+    }
+}
+
+// This app's lossless export: message trees that keep model, parameters and metadata.
+// Version 1 files have flat lists without parent ids.
+fn parse_beautifyollama(file: ExportFile) -> Vec<ImportedConversation> {
+    file.conversations
+        .into_iter()
+        .map(|conversation| {
+            let flat = conversation.messages.iter().all(|m| m.parent_id.is_none());
+            let mut previous: Option<String> = None;
+            let messages = conversation
+                .messages
+                .into_iter()
+                .map(|message| {
+                    let chained = previous.replace(message.id.clone());
+                    ImportedMessage {
+                        key: message.id,
+                        parent: if flat { chained } else { message.parent_id },
+                        role: message.role,
+                        content: message.content,
+                        model: message.model,
+                        parameters: message.parameters,
+                        metadata: message.metadata,
+                        created_at: Some(message.created_at),
+                        pinned: message.pinned,
+                    }
+                })
+                .collect();
+            ImportedConversation {
+                title: conversation.title,
+                created_at: Some(conversation.created_at),
+                updated_at: Some(conversation.updated_at),
+                messages,
+                current: conversation.active_leaf_id,
+                options: conversation.options,
+            }
+        })
+        .collect()
+}
+
+// One message per line. Lines with the same conversation id (conversation_id, conversation or
+// session_id) form one conversation; without ids the whole file is a single conversation.
+fn parse_jsonl(text: &str, fallback_title: &str, warnings: &mut Vec<String>) -> Vec<ImportedConversation> {
+    let mut order: Vec<String> = Vec::new();
+    let mut grouped: HashMap<String, ImportedConversation> = HashMap::new();
+
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let object = match serde_json::from_str::<Value>(line) {
+            Ok(Value::Object(object)) => object,
+            Ok(_) => {
+                warnings.push(format!("Line {}: expected a JSON object", number + 1));
+                continue;
+            }
+            Err(e) => {
+                warnings.push(format!("Line {}: {}", number + 1, e));
+                continue;
+            }
+        };
+        let Some(role) = object.get("role").and_then(|r| r.as_str()).and_then(normalize_role) else {
+            warnings.push(format!("Line {}: missing or unknown role", number + 1));
+            continue;
+        };
+
+        let group = ["conversation_id", "conversation", "session_id"]
+            .iter()
+            .find_map(|key| string(object.get(*key)))
+            .unwrap_or_default();
+        if !grouped.contains_key(&group) {
+            order.push(group.clone());
+        }
+        let conversation = grouped.entry(group.clone()).or_insert_with(|| ImportedConversation {
+            title: if group.is_empty() { fallback_title.to_string() } else { group.clone() },
+            ..ImportedConversation::default()
+        });
+        if let Some(title) = string(object.get("title")).or_else(|| string(object.get("conversation_title"))) {
+            conversation.title = title;
+        }
+
+        let key = string(object.get("id")).unwrap_or_else(|| format!("line-{}", number + 1));
+        let parent = string(object.get("parent_id")).or_else(|| conversation.messages.last().map(|m| m.key.clone()));
+        conversation.messages.push(ImportedMessage {
+            key,
+            parent,
+            role: role.to_string(),
+            content: text_content(object.get("content")),
+            model: string(object.get("model")),
+            parameters: object.get("options").or_else(|| object.get("parameters")).filter(|p| p.is_object()).cloned(),
+            metadata: None,
+            created_at: ["created_at", "timestamp", "time", "date"]
+                .iter()
+                .find_map(|key| timestamp(object.get(*key))),
+            pinned: object.get("pinned").and_then(|v| v.as_bool()).unwrap_or(false),
+        });
+    }
+
+    order.into_iter().filter_map(|group| grouped.remove(&group)).collect()
+}
+
+fn detect(text: &str) -> Result<ImportSource, String> {
+    match serde_json::from_str::<Value>(text) {
+        Ok(Value::Object(object)) => {
+            if object.get("format").and_then(|f| f.as_str()) == Some(EXPORT_FILE_FORMAT) {
+                Ok(ImportSource::BeautifyOllama)
+            } else if object.contains_key("chat") {
+                Ok(ImportSource::OpenWebUi)
+            } else if object.contains_key("mapping") {
+                Ok(ImportSource::ChatGpt)
+            } else if object.contains_key("role") {
+                Ok(ImportSource::Jsonl)
+            } else {
+                Err("Unrecognised export format".to_string())
+            }
+        }
+        Ok(Value::Array(entries)) => match entries.first() {
+            Some(first) if first.get("mapping").is_some() => Ok(ImportSource::ChatGpt),
+            Some(first) if first.get("chat").is_some() => Ok(ImportSource::OpenWebUi),
+            Some(_) => Err("Unrecognised export format".to_string()),
+            None => Err("The file contains no conversations".to_string()),
+        },
+        // Several JSON objects, one per line
+        Err(_) if text.lines().filter(|l| !l.trim().is_empty()).count() > 1 => Ok(ImportSource::Jsonl),
+        Ok(_) => Err("Unrecognised export format".to_string()),
+        Err(e) => Err(format!("The file is neither JSON nor JSONL: {}", e)),
+    }
+}
+
+// Entries of a JSON export: an array, or a single conversation object
+fn json_entries(text: &str) -> Result<Vec<Map<String, Value>>, String> {
+    match serde_json::from_str::<Value>(text).map_err(|e| format!("Failed to parse export: {}", e))? {
+        Value::Array(entries) => Ok(entries.into_iter().filter_map(|e| e.as_object().cloned()).collect()),
+        Value::Object(object) => Ok(vec![object]),
+        _ => Err("Expected a JSON array of conversations".to_string()),
+    }
+}
+
+fn parse(path: &Path, source: Option<ImportSource>) -> Result<(ImportSource, Vec<ImportedConversation>, Vec<String>), String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let source = match source {
+        Some(source) => source,
+        None => detect(&text)?,
+    };
+
+    let mut warnings = Vec::new();
+    let conversations = match source {
+        ImportSource::ChatGpt => json_entries(&text)?.iter().map(parse_chatgpt).collect(),
+        ImportSource::OpenWebUi => json_entries(&text)?.iter().map(parse_open_webui).collect(),
+        ImportSource::Jsonl => {
+            let title = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            parse_jsonl(&text, &title, &mut warnings)
+        }
+        ImportSource::BeautifyOllama => {
+            let file: ExportFile = serde_json::from_str(&text)
+                .map_err(|e| format!("{} is not a BeautifyOllama export: {}", path.display(), e))?;
+            if file.version > crate::export::EXPORT_FILE_VERSION {
+                return Err(format!(
+                    "{} was exported by a newer version (format {}, this build reads up to {})",
+                    path.display(),
+                    file.version,
+                    crate::export::EXPORT_FILE_VERSION
+                ));
+            }
+            parse_beautifyollama(file)
+        }
+    };
+    Ok((source, conversations, warnings))
+}
+
+fn path_fingerprint(path: &[&ImportedMessage]) -> String {
+    conversations::fingerprint(path.iter().map(|m| (m.role.as_str(), m.content.as_str())))
+}
+
+// Duplicate status of every entry, by comparing active branches with stored conversations and each other
+fn duplicates(conversations: &[ImportedConversation]) -> Result<Vec<Option<Duplicate>>, String> {
+    let existing = conversations::fingerprints()?;
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    Ok(conversations
+        .iter()
+        .enumerate()
+        .map(|(index, conversation)| {
+            let branches = conversation.branches();
+            let active = branches.first()?;
+            let key = path_fingerprint(active);
+            if let Some(summary) = existing.get(&key) {
+                return Some(Duplicate::Existing {
+                    conversation_id: summary.id.clone(),
+                    title: summary.title.clone(),
+                });
+            }
+            match seen.get(&key) {
+                Some(first) => Some(Duplicate::InFile { index: *first }),
+                None => {
+                    seen.insert(key, index);
+                    None
+                }
+            }
+        })
+        .collect())
+}
+
+// Read an export and describe what importing it would add, without writing anything
+pub fn preview(path: &Path, source: Option<ImportSource>) -> Result<ImportPreview, String> {
+    let (source, conversations, warnings) = parse(path, source)?;
+    let duplicates = duplicates(&conversations)?;
+
+    let entries = conversations
+        .iter()
+        .zip(duplicates)
+        .enumerate()
+        .map(|(index, (conversation, duplicate))| {
+            let branches = conversation.branches();
+            let active = branches.first().cloned().unwrap_or_default();
+            let mut models: Vec<String> = Vec::new();
+            for model in active.iter().filter_map(|m| m.model.clone()) {
+                if !models.contains(&model) {
+                    models.push(model);
+                }
+            }
+            PreviewEntry {
+                index,
+                title: conversation.title.clone(),
+                created_at: conversation.created_at.or_else(|| active.iter().filter_map(|m| m.created_at).min()),
+                updated_at: conversation.updated_at.or_else(|| active.iter().filter_map(|m| m.created_at).max()),
+                message_count: active.len(),
+                branch_count: branches.len(),
+                models,
+                first_message: active.first().map(|m| m.content.chars().take(PREVIEW_LENGTH).collect()),
+                duplicate,
+            }
+        })
+        .collect();
+
+    Ok(ImportPreview { source, entries, warnings })
+}
+
+fn new_conversation(title: String, conversation: &ImportedConversation) -> NewConversation {
+    let now = conversations::now_millis();
+    let ordered = conversation.ordered();
+    let first = ordered.iter().filter_map(|m| m.created_at).min();
+    let last = ordered.iter().filter_map(|m| m.created_at).max();
+    let created_at = conversation.created_at.or(first).unwrap_or(now);
+    let updated_at = conversation.updated_at.or(last).unwrap_or(created_at).max(created_at);
+
+    // Messages without a time of their own sit at the conversation start, in order
+    let messages = ordered
+        .iter()
+        .map(|message| NewMessage {
+            id: Some(message.key.clone()),
+            parent_id: message.parent.clone(),
+            role: message.role.clone(),
+            content: message.content.clone(),
+            model: message.model.clone(),
+            parameters: message.parameters.clone(),
+            metadata: message.metadata.clone(),
+            created_at: Some(message.created_at.unwrap_or(created_at)),
+            pinned: message.pinned,
+        })
+        .collect();
+
+    NewConversation {
+        title,
+        created_at,
+        updated_at,
+        messages,
+        active_leaf: conversation.active_leaf().map(|leaf| leaf.to_string()),
+        options: conversation.options.clone(),
+    }
+}
+
+// Import an export, keeping every branch in the message tree. `selection` lists preview indices
+// to import exactly (duplicates included); without it everything except duplicates is imported.
+pub fn import(path: &Path, source: Option<ImportSource>, selection: Option<Vec<usize>>) -> Result<ImportResult, String> {
+    let (_, conversations, _) = parse(path, source)?;
+    let duplicates = duplicates(&conversations)?;
+    let mut result = ImportResult::default();
+    let mut pending = Vec::new();
+
+    for (index, (conversation, duplicate)) in conversations.iter().zip(duplicates).enumerate() {
+        let wanted = match &selection {
+            Some(selection) => selection.contains(&index),
+            None => duplicate.is_none(),
+        };
+        if !wanted {
+            if duplicate.is_some() {
+                result.skipped_duplicates += 1;
+            }
+            continue;
+        }
+
+        let new = new_conversation(conversation.title.clone(), conversation);
+        if new.messages.is_empty() {
+            result.skipped_empty += 1;
+            continue;
+        }
+        result.conversations += 1;
+        result.messages += new.messages.len();
+        result.branches += conversation.leaves().len().saturating_sub(1);
+        pending.push(new);
+    }
+
+    conversations::insert_conversations(pending)?;
+    println!(
+        "Imported {} conversations ({} extra branches, {} messages) from {}",
+        result.conversations,
+        result.branches,
+        result.messages,
+        path.display()
+    );
+    Ok(result)
+}
@@ -1,35 +1,77 @@
-use std::process::Command;
-use std::path::Path;
-use std::net::TcpStream;
-use std::time::Duration;
+use std::process::{Child, Command, Stdio};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex, OnceLock};
 use serde_json::json;
 use serde::{Deserialize, Serialize};
 use reqwest;
 use semver::Version;
+use tauri::{Emitter, Manager};
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
+mod binary;
+mod config;
+mod context;
+mod conversations;
+mod credentials;
+mod encryption;
+mod endpoints;
+mod export;
+mod generation;
+mod importers;
+mod personas;
+mod pool;
+mod ports;
+mod profiles;
+mod provider;
+mod proxy;
+mod settings;
+mod systemd;
+mod templates;
+mod tls;
+mod version;
+
 // Default Ollama port
 const DEFAULT_OLLAMA_PORT: u16 = 11434;
 
-// Helper function to get Ollama port from environment or use default
+// Helper function to get the port of the active Ollama endpoint
 fn get_ollama_port() -> u16 {
-    std::env::var("OLLAMA_PORT")
-        .ok()
-        .and_then(|s| s.parse::<u16>().ok())
-        .unwrap_or(DEFAULT_OLLAMA_PORT)
+    settings::active_endpoint().port
 }
 
 // Helper function to get Ollama base URL
 fn get_ollama_base_url() -> String {
-    let port = get_ollama_port();
-    format!("http://localhost:{}", port)
+    settings::active_endpoint().base_url()
 }
 
 // Helper function to get Ollama API endpoint
 fn get_ollama_api_url(endpoint: &str) -> String {
-    format!("{}/api/{}", get_ollama_base_url(), endpoint)
+    settings::active_endpoint().api_url(endpoint)
+}
+
+// Helper function to refuse local-only operations (start, stop, install, ...) while a remote endpoint is active
+fn require_local_endpoint() -> Result<(), String> {
+    let endpoint = settings::active_endpoint();
+    if endpoint.is_local() {
+        Ok(())
+    } else {
+        Err(format!(
+            "This action manages the local Ollama install and is disabled while the remote endpoint '{}' ({}) is active",
+            endpoint.name,
+            endpoint.base_url()
+        ))
+    }
+}
+
+// Helper function to reject Ollama-only operations (pull, rm, ps, ...) on OpenAI-compatible servers
+fn require_ollama_provider(action: &str) -> Result<(), String> {
+    let active = settings::active_endpoint();
+    if active.provider != provider::ProviderKind::Ollama {
+        return Err(format!("{} needs an Ollama server, but '{}' is an OpenAI-compatible endpoint", action, active.name));
+    }
+    Ok(())
 }
 
 // Helper function to generate extended PATH based on platform
@@ -64,102 +106,1148 @@ fn get_extended_path() -> String {
     }
 }
 
-#[tauri::command]
-fn get_platform() -> String {
-    if cfg!(target_os = "macos") {
-        "macos".to_string()
-    } else if cfg!(target_os = "windows") {
-        "windows".to_string()
-    } else if cfg!(target_os = "linux") {
-        "linux".to_string()
-    } else {
-        "unknown".to_string()
+#[tauri::command]
+fn get_platform() -> String {
+    if cfg!(target_os = "macos") {
+        "macos".to_string()
+    } else if cfg!(target_os = "windows") {
+        "windows".to_string()
+    } else if cfg!(target_os = "linux") {
+        "linux".to_string()
+    } else {
+        "unknown".to_string()
+    }
+}
+
+#[tauri::command]
+fn get_ollama_port_config() -> u16 {
+    get_ollama_port()
+}
+
+#[tauri::command]
+fn set_ollama_port_config(app: tauri::AppHandle, port: u16) -> Result<String, String> {
+    if port < 1024 {
+        return Err("Port must be between 1024 and 65535".to_string());
+    }
+
+    // The port belongs to whichever endpoint is active
+    let active_id = settings::active_endpoint().id;
+    save_settings(&app, |settings| {
+        if let Some(endpoint) = settings.endpoints.iter_mut().find(|e| e.id == active_id) {
+            endpoint.port = port;
+        }
+    })?;
+
+    if active_id == endpoints::LOCAL_ENDPOINT_ID {
+        if let Some((_, origin, effective)) = config::port_override().filter(|(_, _, p)| *p != port) {
+            return Ok(format!("Ollama port saved as {}, but {} keeps it at {} while the app is running", port, origin, effective));
+        }
+    }
+    Ok(format!("Ollama port set to {}", port))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EndpointList {
+    endpoints: Vec<endpoints::Endpoint>,
+    active_endpoint: String,
+    active_is_local: bool,
+}
+
+#[tauri::command]
+fn list_endpoints() -> EndpointList {
+    let current = settings::current();
+    let active_is_local = current.active_endpoint().is_local();
+    EndpointList {
+        endpoints: current.endpoints,
+        active_endpoint: current.active_endpoint,
+        active_is_local,
+    }
+}
+
+#[tauri::command]
+fn save_endpoint(app: tauri::AppHandle, mut endpoint: endpoints::Endpoint) -> Result<endpoints::Endpoint, String> {
+    endpoint.base_path = endpoints::normalize_base_path(&endpoint.base_path);
+    endpoint.scheme = endpoint.scheme.trim().to_lowercase();
+    endpoint.host = endpoint.host.trim().to_string();
+
+    // New endpoints come without an id; existing ones are updated in place
+    if endpoint.id.trim().is_empty() {
+        let existing: Vec<String> = settings::current().endpoints.into_iter().map(|e| e.id).collect();
+        endpoint.id = endpoints::unique_id(&endpoint.name, &existing);
+    }
+    endpoint.validate()?;
+    if endpoint.provider != provider::ProviderKind::Ollama {
+        if let Some(pool) = settings::current().pools.iter().find(|p| p.members.contains(&endpoint.id)) {
+            return Err(format!("Endpoint '{}' is a member of pool '{}', which only takes Ollama servers", endpoint.name, pool.name));
+        }
+    }
+
+    // The auth type follows the stored credentials and is only changed by set_endpoint_credentials
+    endpoint.auth = credentials::get(&endpoint.id)
+        .map(|c| c.auth_type())
+        .unwrap_or_default();
+
+    // Insecure mode is only switched on through set_endpoint_tls; keep whatever is stored
+    endpoint.tls.insecure = settings::current()
+        .endpoints
+        .iter()
+        .find(|e| e.id == endpoint.id)
+        .map(|e| e.tls.insecure)
+        .unwrap_or(false);
+
+    let saved = endpoint.clone();
+    save_settings(&app, |settings| {
+        match settings.endpoints.iter_mut().find(|e| e.id == endpoint.id) {
+            Some(existing) => *existing = endpoint,
+            None => settings.endpoints.push(endpoint),
+        }
+    })?;
+    Ok(saved)
+}
+
+#[tauri::command]
+fn delete_endpoint(app: tauri::AppHandle, id: String) -> Result<EndpointList, String> {
+    let current = settings::current();
+    if !current.endpoints.iter().any(|e| e.id == id) {
+        return Err(format!("Endpoint '{}' does not exist", id));
+    }
+    if current.endpoints.len() == 1 {
+        return Err("Cannot delete the last endpoint".to_string());
+    }
+
+    credentials::set(&id, None)?;
+    save_settings(&app, |settings| {
+        settings.endpoints.retain(|e| e.id != id);
+        // Deleting the active endpoint falls back to the first remaining one
+        if settings.active_endpoint == id {
+            settings.active_endpoint = settings.endpoints[0].id.clone();
+        }
+        // Pools lose the member, and pools left empty go away
+        for pool in settings.pools.iter_mut() {
+            pool.members.retain(|member| member != &id);
+        }
+        settings.pools.retain(|pool| !pool.members.is_empty());
+        if let Some(active_pool) = settings.active_pool.clone() {
+            if !settings.pools.iter().any(|p| p.id == active_pool) {
+                settings.active_pool = None;
+            }
+        }
+    })?;
+    Ok(list_endpoints())
+}
+
+#[tauri::command]
+fn set_active_endpoint(app: tauri::AppHandle, id: String) -> Result<EndpointList, String> {
+    if !settings::current().endpoints.iter().any(|e| e.id == id) {
+        return Err(format!("Endpoint '{}' does not exist", id));
+    }
+    save_settings(&app, |settings| settings.active_endpoint = id)?;
+    Ok(list_endpoints())
+}
+
+#[tauri::command]
+async fn test_endpoint(
+    endpoint: endpoints::Endpoint,
+    credentials: Option<credentials::EndpointCredentials>,
+) -> Result<EndpointTestResult, String> {
+    endpoint.validate()?;
+
+    // Credentials typed into the form take precedence over the stored ones
+    let headers = match &credentials {
+        Some(credentials) => credentials.header_map()?,
+        None => credentials::headers_for(&endpoint.id)?,
+    };
+    // Record the presented certificate so it can be inspected even when it is rejected
+    let observation = Arc::new(Mutex::new(tls::HandshakeObservation::default()));
+    let client = endpoint_client_with(&endpoint, headers, Some(Duration::from_secs(5)), Some(observation.clone()))?;
+    let mut result = probe_endpoint(&endpoint, &client).await;
+
+    let observation = observation.lock().unwrap_or_else(|e| e.into_inner());
+    result.certificate = observation.certificate.as_deref().map(tls::describe_certificate);
+    if let Some(rejection) = &observation.rejection {
+        result.status = EndpointProbeStatus::UntrustedCertificate;
+        result.message = format!(
+            "The certificate presented by {} was rejected: {}. Check the certificate details, then add its CA bundle or pin its fingerprint.",
+            endpoint.host, rejection
+        );
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+fn set_endpoint_tls(
+    app: tauri::AppHandle,
+    id: String,
+    tls: tls::TlsOptions,
+    confirm_insecure: bool,
+) -> Result<EndpointList, String> {
+    if tls.insecure && !confirm_insecure {
+        return Err("Insecure mode disables certificate validation and must be confirmed explicitly".to_string());
+    }
+    if !settings::current().endpoints.iter().any(|e| e.id == id) {
+        return Err(format!("Endpoint '{}' does not exist", id));
+    }
+
+    save_settings(&app, |settings| {
+        if let Some(endpoint) = settings.endpoints.iter_mut().find(|e| e.id == id) {
+            endpoint.tls = tls;
+        }
+    })?;
+    Ok(list_endpoints())
+}
+
+#[tauri::command]
+fn set_endpoint_credentials(
+    app: tauri::AppHandle,
+    id: String,
+    credentials: Option<credentials::EndpointCredentials>,
+) -> Result<EndpointList, String> {
+    if !settings::current().endpoints.iter().any(|e| e.id == id) {
+        return Err(format!("Endpoint '{}' does not exist", id));
+    }
+
+    let auth = credentials
+        .as_ref()
+        .map(|c| c.auth_type())
+        .unwrap_or_default();
+    credentials::set(&id, credentials)?;
+
+    save_settings(&app, |settings| {
+        if let Some(endpoint) = settings.endpoints.iter_mut().find(|e| e.id == id) {
+            endpoint.auth = auth;
+        }
+    })?;
+    Ok(list_endpoints())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PoolList {
+    pools: Vec<pool::EndpointPool>,
+    active_pool: Option<String>,
+}
+
+#[tauri::command]
+fn list_pools() -> PoolList {
+    let current = settings::current();
+    PoolList {
+        pools: current.pools,
+        active_pool: current.active_pool,
+    }
+}
+
+#[tauri::command]
+fn save_pool(app: tauri::AppHandle, mut pool: pool::EndpointPool) -> Result<pool::EndpointPool, String> {
+    if pool.id.trim().is_empty() {
+        let existing: Vec<String> = settings::current().pools.into_iter().map(|p| p.id).collect();
+        pool.id = endpoints::unique_id(&pool.name, &existing);
+    }
+    pool.validate(&settings::current().endpoints)?;
+
+    let saved = pool.clone();
+    save_settings(&app, |settings| {
+        match settings.pools.iter_mut().find(|p| p.id == pool.id) {
+            Some(existing) => *existing = pool,
+            None => settings.pools.push(pool),
+        }
+    })?;
+    Ok(saved)
+}
+
+#[tauri::command]
+fn delete_pool(app: tauri::AppHandle, id: String) -> Result<PoolList, String> {
+    if !settings::current().pools.iter().any(|p| p.id == id) {
+        return Err(format!("Pool '{}' does not exist", id));
+    }
+
+    save_settings(&app, |settings| {
+        settings.pools.retain(|p| p.id != id);
+        if settings.active_pool.as_deref() == Some(id.as_str()) {
+            settings.active_pool = None;
+        }
+    })?;
+    Ok(list_pools())
+}
+
+// None switches back to sending model requests to the active endpoint
+#[tauri::command]
+fn set_active_pool(app: tauri::AppHandle, id: Option<String>) -> Result<PoolList, String> {
+    if let Some(id) = &id {
+        if !settings::current().pools.iter().any(|p| &p.id == id) {
+            return Err(format!("Pool '{}' does not exist", id));
+        }
+    }
+    save_settings(&app, |settings| settings.active_pool = id)?;
+    Ok(list_pools())
+}
+
+// Health, installed and loaded models and in-flight requests of each member; defaults to the active pool
+#[tauri::command]
+async fn get_pool_status(id: Option<String>) -> Result<pool::PoolStatus, String> {
+    let current = settings::current();
+    let id = id
+        .or_else(|| current.active_pool.clone())
+        .ok_or_else(|| "No pool selected".to_string())?;
+    let selected = current
+        .pools
+        .iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("Pool '{}' does not exist", id))?;
+
+    let active = current.active_pool.as_deref() == Some(id.as_str());
+    Ok(pool::status(selected, &current.endpoints, active).await)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileList {
+    profiles: Vec<profiles::Profile>,
+    active_profile: Option<String>,
+}
+
+#[tauri::command]
+fn list_profiles() -> ProfileList {
+    let current = settings::current();
+    ProfileList {
+        profiles: current.profiles,
+        active_profile: current.active_profile,
+    }
+}
+
+// Store the current endpoint, models, server environment, search settings and prompt preset as a profile
+#[tauri::command]
+fn capture_profile(app: tauri::AppHandle, name: String) -> Result<profiles::Profile, String> {
+    let current = settings::current();
+    let existing: Vec<String> = current.profiles.iter().map(|p| p.id.clone()).collect();
+    let profile = profiles::Profile::from_settings(endpoints::unique_id(&name, &existing), name, &current);
+
+    let saved = profile.clone();
+    save_settings(&app, |settings| settings.profiles.push(profile))?;
+    Ok(saved)
+}
+
+#[tauri::command]
+fn save_profile(app: tauri::AppHandle, mut profile: profiles::Profile) -> Result<profiles::Profile, String> {
+    if profile.id.trim().is_empty() {
+        let existing: Vec<String> = settings::current().profiles.into_iter().map(|p| p.id).collect();
+        profile.id = endpoints::unique_id(&profile.name, &existing);
+    }
+
+    let saved = profile.clone();
+    save_settings(&app, |settings| {
+        match settings.profiles.iter_mut().find(|p| p.id == profile.id) {
+            Some(existing) => *existing = profile,
+            None => settings.profiles.push(profile),
+        }
+    })?;
+    Ok(saved)
+}
+
+#[tauri::command]
+fn delete_profile(app: tauri::AppHandle, id: String) -> Result<ProfileList, String> {
+    if !settings::current().profiles.iter().any(|p| p.id == id) {
+        return Err(format!("Profile '{}' does not exist", id));
+    }
+
+    save_settings(&app, |settings| {
+        settings.profiles.retain(|p| p.id != id);
+        if settings.active_profile.as_deref() == Some(id.as_str()) {
+            settings.active_profile = None;
+        }
+    })?;
+    Ok(list_profiles())
+}
+
+#[tauri::command]
+fn switch_profile(app: tauri::AppHandle, id: String) -> Result<settings::Settings, String> {
+    let profile = settings::current()
+        .profiles
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("Profile '{}' does not exist", id))?;
+
+    let updated = save_settings(&app, |settings| profile.apply(settings))?;
+    println!("Switched to profile '{}' ({})", profile.name, profile.endpoint.base_url());
+    Ok(updated)
+}
+
+// Write a profile to a portable file; credentials and insecure TLS are never included
+#[tauri::command]
+fn export_profile(id: String, path: String) -> Result<String, String> {
+    let profile = settings::current()
+        .profiles
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("Profile '{}' does not exist", id))?;
+
+    profiles::export_to(&profile, Path::new(&path))?;
+    Ok(format!("Profile '{}' exported to {}", profile.name, path))
+}
+
+// Add a profile from an exported file. A profile or endpoint id already in use gets a fresh one instead of
+// being overwritten, so the profile never picks up credentials stored for a local endpoint.
+#[tauri::command]
+fn import_profile(app: tauri::AppHandle, path: String) -> Result<profiles::Profile, String> {
+    let mut profile = profiles::import_from(Path::new(&path))?;
+    let current = settings::current();
+
+    let existing: Vec<String> = current.profiles.iter().map(|p| p.id.clone()).collect();
+    if existing.contains(&profile.id) {
+        profile.id = endpoints::unique_id(&profile.name, &existing);
+    }
+
+    let existing_endpoints: Vec<String> = current
+        .endpoints
+        .iter()
+        .map(|e| e.id.clone())
+        .chain(current.profiles.iter().map(|p| p.endpoint.id.clone()))
+        .collect();
+    if existing_endpoints.contains(&profile.endpoint.id) {
+        profile.endpoint.id = endpoints::unique_id(&profile.endpoint.name, &existing_endpoints);
+    }
+
+    let saved = profile.clone();
+    save_settings(&app, |settings| settings.profiles.push(profile))?;
+    Ok(saved)
+}
+
+// Every effective setting with the source it came from. `local_storage` lets the frontend include its own values.
+#[tauri::command]
+fn get_effective_config(local_storage: Option<std::collections::BTreeMap<String, serde_json::Value>>) -> config::ConfigReport {
+    config::report(local_storage.unwrap_or_default())
+}
+
+#[tauri::command]
+fn detect_system_proxy() -> std::collections::BTreeMap<String, String> {
+    proxy::detect_env_proxies()
+}
+
+// Conversation store (SQLite in the app data directory)
+
+#[tauri::command]
+fn list_conversations(offset: Option<u32>, limit: Option<u32>) -> Result<conversations::Page<conversations::ConversationSummary>, String> {
+    conversations::list(offset, limit)
+}
+
+#[tauri::command]
+fn get_conversation(id: String) -> Result<conversations::Conversation, String> {
+    conversations::get(&id)
+}
+
+#[tauri::command]
+fn create_conversation(title: Option<String>) -> Result<conversations::ConversationSummary, String> {
+    conversations::create(title)
+}
+
+#[tauri::command]
+fn rename_conversation(id: String, title: String) -> Result<conversations::ConversationSummary, String> {
+    conversations::rename(&id, title)
+}
+
+#[tauri::command]
+fn delete_conversation(id: String) -> Result<(), String> {
+    conversations::delete(&id)
+}
+
+#[tauri::command]
+fn delete_all_conversations() -> Result<usize, String> {
+    conversations::delete_all()
+}
+
+#[tauri::command]
+fn list_messages(conversation_id: String, offset: Option<u32>, limit: Option<u32>) -> Result<conversations::Page<conversations::Message>, String> {
+    conversations::messages(&conversation_id, offset, limit)
+}
+
+#[tauri::command]
+fn add_message(conversation_id: String, message: conversations::NewMessage) -> Result<conversations::Message, String> {
+    conversations::add_message(&conversation_id, message)
+}
+
+#[tauri::command]
+fn update_message(id: String, content: Option<String>, metadata: Option<serde_json::Value>) -> Result<conversations::Message, String> {
+    conversations::update_message(&id, content, metadata)
+}
+
+#[tauri::command]
+fn delete_message(id: String) -> Result<(), String> {
+    conversations::delete_message(&id)
+}
+
+#[tauri::command]
+fn get_conversation_options(id: String) -> Result<generation::GenerationOptions, String> {
+    conversations::generation_options(&id)
+}
+
+// Options chat_stream fills in for requests that name this conversation
+#[tauri::command]
+fn set_conversation_options(id: String, options: generation::GenerationOptions) -> Result<generation::GenerationOptions, String> {
+    conversations::set_generation_options(&id, options)
+}
+
+#[tauri::command]
+fn set_conversation_model(id: String, model: Option<String>) -> Result<conversations::ConversationSummary, String> {
+    conversations::set_model(&id, model)
+}
+
+// Pinned messages are kept when older turns no longer fit the model's context
+#[tauri::command]
+fn set_message_pinned(id: String, pinned: bool) -> Result<conversations::Message, String> {
+    conversations::set_pinned(&id, pinned)
+}
+
+// Add the new text as a sibling of the message and make it the active branch
+#[tauri::command]
+fn edit_message(id: String, content: String) -> Result<conversations::Message, String> {
+    conversations::edit_message(&id, content)
+}
+
+#[tauri::command]
+fn list_branches(message_id: String) -> Result<conversations::Branches, String> {
+    conversations::branches(&message_id)
+}
+
+#[tauri::command]
+fn switch_branch(message_id: String) -> Result<conversations::Conversation, String> {
+    conversations::switch_branch(&message_id)
+}
+
+// Messages to send to /api/chat: the active branch, or the branch ending at `leaf_id`
+#[tauri::command]
+fn build_chat_messages(conversation_id: String, leaf_id: Option<String>) -> Result<Vec<provider::ChatMessage>, String> {
+    conversations::chat_messages(&conversation_id, leaf_id.as_deref())
+}
+
+// Full-text search over all stored messages, best matches first
+#[tauri::command]
+fn search_conversations(
+    query: String,
+    filters: Option<conversations::SearchFilters>,
+    offset: Option<u32>,
+    limit: Option<u32>,
+) -> Result<conversations::Page<conversations::SearchHit>, String> {
+    conversations::search(&query, filters.unwrap_or_default(), offset, limit)
+}
+
+// Write one or more conversations to a single Markdown, HTML or JSON file
+#[tauri::command]
+fn export_conversations(ids: Vec<String>, format: export::ExportFormat, path: String) -> Result<String, String> {
+    let count = export::export_to(&ids, format, Path::new(&path))?;
+    Ok(format!("Exported {} conversation(s) to {}", count, path))
+}
+
+// Read a ChatGPT, Open WebUI, JSONL or BeautifyOllama export and list what it contains, flagging duplicates.
+// `source` is detected from the file when omitted.
+#[tauri::command]
+fn preview_conversation_import(path: String, source: Option<importers::ImportSource>) -> Result<importers::ImportPreview, String> {
+    importers::preview(Path::new(&path), source)
+}
+
+// Import the entries picked from the preview, or everything that is not a duplicate
+#[tauri::command]
+fn import_conversations(
+    path: String,
+    source: Option<importers::ImportSource>,
+    selection: Option<Vec<usize>>,
+) -> Result<importers::ImportResult, String> {
+    importers::import(Path::new(&path), source, selection)
+}
+
+// One-time move of the `ollama-conversations` localStorage value into the database.
+// The frontend passes the raw string; later calls report `already_imported` and change nothing.
+#[tauri::command]
+fn import_local_conversations(data: String) -> Result<conversations::ImportSummary, String> {
+    conversations::import_local_storage(&data)
+}
+
+// Sent when the conversation store locks, by command or after the auto-lock time
+const CONVERSATIONS_LOCKED_EVENT: &str = "conversations-locked";
+
+// How often the auto-lock time is checked
+const AUTO_LOCK_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+fn notify_conversations_locked(app: &tauri::AppHandle) {
+    if let Err(e) = app.emit(CONVERSATIONS_LOCKED_EVENT, ()) {
+        eprintln!("Failed to emit {}: {}", CONVERSATIONS_LOCKED_EVENT, e);
+    }
+}
+
+// Helper function to run slow synchronous work (key derivation, re-encryption, systemctl and pkexec)
+// on a blocking thread instead of the main one or the async runtime
+async fn run_blocking<T, F>(work: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| format!("Background task failed: {}", e))?
+}
+
+#[tauri::command]
+fn get_encryption_status() -> Result<conversations::EncryptionStatus, String> {
+    conversations::encryption_status()
+}
+
+// Encrypt the conversation store. Returns the recovery key; it is not stored anywhere and cannot be shown again.
+#[tauri::command]
+async fn enable_conversation_encryption(passphrase: String) -> Result<String, String> {
+    run_blocking(move || conversations::enable_encryption(&passphrase)).await
+}
+
+#[tauri::command]
+async fn disable_conversation_encryption(passphrase: String) -> Result<(), String> {
+    run_blocking(move || conversations::disable_encryption(&passphrase)).await
+}
+
+#[tauri::command]
+async fn unlock_conversations(passphrase: String) -> Result<(), String> {
+    run_blocking(move || conversations::unlock(&passphrase)).await
+}
+
+// Unlock after a forgotten passphrase; the recovery key stays valid
+#[tauri::command]
+async fn unlock_conversations_with_recovery_key(recovery_key: String, new_passphrase: String) -> Result<(), String> {
+    run_blocking(move || conversations::unlock_with_recovery_key(&recovery_key, &new_passphrase)).await
+}
+
+#[tauri::command]
+fn lock_conversations(app: tauri::AppHandle) -> Result<(), String> {
+    conversations::lock()?;
+    notify_conversations_locked(&app);
+    Ok(())
+}
+
+#[tauri::command]
+async fn change_conversation_passphrase(current: String, new_passphrase: String) -> Result<(), String> {
+    run_blocking(move || conversations::change_passphrase(&current, &new_passphrase)).await
+}
+
+// Save a new recovery key to a file while the store is unlocked; the previous recovery key stops working
+#[tauri::command]
+fn export_recovery_key(path: String) -> Result<String, String> {
+    conversations::reissue_recovery_key(|recovery_key| encryption::export_recovery_key(recovery_key, Path::new(&path)))?;
+    Ok(format!("Saved a new recovery key to {}", path))
+}
+
+#[tauri::command]
+fn list_personas() -> Vec<personas::Persona> {
+    settings::current().personas
+}
+
+#[tauri::command]
+fn save_persona(app: tauri::AppHandle, mut persona: personas::Persona) -> Result<personas::Persona, String> {
+    if persona.id.trim().is_empty() {
+        let existing: Vec<String> = settings::current().personas.into_iter().map(|p| p.id).collect();
+        persona.id = endpoints::unique_id(&persona.name, &existing);
+    }
+
+    let saved = persona.clone();
+    save_settings(&app, |settings| {
+        match settings.personas.iter_mut().find(|p| p.id == persona.id) {
+            Some(existing) => *existing = persona,
+            None => settings.personas.push(persona),
+        }
+    })?;
+    Ok(saved)
+}
+
+#[tauri::command]
+fn delete_persona(app: tauri::AppHandle, id: String) -> Result<Vec<personas::Persona>, String> {
+    if !settings::current().personas.iter().any(|p| p.id == id) {
+        return Err(format!("Persona '{}' does not exist", id));
+    }
+    let updated = save_settings(&app, |settings| settings.personas.retain(|p| p.id != id))?;
+    Ok(updated.personas)
+}
+
+// Start a conversation with the persona's model, system prompt, options and knowledge
+#[tauri::command]
+fn start_persona_conversation(id: String, title: Option<String>) -> Result<conversations::Conversation, String> {
+    let persona = settings::current()
+        .personas
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("Persona '{}' does not exist", id))?;
+    let options = persona.conversation_options()?;
+    conversations::create_from_persona(title.or(Some(persona.name)), &persona.id, &persona.model, options)
+}
+
+// Write the given personas, or all of them, to a file to share
+#[tauri::command]
+fn export_personas(ids: Option<Vec<String>>, path: String) -> Result<String, String> {
+    let all = settings::current().personas;
+    let selected: Vec<personas::Persona> = match &ids {
+        Some(ids) => ids
+            .iter()
+            .map(|id| {
+                all.iter()
+                    .find(|p| &p.id == id)
+                    .cloned()
+                    .ok_or_else(|| format!("Persona '{}' does not exist", id))
+            })
+            .collect::<Result<_, _>>()?,
+        None => all,
+    };
+    personas::export_to(&selected, Path::new(&path))?;
+    Ok(format!("Exported {} persona(s) to {}", selected.len(), path))
+}
+
+// Add the personas from an exported file. Ids already in use get fresh ones instead of being overwritten.
+#[tauri::command]
+fn import_personas(app: tauri::AppHandle, path: String) -> Result<Vec<personas::Persona>, String> {
+    let mut imported = personas::import_from(Path::new(&path))?;
+
+    let mut existing: Vec<String> = settings::current().personas.into_iter().map(|p| p.id).collect();
+    for persona in imported.iter_mut() {
+        if existing.contains(&persona.id) {
+            persona.id = endpoints::unique_id(&persona.name, &existing);
+        }
+        existing.push(persona.id.clone());
+    }
+
+    let added = imported.clone();
+    save_settings(&app, |settings| settings.personas.extend(imported))?;
+    Ok(added)
+}
+
+#[tauri::command]
+fn list_prompt_templates(tag: Option<String>) -> Vec<templates::PromptTemplate> {
+    templates::list(tag.as_deref())
+}
+
+// Add a template (empty id) or replace an existing one
+#[tauri::command]
+fn save_prompt_template(template: templates::PromptTemplate) -> Result<templates::PromptTemplate, String> {
+    templates::save(template)
+}
+
+#[tauri::command]
+fn delete_prompt_template(id: String) -> Result<(), String> {
+    templates::delete(&id)
+}
+
+// The variables to ask for before rendering; built-ins are filled in without asking
+#[tauri::command]
+fn get_template_variables(id: String) -> Result<Vec<templates::TemplateVariable>, String> {
+    templates::variables(&id)
+}
+
+// Fill in a template. The webview passes the selected text and clipboard, which only it can read.
+#[tauri::command]
+fn render_prompt_template(
+    id: String,
+    values: Option<std::collections::HashMap<String, String>>,
+    selection: Option<String>,
+    clipboard: Option<String>,
+) -> Result<templates::RenderedPrompt, String> {
+    templates::render(&id, &values.unwrap_or_default(), selection, clipboard)
+}
+
+#[tauri::command]
+fn list_template_tags() -> Vec<templates::TagCount> {
+    templates::tags()
+}
+
+#[tauri::command]
+fn rename_template_tag(from: String, to: String) -> Result<usize, String> {
+    templates::rename_tag(&from, &to)
+}
+
+#[tauri::command]
+fn delete_template_tag(tag: String) -> Result<usize, String> {
+    templates::delete_tag(&tag)
+}
+
+// Write a library file with the given templates, or all of them
+#[tauri::command]
+fn export_prompt_templates(ids: Option<Vec<String>>, path: String) -> Result<String, String> {
+    let count = templates::export_to(ids.as_deref(), Path::new(&path))?;
+    Ok(format!("Exported {} prompt template(s) to {}", count, path))
+}
+
+#[tauri::command]
+fn import_prompt_templates(path: String) -> Result<templates::LibraryImport, String> {
+    templates::import_from(Path::new(&path))
+}
+
+#[tauri::command]
+fn get_settings() -> settings::Settings {
+    settings::current()
+}
+
+#[tauri::command]
+fn update_settings(app: tauri::AppHandle, patch: serde_json::Value) -> Result<settings::Settings, String> {
+    let before = settings::current();
+    let updated = settings::update_from_patch(patch)?;
+    notify_settings_changed(&app, &before, &updated);
+    Ok(updated)
+}
+
+// Helper function to persist a settings change and tell the webview about it
+fn save_settings<F>(app: &tauri::AppHandle, apply: F) -> Result<settings::Settings, String>
+where
+    F: FnOnce(&mut settings::Settings),
+{
+    let before = settings::current();
+    let updated = settings::update(apply)?;
+    notify_settings_changed(app, &before, &updated);
+    Ok(updated)
+}
+
+fn notify_settings_changed(app: &tauri::AppHandle, before: &settings::Settings, updated: &settings::Settings) {
+    // Cached model details belong to the server that answered; another endpoint or pool may answer differently
+    if before.endpoints != updated.endpoints
+        || before.pools != updated.pools
+        || before.active_endpoint != updated.active_endpoint
+        || before.active_pool != updated.active_pool
+    {
+        provider::forget_model_details();
+    }
+    if let Err(e) = app.emit(settings::SETTINGS_CHANGED_EVENT, updated) {
+        eprintln!("Failed to emit {}: {}", settings::SETTINGS_CHANGED_EVENT, e);
+    }
+}
+
+#[tauri::command]
+fn get_ollama_url() -> String {
+    get_ollama_base_url()
+}
+
+#[tauri::command]
+async fn check_ollama_service_running() -> bool {
+    // An open port is not enough: make sure the listener actually answers like the configured server
+    provider::active().health().await.healthy
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum EndpointProbeStatus {
+    Ok,
+    Unreachable,
+    AuthenticationFailed,
+    UntrustedCertificate,
+    NotOllama,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EndpointTestResult {
+    status: EndpointProbeStatus,
+    version: Option<String>,
+    http_status: Option<u16>,
+    message: String,
+    // Server certificate of https endpoints, filled in by test_endpoint
+    certificate: Option<tls::CertificateInfo>,
+}
+
+// Helper function to build an HTTP client that sends the endpoint's stored credentials
+fn endpoint_client(endpoint: &endpoints::Endpoint, timeout: Option<Duration>) -> Result<reqwest::Client, String> {
+    endpoint_client_with(endpoint, credentials::headers_for(&endpoint.id)?, timeout, None)
+}
+
+fn endpoint_client_with(
+    endpoint: &endpoints::Endpoint,
+    headers: reqwest::header::HeaderMap,
+    timeout: Option<Duration>,
+    observation: Option<Arc<Mutex<tls::HandshakeObservation>>>,
+) -> Result<reqwest::Client, String> {
+    let mut builder = proxy::client_builder()?.default_headers(headers);
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+    // Endpoints without custom trust keep the platform's default TLS stack
+    if endpoint.scheme == "https" && (!endpoint.tls.is_default() || observation.is_some()) {
+        builder = builder.use_preconfigured_tls(tls::client_config(&endpoint.tls, observation)?);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+// Ask an endpoint for Ollama's /api/version and classify the outcome.
+// 401/403 are reported as authentication failures rather than "not running".
+async fn probe_endpoint(endpoint: &endpoints::Endpoint, client: &reqwest::Client) -> EndpointTestResult {
+    let url = endpoint.api_url("version");
+    let result = |status, http_status: Option<u16>, message: String| EndpointTestResult {
+        status,
+        version: None,
+        http_status,
+        message,
+        certificate: None,
+    };
+
+    let response = match client.get(&url).send().await {
+        Ok(response) => response,
+        Err(e) => return result(EndpointProbeStatus::Unreachable, None, format!("Could not reach {}: {}", url, e)),
+    };
+
+    let http_status = response.status();
+    if http_status == reqwest::StatusCode::UNAUTHORIZED || http_status == reqwest::StatusCode::FORBIDDEN {
+        return result(
+            EndpointProbeStatus::AuthenticationFailed,
+            Some(http_status.as_u16()),
+            format!("{} rejected the request ({}). Check the endpoint's credentials.", url, http_status),
+        );
+    }
+    if !http_status.is_success() {
+        return result(EndpointProbeStatus::NotOllama, Some(http_status.as_u16()), format!("{} answered with {}", url, http_status));
+    }
+
+    let version = response
+        .json::<serde_json::Value>()
+        .await
+        .ok()
+        .and_then(|json| json.get("version").and_then(|v| v.as_str()).map(|v| v.to_string()));
+
+    match version {
+        Some(version) => EndpointTestResult {
+            status: EndpointProbeStatus::Ok,
+            message: format!("Connected to Ollama {} at {}", version, endpoint.base_url()),
+            version: Some(version),
+            http_status: Some(http_status.as_u16()),
+            certificate: None,
+        },
+        None => result(
+            EndpointProbeStatus::NotOllama,
+            Some(http_status.as_u16()),
+            format!("{} did not answer like an Ollama server", url),
+        ),
     }
 }
 
-#[tauri::command]
-fn get_ollama_port_config() -> u16 {
-    get_ollama_port()
+// Helper function to get an endpoint's server version using its stored credentials
+async fn probe_endpoint_version(endpoint: &endpoints::Endpoint) -> Result<String, String> {
+    let client = endpoint_client(endpoint, Some(Duration::from_secs(2)))?;
+    let probe = probe_endpoint(endpoint, &client).await;
+    probe.version.ok_or(probe.message)
 }
 
-#[tauri::command]
-fn set_ollama_port_config(port: u16) -> Result<String, String> {
-    if port < 1024 || port > 65535 {
-        return Err("Port must be between 1024 and 65535".to_string());
-    }
-    
-    std::env::set_var("OLLAMA_PORT", port.to_string());
-    Ok(format!("Ollama port set to {}", port))
+// Helper function to ask a port on this machine for Ollama's /api/version
+async fn probe_ollama_version(port: u16) -> Option<String> {
+    let endpoint = endpoints::Endpoint {
+        host: "127.0.0.1".to_string(),
+        ..endpoints::Endpoint::local(port)
+    };
+    probe_endpoint_version(&endpoint).await.ok()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PortCheck {
+    port: u16,
+    in_use: bool,
+    is_ollama: bool,
+    ollama_version: Option<String>,
+    owner: Option<ports::PortOwner>,
+    suggested_port: Option<u16>,
 }
 
 #[tauri::command]
-fn get_ollama_url() -> String {
-    get_ollama_base_url()
+async fn check_ollama_port(port: Option<u16>) -> Result<PortCheck, String> {
+    let port = port.unwrap_or_else(get_ollama_port);
+    let in_use = ports::port_in_use(port);
+    let ollama_version = if in_use { probe_ollama_version(port).await } else { None };
+    let is_ollama = ollama_version.is_some();
+
+    // Only look up the owner and an alternative port when something else holds the port
+    let conflict = in_use && !is_ollama;
+    let owner = if conflict { ports::find_port_owner(port) } else { None };
+    let suggested_port = if conflict { ports::find_free_port(port.saturating_add(1)).ok() } else { None };
+
+    Ok(PortCheck {
+        port,
+        in_use,
+        is_ollama,
+        ollama_version,
+        owner,
+        suggested_port,
+    })
+}
+
+// Helper function to describe what is holding a port in user-facing messages
+fn describe_port_owner(port: u16) -> String {
+    match ports::find_port_owner(port) {
+        Some(ports::PortOwner { pid, name: Some(name) }) => format!("{} (PID {})", name, pid),
+        Some(ports::PortOwner { pid, name: None }) => format!("PID {}", pid),
+        None => "an unknown process".to_string(),
+    }
 }
 
 #[tauri::command]
-fn check_ollama_service_running() -> bool {
-    // Check if Ollama service is running by attempting to connect to the configured port
-    let port = get_ollama_port();
-    let address = format!("127.0.0.1:{}", port);
-    TcpStream::connect_timeout(
-        &address.parse().unwrap(),
-        Duration::from_millis(1000)
-    ).is_ok()
+async fn start_ollama_on_free_port(app: tauri::AppHandle, timeout_secs: Option<u64>) -> Result<ServiceStartInfo, ServiceStartError> {
+    require_local_endpoint()
+        .map_err(|e| ServiceStartError::new(ServiceStartErrorKind::RemoteEndpoint, e))?;
+
+    let port = ports::find_free_port(get_ollama_port())
+        .map_err(|e| ServiceStartError::new(ServiceStartErrorKind::SpawnFailed, e))?;
+
+    // `ollama serve` picks its listen address from OLLAMA_HOST
+    let spawned = spawn_ollama_serve(Some(format!("127.0.0.1:{}", port)))
+        .map_err(|e| ServiceStartError::new(ServiceStartErrorKind::SpawnFailed, e))?;
+
+    let log_source = StartupLogSource::File(ollama_serve_log_path());
+    let (version, startup_ms) = wait_for_ollama_ready(port, startup_timeout(timeout_secs), Some(spawned.child), &log_source).await?;
+
+    set_ollama_port_config(app, port).map_err(|e| {
+        ServiceStartError::new(
+            ServiceStartErrorKind::ConfigFailed,
+            format!("Ollama {} started on port {}, but the port could not be saved: {}", version, port, e),
+        )
+    })?;
+    Ok(ServiceStartInfo {
+        message: format!("Ollama {} started on free port {} and saved as the configured port", version, port),
+        method: spawned.method,
+        port,
+        version,
+        startup_ms,
+        already_running: false,
+    })
 }
 
 #[tauri::command]
 fn check_ollama_installed() -> Result<String, String> {
     // Try to run ollama --version to check if it's installed and accessible
-    let extended_path = get_extended_path();
-    
-    let ollama_cmd = if cfg!(target_os = "windows") { "ollama.exe" } else { "ollama" };
-    
-    let output = Command::new(ollama_cmd)
+    let raw = read_ollama_cli_version()?;
+
+    // Report a clean semver string when the output can be parsed, the raw output otherwise
+    let (server, client) = version::parse_cli_output(&raw);
+    Ok(client
+        .or(server)
+        .map(|v| v.to_string())
+        .unwrap_or(raw))
+}
+
+// Helper function to run `ollama --version` and return its combined output
+fn read_ollama_cli_version() -> Result<String, String> {
+    let binary = binary::resolve_ollama_binary()?;
+
+    let output = binary.client_command()
         .arg("--version")
-        .env("PATH", &extended_path)
         .output();
 
     match output {
         Ok(output) => {
             if output.status.success() {
-                let version_str = String::from_utf8_lossy(&output.stdout);
-                Ok(version_str.trim().to_string())
+                // The client version is printed as a warning on stderr when no server is running
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                Ok(format!("{}\n{}", stdout.trim(), stderr.trim()).trim().to_string())
             } else {
                 let error_str = String::from_utf8_lossy(&output.stderr);
                 Err(format!("Ollama command failed: {}", error_str))
             }
         }
-        Err(e) => {
-            // On Windows, try absolute paths
-            if cfg!(target_os = "windows") {
-                let userprofile_path = format!("{}\\AppData\\Local\\Programs\\Ollama\\ollama.exe", 
-                    std::env::var("USERPROFILE").unwrap_or_default());
-                let ollama_paths = vec![
-                    "C:\\Program Files\\Ollama\\ollama.exe",
-                    "C:\\Program Files (x86)\\Ollama\\ollama.exe",
-                    &userprofile_path,
-                ];
-                
-                for ollama_path in ollama_paths {
-                    if Path::new(ollama_path).exists() {
-                        let output = Command::new(ollama_path)
-                            .arg("--version")
-                            .output();
-                        
-                        if let Ok(output) = output {
-                            if output.status.success() {
-                                let version_str = String::from_utf8_lossy(&output.stdout);
-                                return Ok(format!("{} (found at {})", version_str.trim(), ollama_path));
-                            }
-                        }
-                    }
-                }
-            }
-            
-            Err(format!("Ollama not found or not accessible: {}", e))
+        Err(e) => Err(format!("Ollama not found or not accessible at {}: {}", binary.display_path(), e)),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaVersionInfo {
+    server_version: Option<String>,
+    cli_version: Option<String>,
+    raw_cli_output: Option<String>,
+    features: Vec<version::FeatureSupport>,
+}
+
+// Server versions by endpoint URL, so feature checks do not probe the server on every request
+const SERVER_VERSION_TTL: Duration = Duration::from_secs(300);
+
+type ServerVersionCache = std::collections::HashMap<String, (Instant, Version)>;
+
+static SERVER_VERSIONS: OnceLock<Mutex<ServerVersionCache>> = OnceLock::new();
+
+fn server_versions() -> std::sync::MutexGuard<'static, ServerVersionCache> {
+    SERVER_VERSIONS
+        .get_or_init(|| Mutex::new(ServerVersionCache::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+// Helper function to get the version of the Ollama server behind an endpoint. Only endpoints on this
+// machine fall back to what the CLI reports; it knows nothing about a remote server.
+async fn endpoint_server_version(endpoint: &endpoints::Endpoint) -> Option<Version> {
+    let key = endpoint.base_url();
+    if let Some((checked_at, version)) = server_versions().get(&key) {
+        if checked_at.elapsed() < SERVER_VERSION_TTL {
+            return Some(version.clone());
+        }
+    }
+
+    if let Some(server) = probe_endpoint_version(endpoint).await.ok().as_deref().and_then(version::parse_version) {
+        server_versions().insert(key, (Instant::now(), server.clone()));
+        return Some(server);
+    }
+    if !endpoint.is_local() {
+        return None;
+    }
+
+    let raw = tokio::task::spawn_blocking(read_ollama_cli_version).await.ok()?.ok()?;
+    let (server, client) = version::parse_cli_output(&raw);
+    server.or(client)
+}
+
+// Return an error when the server behind `endpoint` is too old for any of `features`
+async fn require_endpoint_features(endpoint: &endpoints::Endpoint, features: &[version::OllamaFeature]) -> Result<(), String> {
+    if features.is_empty() {
+        return Ok(());
+    }
+    let detected = endpoint_server_version(endpoint).await;
+    features
+        .iter()
+        .try_for_each(|feature| version::check_feature(detected.as_ref(), *feature))
+}
+
+// Return an error when the active endpoint's Ollama is too old for a feature
+async fn require_ollama_feature(feature: version::OllamaFeature) -> Result<(), String> {
+    require_endpoint_features(&settings::active_endpoint(), &[feature]).await
+}
+
+#[tauri::command]
+async fn get_ollama_version_info() -> Result<OllamaVersionInfo, String> {
+    let raw_cli_output = read_ollama_cli_version().ok();
+    let (cli_server, cli_client) = raw_cli_output
+        .as_deref()
+        .map(version::parse_cli_output)
+        .unwrap_or((None, None));
+
+    let server = probe_endpoint_version(&settings::active_endpoint())
+        .await
+        .ok()
+        .as_deref()
+        .and_then(version::parse_version)
+        .or(cli_server);
+
+    // Features are gated on the server, since that is what handles the requests
+    let features = version::feature_table(server.as_ref().or(cli_client.as_ref()));
+
+    Ok(OllamaVersionInfo {
+        server_version: server.map(|v| v.to_string()),
+        cli_version: cli_client.map(|v| v.to_string()),
+        raw_cli_output,
+        features,
+    })
+}
+
+#[tauri::command]
+async fn check_ollama_feature(feature: version::OllamaFeature) -> Result<(), String> {
+    require_ollama_feature(feature).await
+}
+
+#[tauri::command]
+fn get_ollama_binary_info() -> Result<binary::OllamaBinary, String> {
+    binary::resolve_ollama_binary()
+}
+
+#[tauri::command]
+fn set_ollama_binary_path(app: tauri::AppHandle, path: Option<String>) -> Result<binary::OllamaBinary, String> {
+    // An empty or missing path clears the override and goes back to automatic detection
+    let path = path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if let Some(path) = &path {
+        if !Path::new(path).is_file() {
+            return Err(format!("No file found at {}", path));
         }
     }
+    save_settings(&app, |settings| settings.ollama_binary_path = path)?;
+
+    binary::clear_binary_cache();
+    binary::resolve_ollama_binary()
 }
 
 #[tauri::command]
@@ -171,6 +1259,8 @@ fn check_ollama_installation_paths() -> bool {
 
 #[tauri::command]
 async fn install_ollama_macos() -> Result<String, String> {
+    require_local_endpoint()?;
+
     // Common Homebrew installation paths
     let homebrew_paths = vec![
         "/opt/homebrew/bin/brew",  // Apple Silicon Macs
@@ -278,6 +1368,8 @@ async fn install_ollama_macos() -> Result<String, String> {
 
 #[tauri::command]
 async fn install_ollama_windows() -> Result<String, String> {
+    require_local_endpoint()?;
+
     // On Windows, redirect user to the official download page
     let download_url = "https://ollama.com/download/windows";
     
@@ -293,6 +1385,8 @@ async fn install_ollama_windows() -> Result<String, String> {
 
 #[tauri::command]
 async fn install_ollama_linux() -> Result<String, String> {
+    require_local_endpoint()?;
+
     // On Linux, use the official install script via curl
     let output = Command::new("sh")
         .args(["-c", "curl -fsSL https://ollama.com/install.sh | sh"])
@@ -319,19 +1413,23 @@ async fn install_ollama_linux() -> Result<String, String> {
 
 #[tauri::command]
 async fn download_ollama_model(model_name: String) -> Result<String, String> {
-    // Extended PATH to include platform-specific common locations
-    let extended_path = get_extended_path();
-    
-    let ollama_cmd = if cfg!(target_os = "windows") { "ollama.exe" } else { "ollama" };
-    
-    let output = Command::new(ollama_cmd)
+    require_ollama_provider("Downloading models")?;
+    // Remote endpoints are managed over HTTP; the local CLI may not even be installed
+    if !settings::active_endpoint().is_local() {
+        ollama_api_request(reqwest::Method::POST, "pull", json!({ "model": model_name, "stream": false })).await
+            .map_err(|e| format!("Failed to download model '{}': {}", model_name, e))?;
+        provider::forget_model_details();
+        return Ok(format!("Model '{}' downloaded successfully on {}", model_name, get_ollama_base_url()));
+    }
+
+    let output = binary::ollama_command()?
         .args(["pull", &model_name])
-        .env("PATH", &extended_path)
         .output();
 
     match output {
         Ok(output) => {
             if output.status.success() {
+                provider::forget_model_details();
                 let stdout = String::from_utf8_lossy(&output.stdout);
                 Ok(format!("Model '{}' downloaded successfully. Output: {}", model_name, stdout.trim()))
             } else {
@@ -339,291 +1437,422 @@ async fn download_ollama_model(model_name: String) -> Result<String, String> {
                 Err(format!("Failed to download model '{}': {}", model_name, error))
             }
         }
-        Err(e) => {
-            // On Windows, try absolute paths
-            if cfg!(target_os = "windows") {
-                let userprofile_path = format!("{}\\AppData\\Local\\Programs\\Ollama\\ollama.exe", 
-                    std::env::var("USERPROFILE").unwrap_or_default());
-                let ollama_paths = vec![
-                    "C:\\Program Files\\Ollama\\ollama.exe",
-                    "C:\\Program Files (x86)\\Ollama\\ollama.exe",
-                    &userprofile_path,
-                ];
-                
-                for ollama_path in ollama_paths {
-                    if Path::new(ollama_path).exists() {
-                        let output = Command::new(ollama_path)
-                            .args(["pull", &model_name])
-                            .output();
-                        
-                        if let Ok(output) = output {
-                            if output.status.success() {
-                                let stdout = String::from_utf8_lossy(&output.stdout);
-                                return Ok(format!("Model '{}' downloaded successfully using {}. Output: {}", 
-                                    model_name, ollama_path, stdout.trim()));
-                            } else {
-                                let error = String::from_utf8_lossy(&output.stderr);
-                                return Err(format!("Failed to download model '{}' using {}: {}", 
-                                    model_name, ollama_path, error));
-                            }
-                        }
-                    }
-                }
-            }
-            
-            Err(format!("Failed to run ollama pull: {}. Make sure Ollama is installed and accessible.", e))
-        }
+        Err(e) => Err(format!("Failed to run ollama pull: {}. Make sure Ollama is installed and accessible.", e)),
     }
 }
 
 #[tauri::command]
 async fn list_installed_models() -> Result<Vec<String>, String> {
-    // Extended PATH to include platform-specific common locations
-    let extended_path = get_extended_path();
-    
+    // OpenAI-compatible servers and pools have no CLI to ask
+    if settings::active_endpoint().provider != provider::ProviderKind::Ollama || pool::active_pool().is_some() {
+        return provider::active().list_models().await
+            .map_err(|e| format!("Failed to list models on {}: {}", get_ollama_base_url(), e));
+    }
+
+    if !settings::active_endpoint().is_local() {
+        let json = ollama_api_request(reqwest::Method::GET, "tags", serde_json::Value::Null).await
+            .map_err(|e| format!("Failed to list models on {}: {}", get_ollama_base_url(), e))?;
+        return Ok(model_names_from_json(&json));
+    }
+
     // First try ollama list command
-    let ollama_cmd = if cfg!(target_os = "windows") { "ollama.exe" } else { "ollama" };
-    
-    let output = Command::new(ollama_cmd)
+    let output = binary::ollama_command()?
         .args(["list"])
-        .env("PATH", &extended_path)
         .output();
 
     match output {
         Ok(output) => {
             if output.status.success() {
                 let output_str = String::from_utf8_lossy(&output.stdout);
-                let models: Vec<String> = output_str
-                    .lines()
-                    .skip(1) // Skip header line
-                    .filter_map(|line| {
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if !parts.is_empty() && !parts[0].is_empty() {
-                            Some(parts[0].to_string())
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-                Ok(models)
+                Ok(parse_ollama_list(&output_str))
             } else {
                 let error = String::from_utf8_lossy(&output.stderr);
                 
                 // If ollama list fails, try to use the API directly
-                if cfg!(target_os = "windows") {
-                    // On Windows, try to use curl to call the API
-                    let api_url = get_ollama_api_url("tags");
-                    let api_result = Command::new("curl")
-                        .args(["-s", &api_url])
-                        .output();
-                    
-                    match api_result {
-                        Ok(api_output) if api_output.status.success() => {
-                            let response = String::from_utf8_lossy(&api_output.stdout);
-                            // Simple JSON parsing - look for model names
-                            if response.contains("models") && response.contains("name") {
-                                let mut models = Vec::new();
-                                // Extract model names using simple string parsing
-                                let lines: Vec<&str> = response.lines().collect();
-                                for line in lines {
-                                    if line.contains("\"name\"") {
-                                        if let Some(start) = line.find("\"name\":\"") {
-                                            let start = start + 8; // length of "name":"
-                                            if let Some(end) = line[start..].find("\"") {
-                                                let model_name = &line[start..start + end];
-                                                if !model_name.is_empty() {
-                                                    models.push(model_name.to_string());
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                if !models.is_empty() {
-                                    return Ok(models);
-                                }
-                            }
-                        }
-                        _ => {}
+                if let Ok(json) = ollama_api_request(reqwest::Method::GET, "tags", serde_json::Value::Null).await {
+                    let models = model_names_from_json(&json);
+                    if !models.is_empty() {
+                        return Ok(models);
                     }
                 }
                 
                 Err(format!("Failed to list models using 'ollama list': {}. Make sure Ollama is installed and running.", error))
             }
         }
-        Err(e) => {
-            // Try absolute paths on Windows
-            if cfg!(target_os = "windows") {
-                let userprofile_path = format!("{}\\AppData\\Local\\Programs\\Ollama\\ollama.exe", 
-                    std::env::var("USERPROFILE").unwrap_or_default());
-                let ollama_paths = vec![
-                    "C:\\Program Files\\Ollama\\ollama.exe",
-                    "C:\\Program Files (x86)\\Ollama\\ollama.exe",
-                    &userprofile_path,
-                ];
-                
-                for ollama_path in ollama_paths {
-                    if Path::new(ollama_path).exists() {
-                        let output = Command::new(ollama_path)
-                            .args(["list"])
-                            .output();
-                        
-                        if let Ok(output) = output {
-                            if output.status.success() {
-                                let output_str = String::from_utf8_lossy(&output.stdout);
-                                let models: Vec<String> = output_str
-                                    .lines()
-                                    .skip(1)
-                                    .filter_map(|line| {
-                                        let parts: Vec<&str> = line.split_whitespace().collect();
-                                        if !parts.is_empty() {
-                                            Some(parts[0].to_string())
-                                        } else {
-                                            None
-                                        }
-                                    })
-                                    .collect();
-                                return Ok(models);
-                            }
-                        }
-                    }
-                }
-            }
-            
-            Err(format!("Failed to run 'ollama list': {}. Make sure Ollama is installed and accessible.", e))
-        }
+        Err(e) => Err(format!("Failed to run 'ollama list': {}. Make sure Ollama is installed and accessible.", e)),
     }
 }
 
-#[tauri::command]
-async fn start_ollama_service() -> Result<String, String> {
-    if cfg!(target_os = "windows") {
-        // Windows: Try multiple methods with better error handling
-        let extended_path = get_extended_path();
-        
-        // Method 1: Try to find ollama.exe and run it directly
-        let userprofile_path = format!("{}\\AppData\\Local\\Programs\\Ollama\\ollama.exe", 
-            std::env::var("USERPROFILE").unwrap_or_default());
-        let ollama_paths = vec![
-            "ollama.exe",
-            "C:\\Program Files\\Ollama\\ollama.exe",
-            "C:\\Program Files (x86)\\Ollama\\ollama.exe",
-            &userprofile_path,
-        ];
-        
-        for ollama_path in &ollama_paths {
-            // First check if the path exists (for absolute paths)
-            if ollama_path.contains("\\") && !Path::new(ollama_path).exists() {
-                continue;
-            }
-            
-            #[cfg(target_os = "windows")]
-            let result = Command::new(ollama_path)
-                .args(["serve"])
-                .env("PATH", &extended_path)
-                .creation_flags(0x08000000) // CREATE_NO_WINDOW
-                .spawn();
-            
-            #[cfg(not(target_os = "windows"))]
-            let result = Command::new(ollama_path)
-                .args(["serve"])
-                .env("PATH", &extended_path)
-                .spawn();
-            
-            match result {
-                Ok(_) => return Ok(format!("Ollama service started successfully using: {}", ollama_path)),
-                Err(_) if ollama_path == &"ollama.exe" => {
-                    // Continue trying other paths for ollama.exe
-                    continue;
-                }
-                Err(e) => {
-                    // For absolute paths, log the error but continue
-                    eprintln!("Failed to start Ollama at {}: {}", ollama_path, e);
-                    continue;
-                }
+// Helper function to send a JSON request to the active endpoint's API and return the JSON reply.
+// A Null body sends no body at all.
+async fn ollama_api_request(method: reqwest::Method, endpoint: &str, body: serde_json::Value) -> Result<serde_json::Value, String> {
+    let active = settings::active_endpoint();
+    let mut request = endpoint_client(&active, None)?.request(method, active.api_url(endpoint));
+    if !body.is_null() {
+        request = request.json(&body);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Request to /api/{} failed: {}", endpoint, e))?;
+    read_api_response(endpoint, response).await
+}
+
+// Helper function to send a request that runs `model`, through the active pool when one is selected
+async fn ollama_model_request(endpoint: &str, model: &str, body: serde_json::Value) -> Result<serde_json::Value, String> {
+    let Some((selected, endpoints)) = pool::active_pool() else {
+        return ollama_api_request(reqwest::Method::POST, endpoint, body).await;
+    };
+
+    // Keep the in-flight guard alive until the whole body has been read
+    let pool::PooledResponse { endpoint: member, response, in_flight: _in_flight } =
+        pool::send(&selected, &endpoints, model, &[], |member, client| {
+            client.post(member.api_url(endpoint)).json(&body)
+        })
+        .await?;
+    println!("Pool '{}': /api/{} for {} handled by {}", selected.name, endpoint, model, member.name);
+    read_api_response(endpoint, response).await
+}
+
+async fn read_api_response(endpoint: &str, response: reqwest::Response) -> Result<serde_json::Value, String> {
+    let status = response.status();
+    let text = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read /api/{} response: {}", endpoint, e))?;
+
+    if !status.is_success() {
+        return Err(format!("/api/{} returned {}: {}", endpoint, status, text.trim()));
+    }
+
+    // DELETE /api/delete answers with an empty body
+    if text.trim().is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+    serde_json::from_str(&text).map_err(|e| format!("Failed to parse /api/{} response: {}", endpoint, e))
+}
+
+// Helper function to extract model names from an /api/tags or /api/ps response
+fn model_names_from_json(json: &serde_json::Value) -> Vec<String> {
+    json.get("models")
+        .and_then(|v| v.as_array())
+        .map(|models| {
+            models.iter()
+                .filter_map(|m| m.get("name").and_then(|n| n.as_str()))
+                .map(|name| name.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Helper function to extract model names from `ollama list` output
+fn parse_ollama_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip(1) // Skip header line
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if !parts.is_empty() && !parts[0].is_empty() {
+                Some(parts[0].to_string())
+            } else {
+                None
             }
+        })
+        .collect()
+}
+
+// Default time to wait for a freshly started server to answer /api/version
+const DEFAULT_STARTUP_TIMEOUT_SECS: u64 = 30;
+
+// Number of log lines attached to a failed start
+const STARTUP_LOG_TAIL_LINES: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ServiceStartInfo {
+    message: String,
+    method: String,
+    port: u16,
+    version: String,
+    startup_ms: u64,
+    already_running: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ServiceStartErrorKind {
+    RemoteEndpoint,
+    PortConflict,
+    SpawnFailed,
+    // Ollama is up, but the settings pointing the app at it could not be saved
+    ConfigFailed,
+    Exited,
+    Timeout,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ServiceStartError {
+    kind: ServiceStartErrorKind,
+    message: String,
+    exit_code: Option<i32>,
+    log_lines: Vec<String>,
+}
+
+impl ServiceStartError {
+    fn new(kind: ServiceStartErrorKind, message: String) -> Self {
+        ServiceStartError {
+            kind,
+            message,
+            exit_code: None,
+            log_lines: Vec::new(),
         }
-        
-        // Method 2: Use cmd with start command as fallback
-        let output = Command::new("cmd")
-            .args(["/C", "start", "/B", "ollama", "serve"])
-            .env("PATH", &extended_path)
-            .output();
-        
-        match output {
-            Ok(output) => {
-                if output.status.success() {
-                    Ok("Ollama service started successfully using: cmd /C start /B ollama serve".to_string())
-                } else {
-                    let error = String::from_utf8_lossy(&output.stderr);
-                    Err(format!("Failed to start Ollama service on Windows. Error: {}. Try running 'ollama serve' manually in Command Prompt.", error))
-                }
+    }
+}
+
+// Where the server output goes while we wait for it to come up
+enum StartupLogSource {
+    File(PathBuf),
+    Journal(systemd::SystemdScope, String),
+}
+
+impl StartupLogSource {
+    async fn tail(&self) -> Vec<String> {
+        match self {
+            StartupLogSource::File(path) => std::fs::read_to_string(path)
+                .map(|text| {
+                    let lines: Vec<String> = text.lines().map(|line| line.to_string()).collect();
+                    let skip = lines.len().saturating_sub(STARTUP_LOG_TAIL_LINES);
+                    lines.into_iter().skip(skip).collect()
+                })
+                .unwrap_or_default(),
+            StartupLogSource::Journal(scope, unit) => {
+                let (scope, unit) = (*scope, unit.clone());
+                run_blocking(move || systemd::journal_lines(scope, &unit, STARTUP_LOG_TAIL_LINES))
+                    .await
+                    .unwrap_or_default()
             }
-            Err(e) => Err(format!("Failed to start Ollama service on Windows. Error: {}. Try running 'ollama serve' manually in Command Prompt.", e))
         }
+    }
+}
+
+// Helper function to get the log file used for directly spawned `ollama serve` processes
+fn ollama_serve_log_path() -> PathBuf {
+    std::env::temp_dir().join("beautifyollama-ollama-serve.log")
+}
+
+// Helper function to open (and truncate) the serve log for a new child process.
+// Ollama writes its logs to stderr, so that is the stream captured here.
+fn ollama_serve_log_stdio() -> Stdio {
+    std::fs::File::create(ollama_serve_log_path())
+        .map(Stdio::from)
+        .unwrap_or_else(|_| Stdio::null())
+}
+
+// A server process we spawned ourselves, with a description of how it was launched
+struct SpawnedServer {
+    child: Child,
+    method: String,
+}
+
+// Spawn `ollama serve` from the resolved binary, logging its output to the serve log.
+// `host` overrides OLLAMA_HOST so the server listens where we will look for it.
+fn spawn_ollama_serve(host: Option<String>) -> Result<SpawnedServer, String> {
+    let binary = binary::resolve_ollama_binary()?;
+    // Configured server environment first, so an explicit host still wins
+    let host_env: Vec<(String, String)> = settings::current()
+        .server_env
+        .into_iter()
+        .chain(host.into_iter().map(|h| ("OLLAMA_HOST".to_string(), h)))
+        .collect();
+
+    if cfg!(target_os = "windows") {
+        // Windows: run ollama.exe directly without opening a console window
+        let mut command = binary.command();
+        command
+            .args(["serve"])
+            .envs(host_env)
+            .stdout(Stdio::null())
+            .stderr(ollama_serve_log_stdio());
+
+        #[cfg(target_os = "windows")]
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+        command
+            .spawn()
+            .map(|child| SpawnedServer { child, method: binary.display_path() })
+            .map_err(|e| format!("Failed to start Ollama service on Windows using {}. Error: {}. Try running 'ollama serve' manually in Command Prompt.", binary.display_path(), e))
     } else {
-        // macOS/Linux: Try multiple methods with proper PATH handling
-        
-        // Extended PATH to include platform-specific common locations
-        let extended_path = get_extended_path();
-        
-        // Method 1: Use nohup for proper daemonization
+        // macOS/Linux: Use nohup for proper daemonization
         let nohup_result = Command::new("nohup")
-            .args(["ollama", "serve"])
-            .env("PATH", &extended_path)
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
+            .arg(&binary.path)
+            .arg("serve")
+            .env("PATH", get_extended_path())
+            .envs(host_env.clone())
+            .stdout(Stdio::null())
+            .stderr(ollama_serve_log_stdio())
             .spawn();
             
         match nohup_result {
-            Ok(_) => Ok("Ollama service started successfully. Command: 'nohup ollama serve' (daemonized)".to_string()),
+            Ok(child) => Ok(SpawnedServer { child, method: format!("nohup {} serve (daemonized)", binary.display_path()) }),
             Err(_) => {
-                // Fallback: Direct spawn with extended PATH
-                match Command::new("ollama")
+                // Fallback: Direct spawn when nohup is not available
+                binary.command()
                     .args(["serve"])
-                    .env("PATH", &extended_path)
-                    .stdout(std::process::Stdio::null())
-                    .stderr(std::process::Stdio::null())
-                    .spawn() {
-                    Ok(_) => Ok("Ollama service started successfully. Command: 'ollama serve' (background process)".to_string()),
-                    Err(e) => {
-                        // Final fallback: Try using bash with full path resolution
-                        let bash_cmd = "export PATH=\"/opt/homebrew/bin:/usr/local/bin:$PATH\" && ollama serve &";
-                        match Command::new("bash")
-                            .args(["-c", bash_cmd])
-                            .spawn() {
-                            Ok(_) => Ok("Ollama service started successfully using bash with extended PATH".to_string()),
-                            Err(bash_err) => {
-                                // Last resort: Try direct paths
-                                let ollama_paths = vec![
-                                    "/opt/homebrew/bin/ollama",
-                                    "/usr/local/bin/ollama",
-                                ];
-                                
-                                for ollama_path in ollama_paths {
-                                    if Path::new(ollama_path).exists() {
-                                        match Command::new(ollama_path)
-                                            .args(["serve"])
-                                            .stdout(std::process::Stdio::null())
-                                            .stderr(std::process::Stdio::null())
-                                            .spawn() {
-                                            Ok(_) => return Ok(format!("Ollama service started successfully using direct path: {}", ollama_path)),
-                                            Err(_) => continue,
-                                        }
-                                    }
-                                }
-                                
-                                Err(format!("Failed to start Ollama service. Direct spawn error: {}. Bash error: {}. No ollama executable found in common paths.", e, bash_err))
-                            }
-                        }
-                    }
+                    .envs(host_env)
+                    .stdout(Stdio::null())
+                    .stderr(ollama_serve_log_stdio())
+                    .spawn()
+                    .map(|child| SpawnedServer { child, method: format!("{} serve (background process)", binary.display_path()) })
+                    .map_err(|e| format!("Failed to start Ollama service using {}: {}", binary.display_path(), e))
+            }
+        }
+    }
+}
+
+// Poll /api/version until the server answers, the process exits, or the timeout passes.
+// Returns the server version and how long startup took.
+async fn wait_for_ollama_ready(
+    port: u16,
+    timeout: Duration,
+    mut child: Option<Child>,
+    log_source: &StartupLogSource,
+) -> Result<(String, u64), ServiceStartError> {
+    let started = Instant::now();
+
+    loop {
+        if let Some(version) = probe_ollama_version(port).await {
+            return Ok((version, started.elapsed().as_millis() as u64));
+        }
+
+        if let Some(process) = child.as_mut() {
+            if let Ok(Some(status)) = process.try_wait() {
+                return Err(ServiceStartError {
+                    kind: ServiceStartErrorKind::Exited,
+                    message: format!("Ollama exited during startup ({})", status),
+                    exit_code: status.code(),
+                    log_lines: log_source.tail().await,
+                });
+            }
+        }
+
+        if let StartupLogSource::Journal(scope, unit) = log_source {
+            let (scope, name) = (*scope, unit.clone());
+            let status = tokio::task::spawn_blocking(move || systemd::unit_status(scope, &name)).await.ok().flatten();
+            if let Some(status) = status {
+                if status.active_state == "failed" {
+                    return Err(ServiceStartError {
+                        kind: ServiceStartErrorKind::Exited,
+                        message: format!("{} failed during startup ({})", unit, status.sub_state),
+                        exit_code: None,
+                        log_lines: log_source.tail().await,
+                    });
                 }
             }
         }
+
+        if started.elapsed() >= timeout {
+            return Err(ServiceStartError {
+                kind: ServiceStartErrorKind::Timeout,
+                message: format!(
+                    "Ollama did not answer on port {} within {} seconds",
+                    port,
+                    timeout.as_secs()
+                ),
+                exit_code: None,
+                log_lines: log_source.tail().await,
+            });
+        }
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+// Helper function to turn a timeout argument from the frontend into a Duration
+fn startup_timeout(timeout_secs: Option<u64>) -> Duration {
+    Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_STARTUP_TIMEOUT_SECS).max(1))
+}
+
+#[tauri::command]
+async fn start_ollama_service(timeout_secs: Option<u64>) -> Result<ServiceStartInfo, ServiceStartError> {
+    require_local_endpoint()
+        .map_err(|e| ServiceStartError::new(ServiceStartErrorKind::RemoteEndpoint, e))?;
+
+    let timeout = startup_timeout(timeout_secs);
+    let port = get_ollama_port();
+
+    // Linux: let systemd manage the server when the install script created a unit for it
+    if let Some(unit) = detect_ollama_unit().await {
+        let mut method = format!("systemd ({} {} unit)", unit.unit, scope_label(unit.scope));
+        if unit.is_active() {
+            method = format!("{}, already active", method);
+        } else {
+            run_unit_action(&unit, "start")
+                .await
+                .map_err(|e| ServiceStartError::new(ServiceStartErrorKind::SpawnFailed, e))?;
+        }
+
+        let log_source = StartupLogSource::Journal(unit.scope, unit.unit.clone());
+        let (version, startup_ms) = wait_for_ollama_ready(port, timeout, None, &log_source).await?;
+        return Ok(ServiceStartInfo {
+            message: format!("Ollama {} is running via {}", version, method),
+            method,
+            port,
+            version,
+            startup_ms,
+            already_running: unit.is_active(),
+        });
+    }
+
+    // Refuse to spawn a second server into a port some other program already holds
+    if ports::port_in_use(port) {
+        if let Some(version) = probe_ollama_version(port).await {
+            return Ok(ServiceStartInfo {
+                message: format!("Ollama service is already running on port {}", port),
+                method: "existing process".to_string(),
+                port,
+                version,
+                startup_ms: 0,
+                already_running: true,
+            });
+        }
+        return Err(ServiceStartError::new(
+            ServiceStartErrorKind::PortConflict,
+            format!(
+                "Port {} is in use by {}, which is not Ollama. Stop that program or start Ollama on a free port instead.",
+                port,
+                describe_port_owner(port)
+            ),
+        ));
     }
+
+    // Only override OLLAMA_HOST when the configured port differs from Ollama's own default
+    let host = (port != DEFAULT_OLLAMA_PORT).then(|| format!("127.0.0.1:{}", port));
+    let spawned = spawn_ollama_serve(host)
+        .map_err(|e| ServiceStartError::new(ServiceStartErrorKind::SpawnFailed, e))?;
+
+    let log_source = StartupLogSource::File(ollama_serve_log_path());
+    let (version, startup_ms) = wait_for_ollama_ready(port, timeout, Some(spawned.child), &log_source).await?;
+    Ok(ServiceStartInfo {
+        message: format!("Ollama {} started successfully in {} ms using: {}", version, startup_ms, spawned.method),
+        method: spawned.method,
+        port,
+        version,
+        startup_ms,
+        already_running: false,
+    })
 }
 
 #[tauri::command]
 async fn stop_ollama_service() -> Result<String, String> {
+    require_local_endpoint()?;
+
+    // Stopping a systemd-managed server with pkill would just get it restarted by the unit
+    if let Some(unit) = detect_ollama_unit().await {
+        return run_unit_action(&unit, "stop")
+            .await
+            .map(|_| format!("Ollama service stopped successfully via systemd ({} {} unit)", unit.unit, scope_label(unit.scope)));
+    }
+
     let output = if cfg!(target_os = "windows") {
         Command::new("taskkill")
             .args(["/F", "/IM", "ollama.exe"])
@@ -648,37 +1877,91 @@ async fn stop_ollama_service() -> Result<String, String> {
 }
 
 #[tauri::command]
-async fn load_ollama_model(model_name: String) -> Result<String, String> {
-    // Load a model by making a simple request to it
-    let api_url = get_ollama_api_url("generate");
-    let output = Command::new("curl")
-        .args([
-            "-X", "POST",
-            &api_url,
-            "-H", "Content-Type: application/json",
-            "-d", &format!(r#"{{"model": "{}", "prompt": "hello", "stream": false}}"#, model_name)
-        ])
-        .output();
+async fn restart_ollama_service(timeout_secs: Option<u64>) -> Result<ServiceStartInfo, ServiceStartError> {
+    require_local_endpoint()
+        .map_err(|e| ServiceStartError::new(ServiceStartErrorKind::RemoteEndpoint, e))?;
+
+    if let Some(unit) = detect_ollama_unit().await {
+        run_unit_action(&unit, "restart")
+            .await
+            .map_err(|e| ServiceStartError::new(ServiceStartErrorKind::SpawnFailed, e))?;
+
+        let port = get_ollama_port();
+        let method = format!("systemd ({} {} unit)", unit.unit, scope_label(unit.scope));
+        let log_source = StartupLogSource::Journal(unit.scope, unit.unit.clone());
+        let (version, startup_ms) = wait_for_ollama_ready(port, startup_timeout(timeout_secs), None, &log_source).await?;
+        return Ok(ServiceStartInfo {
+            message: format!("Ollama {} restarted successfully via {}", version, method),
+            method,
+            port,
+            version,
+            startup_ms,
+            already_running: false,
+        });
+    }
 
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                // Check if the response contains an error
-                if stdout.contains("error") {
-                    Err(format!("Failed to load model '{}': {}", model_name, stdout))
-                } else {
-                    Ok(format!("Model '{}' loaded successfully. API Response received. Command: curl -X POST {}", model_name, api_url))
-                }
-            } else {
-                let error = String::from_utf8_lossy(&output.stderr);
-                Err(format!("Failed to load model '{}': {}", model_name, error))
-            }
-        }
-        Err(e) => Err(format!("Failed to load model: {}", e)),
+    // Fallback: stop the raw process (ignoring "nothing to stop" failures) and spawn it again
+    let _ = stop_ollama_service().await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    start_ollama_service(timeout_secs).await
+}
+
+#[tauri::command]
+async fn get_ollama_systemd_status() -> Option<systemd::SystemdUnitStatus> {
+    // Returns None when systemd is not in use or no ollama.service unit is installed
+    detect_ollama_unit().await
+}
+
+#[tauri::command]
+async fn get_ollama_service_logs(lines: Option<usize>) -> Result<Vec<String>, String> {
+    require_local_endpoint()?;
+
+    let unit = detect_ollama_unit()
+        .await
+        .ok_or_else(|| "No systemd unit found for Ollama; service logs are only available for systemd-managed installs".to_string())?;
+
+    let lines = lines.unwrap_or(systemd::DEFAULT_JOURNAL_LINES);
+    run_blocking(move || systemd::journal_lines(unit.scope, &unit.unit, lines)).await
+}
+
+// systemctl calls run on a blocking thread; a start or stop can wait on pkexec's password prompt
+async fn detect_ollama_unit() -> Option<systemd::SystemdUnitStatus> {
+    tokio::task::spawn_blocking(systemd::detect_ollama_unit).await.ok().flatten()
+}
+
+async fn run_unit_action(unit: &systemd::SystemdUnitStatus, action: &'static str) -> Result<String, String> {
+    let (scope, name) = (unit.scope, unit.unit.clone());
+    run_blocking(move || systemd::run_unit_action(scope, &name, action)).await
+}
+
+// Helper function to describe a systemd scope in user-facing messages
+fn scope_label(scope: systemd::SystemdScope) -> &'static str {
+    match scope {
+        systemd::SystemdScope::System => "system",
+        systemd::SystemdScope::User => "user",
     }
 }
 
+#[tauri::command]
+async fn load_ollama_model(model_name: String) -> Result<String, String> {
+    require_ollama_provider("Preloading models")?;
+    // A generate request without a prompt loads the model into memory
+    ollama_model_request("generate", &model_name, json!({ "model": model_name, "stream": false })).await
+        .map_err(|e| format!("Failed to load model '{}': {}", model_name, e))?;
+
+    Ok(format!("Model '{}' loaded successfully. API Response received. Request: POST {}", model_name, get_ollama_api_url("generate")))
+}
+
+#[tauri::command]
+async fn list_running_models() -> Result<Vec<String>, String> {
+    require_ollama_provider("Listing running models")?;
+    require_ollama_feature(version::OllamaFeature::PsApi).await?;
+
+    let json = ollama_api_request(reqwest::Method::GET, "ps", serde_json::Value::Null).await
+        .map_err(|e| format!("Failed to query running models: {}", e))?;
+    Ok(model_names_from_json(&json))
+}
+
 #[tauri::command]
 async fn unload_ollama_model() -> Result<String, String> {
     // Unload model by stopping the ollama service temporarily or using API if available
@@ -688,17 +1971,22 @@ async fn unload_ollama_model() -> Result<String, String> {
 
 #[tauri::command]
 async fn uninstall_ollama_model(model_name: String) -> Result<String, String> {
-    // Extended PATH to include platform-specific common locations
-    let extended_path = get_extended_path();
-    
-    let output = Command::new("ollama")
+    require_ollama_provider("Removing models")?;
+    if !settings::active_endpoint().is_local() {
+        ollama_api_request(reqwest::Method::DELETE, "delete", json!({ "model": model_name })).await
+            .map_err(|e| format!("Failed to uninstall model '{}': {}", model_name, e))?;
+        provider::forget_model_details();
+        return Ok(format!("Model '{}' uninstalled successfully", model_name));
+    }
+
+    let output = binary::ollama_command()?
         .args(["rm", &model_name])
-        .env("PATH", &extended_path)
         .output();
 
     match output {
         Ok(output) => {
             if output.status.success() {
+                provider::forget_model_details();
                 Ok(format!("Model '{}' uninstalled successfully", model_name))
             } else {
                 let error = String::from_utf8_lossy(&output.stderr);
@@ -712,27 +2000,14 @@ async fn uninstall_ollama_model(model_name: String) -> Result<String, String> {
 #[tauri::command]
 async fn scan_for_models() -> Result<Vec<String>, String> {
     // Try to run ollama list to get the most up-to-date model list
-    let output = Command::new("ollama")
-        .args(["list"])
-        .output();
+    let output = binary::ollama_command()
+        .and_then(|mut command| command.args(["list"]).output().map_err(|e| e.to_string()));
 
     match output {
         Ok(output) => {
             if output.status.success() {
                 let output_str = String::from_utf8_lossy(&output.stdout);
-                let models: Vec<String> = output_str
-                    .lines()
-                    .skip(1) // Skip header line
-                    .filter_map(|line| {
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if !parts.is_empty() {
-                            Some(parts[0].to_string())
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-                Ok(models)
+                Ok(parse_ollama_list(&output_str))
             } else {
                 // If ollama list fails, try to scan common model directories
                 let mut found_models = Vec::new();
@@ -779,39 +2054,19 @@ async fn scan_for_models() -> Result<Vec<String>, String> {
 }
 
 #[tauri::command]
-fn check_ollama_service_status() -> Result<bool, String> {
-    // Primary method: Check if Ollama service is running by testing TCP connection to the configured port
-    if check_ollama_service_running() {
+async fn check_ollama_service_status() -> Result<bool, String> {
+    // Primary method: Check if Ollama service is running by asking the configured port for its version
+    if check_ollama_service_running().await {
         return Ok(true);
     }
 
-    // Secondary method: Try to call the API directly
-    let api_url = get_ollama_api_url("tags");
-    let output = Command::new("curl")
-        .args([
-            "-s",
-            "--connect-timeout", "2",
-            "--max-time", "3",
-            &api_url
-        ])
-        .output();
-
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                let response = String::from_utf8_lossy(&output.stdout);
-                // If we get a JSON response, the service is running
-                Ok(response.contains("models") || response.starts_with("{"))
-            } else {
-                // Fallback: Check for process (less reliable but works if TCP check fails)
-                check_process_running()
-            }
-        }
-        Err(_) => {
-            // Fallback: Check for process (less reliable but works if curl isn't available)
-            check_process_running()
-        }
+    // A local process check says nothing about a remote server
+    if !settings::active_endpoint().is_local() {
+        return Ok(false);
     }
+
+    // Fallback: Check for process (less reliable but works if the API check fails)
+    check_process_running()
 }
 
 fn check_process_running() -> Result<bool, String> {
@@ -841,6 +2096,8 @@ fn check_process_running() -> Result<bool, String> {
 
 #[tauri::command]
 async fn fix_windows_ollama_service() -> Result<String, String> {
+    require_local_endpoint()?;
+
     if !cfg!(target_os = "windows") {
         return Err("This function is only for Windows".to_string());
     }
@@ -860,28 +2117,11 @@ async fn fix_windows_ollama_service() -> Result<String, String> {
     
     // 3. Find the best Ollama installation
     fix_info.push_str("\n2. Finding Ollama installation...\n");
-    let userprofile_path = format!("{}\\AppData\\Local\\Programs\\Ollama\\ollama.exe", 
-        std::env::var("USERPROFILE").unwrap_or_default());
-    let ollama_paths = vec![
-        "C:\\Program Files\\Ollama\\ollama.exe",
-        "C:\\Program Files (x86)\\Ollama\\ollama.exe",
-        &userprofile_path,
-    ];
-    
-    let mut best_ollama_path = None;
-    for path in &ollama_paths {
-        if Path::new(path).exists() {
-            fix_info.push_str(&format!("   ✓ Found Ollama at: {}\n", path));
-            best_ollama_path = Some(path.to_string());
-            break;
-        }
-    }
-    
-    if best_ollama_path.is_none() {
-        return Err("Ollama executable not found in common locations. Please reinstall Ollama.".to_string());
-    }
-    
-    let ollama_path = best_ollama_path.unwrap();
+    binary::clear_binary_cache();
+    let ollama_path = binary::resolve_ollama_binary()
+        .map_err(|e| format!("{} Please reinstall Ollama.", e))?
+        .path;
+    fix_info.push_str(&format!("   ✓ Found Ollama at: {}\n", ollama_path.display()));
     
     // 4. Start the service properly
     fix_info.push_str("\n3. Starting Ollama service...\n");
@@ -902,7 +2142,7 @@ async fn fix_windows_ollama_service() -> Result<String, String> {
             
             while attempts < max_attempts {
                 std::thread::sleep(std::time::Duration::from_secs(1));
-                if check_ollama_service_running() {
+                if check_ollama_service_running().await {
                     fix_info.push_str(&format!("   ✓ Service is running! (took {} seconds)\n", attempts + 1));
                     
                     // 6. Try to list models to verify everything works
@@ -956,25 +2196,26 @@ async fn diagnose_windows_ollama_issues() -> Result<String, String> {
     
     // 1. Check if Ollama is installed in common locations
     diagnostic_info.push_str("1. Checking Ollama installation paths:\n");
-    let userprofile_path = format!("{}\\AppData\\Local\\Programs\\Ollama\\ollama.exe", 
-        std::env::var("USERPROFILE").unwrap_or_default());
-    let ollama_paths = vec![
-        "C:\\Program Files\\Ollama\\ollama.exe",
-        "C:\\Program Files (x86)\\Ollama\\ollama.exe",
-        &userprofile_path,
-    ];
-    
-    let mut ollama_found_at = None;
-    for path in &ollama_paths {
-        if Path::new(path).exists() {
-            diagnostic_info.push_str(&format!("   ✓ Found: {}\n", path));
-            if ollama_found_at.is_none() {
-                ollama_found_at = Some(path.to_string());
-            }
+    for path in binary::known_locations() {
+        if path.is_file() {
+            diagnostic_info.push_str(&format!("   ✓ Found: {}\n", path.display()));
         } else {
-            diagnostic_info.push_str(&format!("   ✗ Not found: {}\n", path));
+            diagnostic_info.push_str(&format!("   ✗ Not found: {}\n", path.display()));
         }
     }
+
+    // The resolver also honours a custom binary path and PATH lookups
+    binary::clear_binary_cache();
+    let ollama_found_at = match binary::resolve_ollama_binary() {
+        Ok(binary) => {
+            diagnostic_info.push_str(&format!("   → Using: {} ({:?})\n", binary.display_path(), binary.source));
+            Some(binary.path)
+        }
+        Err(e) => {
+            diagnostic_info.push_str(&format!("   ✗ {}\n", e));
+            None
+        }
+    };
     
     // 2. Check if ollama.exe is in PATH
     diagnostic_info.push_str("\n2. Checking if ollama.exe is accessible via PATH:\n");
@@ -1016,7 +2257,7 @@ async fn diagnose_windows_ollama_issues() -> Result<String, String> {
     
     // 4. Check if Ollama service is running (TCP connection)
     diagnostic_info.push_str("\n4. Checking if Ollama service is running:\n");
-    if check_ollama_service_running() {
+    if check_ollama_service_running().await {
         diagnostic_info.push_str("   ✓ Ollama service is responding on port 11434\n");
         
         // 5. Try to list models via API
@@ -1046,7 +2287,7 @@ async fn diagnose_windows_ollama_issues() -> Result<String, String> {
         // Try to start the service
         diagnostic_info.push_str("\n5. Attempting to start Ollama service:\n");
         if let Some(ollama_path) = &ollama_found_at {
-            diagnostic_info.push_str(&format!("   Trying to start: {} serve\n", ollama_path));
+            diagnostic_info.push_str(&format!("   Trying to start: {} serve\n", ollama_path.display()));
             let start_result = Command::new(ollama_path)
                 .args(["serve"])
                 .stdout(std::process::Stdio::null())
@@ -1058,7 +2299,7 @@ async fn diagnose_windows_ollama_issues() -> Result<String, String> {
                     diagnostic_info.push_str("   ✓ Service start command executed\n");
                     std::thread::sleep(std::time::Duration::from_secs(3));
                     
-                    if check_ollama_service_running() {
+                    if check_ollama_service_running().await {
                         diagnostic_info.push_str("   ✓ Service is now running!\n");
                     } else {
                         diagnostic_info.push_str("   ⚠ Service started but not responding yet\n");
@@ -1097,14 +2338,27 @@ async fn diagnose_windows_ollama_issues() -> Result<String, String> {
 
 #[tauri::command]
 async fn ask_ollama_verbose(model: String, prompt: String) -> Result<String, String> {
-    // Extended PATH to include platform-specific common locations
-    let extended_path = get_extended_path();
-    
-    let ollama_cmd = if cfg!(target_os = "windows") { "ollama.exe" } else { "ollama" };
-    
-    let output = Command::new(ollama_cmd)
+    let active_provider = provider::active();
+    if active_provider.kind() != provider::ProviderKind::Ollama {
+        let request = provider::ChatRequest::prompt(model, prompt);
+        let result = active_provider.chat(&request, &mut |_| {}).await?;
+        return Ok(format_chat_result(&result));
+    }
+
+    // The CLI cannot send endpoint credentials or pick a pool member, so those are asked over the API
+    if !settings::active_endpoint().is_local() || pool::active_pool().is_some() {
+        let json = ollama_model_request("generate", &model, json!({
+            "model": model,
+            "prompt": prompt,
+            "stream": false
+        })).await?;
+        return Ok(format_generate_response(&json));
+    }
+
+    let binary = binary::resolve_ollama_binary()?;
+
+    let output = binary.client_command()
         .args(["run", &model, "--verbose", &prompt])
-        .env("PATH", &extended_path)
         .output();
 
     match output {
@@ -1115,46 +2369,13 @@ async fn ask_ollama_verbose(model: String, prompt: String) -> Result<String, Str
                 
                 // Combine both stdout and stderr
                 let combined_output = format!("{}{}", stdout, stderr);
-                
-                // Split into content and stats
-                let lines: Vec<&str> = combined_output.lines().collect();
-                let mut content_lines = Vec::new();
-                let mut stats_lines = Vec::new();
-                let mut in_stats = false;
-                
-                for line in lines {
-                    if line.contains("total duration:") || line.contains("load duration:") || 
-                       line.contains("prompt eval") || line.contains("eval count:") || 
-                       line.contains("eval duration:") || line.contains("eval rate:") {
-                        in_stats = true;
-                        stats_lines.push(line);
-                    } else if in_stats && (line.trim().is_empty() || 
-                            line.contains("also web search") || line.contains("Suggested search:")) {
-                        // End of stats, this line is part of content
-                        content_lines.push(line);
-                        in_stats = false;
-                    } else if !in_stats {
-                        content_lines.push(line);
-                    } else {
-                        stats_lines.push(line);
-                    }
-                }
-                
-                let content = content_lines.join("\n").trim().to_string();
-                let stats = if !stats_lines.is_empty() {
-                    format!("\n\n=== VERBOSE STATS ===\n{}", stats_lines.join("\n"))
-                } else {
-                    String::new()
-                };
-                
-                Ok(format!("{}{}", content, stats))
+                Ok(split_verbose_output(&combined_output))
             } else {
                 let error = String::from_utf8_lossy(&output.stderr);
                 
                 // Try alternative without --verbose flag as fallback
-                let fallback_output = Command::new(ollama_cmd)
+                let fallback_output = binary.client_command()
                     .args(["run", &model, &prompt])
-                    .env("PATH", &extended_path)
                     .output();
                 
                 match fallback_output {
@@ -1166,72 +2387,250 @@ async fn ask_ollama_verbose(model: String, prompt: String) -> Result<String, Str
                 }
             }
         }
-        Err(e) => {
-            // Try absolute paths on Windows
-            if cfg!(target_os = "windows") {
-                let userprofile_path = format!("{}\\AppData\\Local\\Programs\\Ollama\\ollama.exe", 
-                    std::env::var("USERPROFILE").unwrap_or_default());
-                let ollama_paths = vec![
-                    "C:\\Program Files\\Ollama\\ollama.exe",
-                    "C:\\Program Files (x86)\\Ollama\\ollama.exe",
-                    &userprofile_path,
-                ];
-                
-                for ollama_path in ollama_paths {
-                    if Path::new(ollama_path).exists() {
-                        let output = Command::new(ollama_path)
-                            .args(["run", &model, "--verbose", &prompt])
-                            .output();
-                        
-                        if let Ok(output) = output {
-                            if output.status.success() {
-                                let stdout = String::from_utf8_lossy(&output.stdout);
-                                let stderr = String::from_utf8_lossy(&output.stderr);
-                                
-                                // Combine both stdout and stderr
-                                let combined_output = format!("{}{}", stdout, stderr);
-                                
-                                // Split into content and stats
-                                let lines: Vec<&str> = combined_output.lines().collect();
-                                let mut content_lines = Vec::new();
-                                let mut stats_lines = Vec::new();
-                                let mut in_stats = false;
-                                
-                                for line in lines {
-                                    if line.contains("total duration:") || line.contains("load duration:") || 
-                                       line.contains("prompt eval") || line.contains("eval count:") || 
-                                       line.contains("eval duration:") || line.contains("eval rate:") {
-                                        in_stats = true;
-                                        stats_lines.push(line);
-                                    } else if in_stats && (line.trim().is_empty() || 
-                                            line.contains("also web search") || line.contains("Suggested search:")) {
-                                        // End of stats, this line is part of content
-                                        content_lines.push(line);
-                                        in_stats = false;
-                                    } else if !in_stats {
-                                        content_lines.push(line);
-                                    } else {
-                                        stats_lines.push(line);
-                                    }
-                                }
-                                
-                                let content = content_lines.join("\n").trim().to_string();
-                                let stats = if !stats_lines.is_empty() {
-                                    format!("\n\n=== VERBOSE STATS ===\n{}", stats_lines.join("\n"))
-                                } else {
-                                    String::new()
-                                };
-                                
-                                return Ok(format!("{}{}", content, stats));
-                            }
-                        }
-                    }
-                }
-            }
-            
-            Err(format!("Failed to run ollama verbose command: {}", e))
+        Err(e) => Err(format!("Failed to run ollama verbose command: {}", e)),
+    }
+}
+
+// Helper function to render a provider chat result with the token counts it reported
+fn format_chat_result(result: &provider::ChatResult) -> String {
+    let mut stats_lines = Vec::new();
+    if let Some(prompt_tokens) = result.prompt_tokens {
+        stats_lines.push(format!("prompt eval count:    {} token(s)", prompt_tokens));
+    }
+    if let Some(completion_tokens) = result.completion_tokens {
+        stats_lines.push(format!("eval count:           {} token(s)", completion_tokens));
+    }
+
+    let content = result.content.trim().to_string();
+    if stats_lines.is_empty() {
+        content
+    } else {
+        format!("{}\n\n=== VERBOSE STATS ===\n{}", content, stats_lines.join("\n"))
+    }
+}
+
+// Helper function to render an /api/generate reply like `ollama run --verbose` output
+fn format_generate_response(json: &serde_json::Value) -> String {
+    let content = json.get("response").and_then(|v| v.as_str()).unwrap_or_default().trim().to_string();
+
+    // Durations are reported in nanoseconds
+    let seconds = |key: &str| json.get(key).and_then(|v| v.as_u64()).map(|ns| ns as f64 / 1e9);
+    let count = |key: &str| json.get(key).and_then(|v| v.as_u64());
+
+    let mut stats_lines = Vec::new();
+    if let Some(total) = seconds("total_duration") {
+        stats_lines.push(format!("total duration:       {:.6}s", total));
+    }
+    if let Some(load) = seconds("load_duration") {
+        stats_lines.push(format!("load duration:        {:.6}s", load));
+    }
+    if let Some(prompt_count) = count("prompt_eval_count") {
+        stats_lines.push(format!("prompt eval count:    {} token(s)", prompt_count));
+    }
+    if let Some(eval_count) = count("eval_count") {
+        stats_lines.push(format!("eval count:           {} token(s)", eval_count));
+    }
+    if let (Some(eval_count), Some(eval_seconds)) = (count("eval_count"), seconds("eval_duration")) {
+        stats_lines.push(format!("eval duration:        {:.6}s", eval_seconds));
+        if eval_seconds > 0.0 {
+            stats_lines.push(format!("eval rate:            {:.2} tokens/s", eval_count as f64 / eval_seconds));
+        }
+    }
+
+    if stats_lines.is_empty() {
+        content
+    } else {
+        format!("{}\n\n=== VERBOSE STATS ===\n{}", content, stats_lines.join("\n"))
+    }
+}
+
+// Helper function to separate the model's answer from the `--verbose` timing stats
+fn split_verbose_output(combined_output: &str) -> String {
+    // Split into content and stats
+    let lines: Vec<&str> = combined_output.lines().collect();
+    let mut content_lines = Vec::new();
+    let mut stats_lines = Vec::new();
+    let mut in_stats = false;
+    
+    for line in lines {
+        if line.contains("total duration:") || line.contains("load duration:") || 
+           line.contains("prompt eval") || line.contains("eval count:") || 
+           line.contains("eval duration:") || line.contains("eval rate:") {
+            in_stats = true;
+            stats_lines.push(line);
+        } else if in_stats && (line.trim().is_empty() || 
+                line.contains("also web search") || line.contains("Suggested search:")) {
+            // End of stats, this line is part of content
+            content_lines.push(line);
+            in_stats = false;
+        } else if !in_stats {
+            content_lines.push(line);
+        } else {
+            stats_lines.push(line);
+        }
+    }
+    
+    let content = content_lines.join("\n").trim().to_string();
+    let stats = if !stats_lines.is_empty() {
+        format!("\n\n=== VERBOSE STATS ===\n{}", stats_lines.join("\n"))
+    } else {
+        String::new()
+    };
+    
+    format!("{}{}", content, stats)
+}
+
+// Event carrying streamed chat text; `request_id` lets the webview tell concurrent chats apart
+const CHAT_STREAM_EVENT: &str = "chat-stream";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ChatStreamEvent {
+    request_id: String,
+    delta: String,
+    // The delta is reasoning, to be shown apart from the answer
+    thinking: bool,
+    done: bool,
+}
+
+// Event sent before a chat starts, telling the webview how the messages fit the context window
+const CHAT_CONTEXT_EVENT: &str = "chat-context";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ChatContextEvent {
+    request_id: String,
+    report: context::ContextReport,
+}
+
+// Helper function to give a request that names a conversation the conversation's options,
+// and its model when the request has none. A request still without a system prompt then gets
+// the prompt preset from the settings. Returns how many messages were put in front of the UI's.
+fn apply_conversation_defaults(request: &mut provider::ChatRequest) -> Result<usize, String> {
+    let mut inserted = false;
+    if let Some(conversation_id) = request.conversation_id.clone() {
+        inserted = conversations::generation_options(&conversation_id)?.apply(request);
+        if request.model.trim().is_empty() {
+            request.model = conversations::model(&conversation_id)?
+                .ok_or_else(|| format!("Conversation '{}' has no model; choose one for the request", conversation_id))?;
+        }
+    }
+
+    let has_system_prompt = request.messages.first().is_some_and(|message| message.role == "system");
+    if let Some(preset) = settings::current().prompt_preset.filter(|preset| !preset.trim().is_empty() && !has_system_prompt) {
+        request.messages.insert(0, provider::ChatMessage {
+            role: "system".to_string(),
+            content: preset,
+            pinned: false,
+        });
+        inserted = true;
+    }
+    Ok(usize::from(inserted))
+}
+
+// Chat through the configured provider, streaming the reply as chat-stream events.
+// A request naming a conversation gets that conversation's model and generation options; messages that do not fit the context window are trimmed first and reported as a chat-context event.
+#[tauri::command]
+async fn chat_stream(
+    app: tauri::AppHandle,
+    request_id: String,
+    mut request: provider::ChatRequest,
+) -> Result<provider::ChatResult, String> {
+    let emit = |delta: &str, thinking: bool, done: bool| {
+        let event = ChatStreamEvent { request_id: request_id.clone(), delta: delta.to_string(), thinking, done };
+        if let Err(e) = app.emit(CHAT_STREAM_EVENT, event) {
+            eprintln!("Failed to emit {}: {}", CHAT_STREAM_EVENT, e);
         }
+    };
+
+    let inserted = apply_conversation_defaults(&mut request)?;
+    // Taken before trimming, which may add a summary as a system message
+    let system_prompt = request
+        .messages
+        .first()
+        .filter(|message| message.role == "system")
+        .map(|message| message.content.clone());
+    let provider = provider::active();
+    let mut report = context::fit(provider.as_ref(), &mut request).await;
+    report.skip_inserted(inserted);
+    if !report.dropped.is_empty() {
+        println!("✂️ Left {} message(s) out of the request to fit {} tokens", report.dropped.len(), report.window.unwrap_or_default());
+    }
+    if let Err(e) = app.emit(CHAT_CONTEXT_EVENT, ChatContextEvent { request_id: request_id.clone(), report }) {
+        eprintln!("Failed to emit {}: {}", CHAT_CONTEXT_EVENT, e);
+    }
+
+    let result = provider
+        .chat(&request, &mut |delta| match delta {
+            provider::ChatDelta::Content(text) => emit(text, false, false),
+            provider::ChatDelta::Thinking(text) => emit(text, true, false),
+        })
+        .await;
+    emit("", false, true);
+    result.map(|result| provider::ChatResult { system_prompt, ..result })
+}
+
+// How a chat request would fit the context window, without sending it
+#[tauri::command]
+async fn check_chat_context(mut request: provider::ChatRequest) -> Result<context::ContextReport, String> {
+    let inserted = apply_conversation_defaults(&mut request)?;
+    let mut report = context::check(provider::active().as_ref(), &request).await;
+    report.skip_inserted(inserted);
+    Ok(report)
+}
+
+#[tauri::command]
+async fn embed_texts(model: String, input: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+    provider::active().embed(&model, &input).await
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ModelCapabilities {
+    model: String,
+    capabilities: Vec<String>,
+    // Whether chat requests may set `think` for this model
+    thinking: bool,
+}
+
+#[tauri::command]
+async fn get_model_capabilities(model: String) -> Result<ModelCapabilities, String> {
+    let capabilities = provider::active().capabilities(&model).await?;
+    Ok(ModelCapabilities {
+        thinking: capabilities.iter().any(|c| c == "thinking"),
+        model,
+        capabilities,
+    })
+}
+
+#[tauri::command]
+async fn check_provider_health() -> provider::ProviderHealth {
+    provider::active().health().await
+}
+
+// Hand the endpoint's credentials and TLS settings to the search script. They go through the environment as
+// JSON so secrets stay out of the process list.
+fn apply_endpoint_to_search_command(cmd: &mut Command, endpoint: &endpoints::Endpoint) -> Result<(), String> {
+    let headers: std::collections::BTreeMap<String, String> = credentials::headers_for(&endpoint.id)?
+        .iter()
+        .filter_map(|(name, value)| value.to_str().ok().map(|value| (name.to_string(), value.to_string())))
+        .collect();
+    if !headers.is_empty() {
+        let json = serde_json::to_string(&headers)
+            .map_err(|e| format!("Failed to pass endpoint credentials to the search script: {}", e))?;
+        cmd.env("BEAUTIFYOLLAMA_ENDPOINT_HEADERS", json);
     }
+
+    if endpoint.scheme == "https" && !endpoint.tls.is_default() {
+        // The script compares plain lowercase hex
+        let mut options = endpoint.tls.clone();
+        options.pinned_sha256 = options
+            .pinned_sha256
+            .iter()
+            .map(|pin| tls::normalize_fingerprint(pin))
+            .collect::<Result<_, _>>()?;
+        let json = serde_json::to_string(&options)
+            .map_err(|e| format!("Failed to pass endpoint TLS settings to the search script: {}", e))?;
+        cmd.env("BEAUTIFYOLLAMA_ENDPOINT_TLS", json);
+    }
+    Ok(())
 }
 
 #[tauri::command]
@@ -1251,6 +2650,33 @@ async fn search_web(query: String, thinking: Option<bool>) -> Result<String, Str
     if thinking_mode {
         cmd.arg("--thinking");
     }
+
+    let current = settings::current();
+    if let Some(model) = &current.default_models.search {
+        cmd.arg("--model").arg(model);
+    }
+    if !current.search.searxng_instances.is_empty() {
+        cmd.arg("--instances").arg(current.search.searxng_instances.join(","));
+    }
+    if let Some(max_results) = current.search.max_results {
+        cmd.arg("--max-results").arg(max_results.to_string());
+    }
+    if let Some(timeout) = current.search.timeout_secs {
+        cmd.arg("--timeout").arg(timeout.to_string());
+    }
+    // The script talks to Ollama through the ollama package, which reads OLLAMA_HOST;
+    // OpenAI-compatible servers are called directly
+    let active = current.active_endpoint();
+    match active.provider {
+        provider::ProviderKind::Ollama => {
+            cmd.env("OLLAMA_HOST", active.base_url());
+        }
+        provider::ProviderKind::OpenAiCompatible => {
+            cmd.arg("--provider").arg("openai_compatible").arg("--base-url").arg(active.base_url());
+        }
+    }
+    apply_endpoint_to_search_command(&mut cmd, &active)?;
+    proxy::apply_to_command(&mut cmd);
     
     println!("🐍 Calling Python search script with exact SearxNG instances...");
     
@@ -1296,63 +2722,35 @@ async fn search_web(query: String, thinking: Option<bool>) -> Result<String, Str
 async fn generate_search_queries_with_ollama(query: &str, thinking: bool) -> Result<Vec<String>, String> {
     println!("🧠 Generating search queries with Ollama...");
     
-    // Add /nothinking by default unless thinking mode is enabled
-    let prompt = if thinking {
-        format!(
-            "Based on the following user query, generate three Google search queries \
-            that would help understand the query better. Provide each query enclosed in double quotes.\n\n\
-            User query: {}", 
-            query
-        )
-    } else {
-        format!(
-            "/nothinking Based on the following user query, generate three Google search queries \
-            that would help understand the query better. Provide each query enclosed in double quotes.\n\n\
-            User query: {}", 
-            query
-        )
+    let prompt = format!(
+        "Based on the following user query, generate three Google search queries \
+        that would help understand the query better. Provide each query enclosed in double quotes.\n\n\
+        User query: {}", 
+        query
+    );
+    
+    // Reasoning is switched with the think field and comes back separately, so it never
+    // ends up in the content the queries are parsed from
+    let models = settings::current().default_models;
+    let model = models
+        .search
+        .or(models.chat)
+        .ok_or_else(|| "No model is configured for web search; choose a search or chat model in the settings".to_string())?;
+    let request = provider::ChatRequest {
+        think: Some(thinking),
+        ..provider::ChatRequest::prompt(model, prompt)
     };
-    
-    let request_body = json!({
-        "model": "llama2",
-        "messages": [{"role": "user", "content": prompt}],
-        "stream": false
-    });
-    
-    let output = Command::new("curl")
-        .args([
-            "-s",
-            "--connect-timeout", "10",
-            "--max-time", "30",
-            "-H", "Content-Type: application/json",
-            "-X", "POST",
-            "-d", &request_body.to_string(),
-            "http://localhost:11434/api/chat"
-        ])
-        .output();
-
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                let response = String::from_utf8_lossy(&output.stdout);
-                
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(&response) {
-                    if let Some(content) = json.get("message")
-                        .and_then(|m| m.get("content"))
-                        .and_then(|c| c.as_str()) {
-                        
-                        // Extract queries enclosed in quotes using regex
-                        let re = regex::Regex::new(r#""([^"]+)""#).unwrap();
-                        let queries: Vec<String> = re.captures_iter(content)
-                            .map(|cap| cap[1].to_string())
-                            .collect();
-                        
-                        if queries.len() >= 3 {
-                            println!("✅ Generated {} search queries", queries.len());
-                            return Ok(queries.into_iter().take(3).collect());
-                        }
-                    }
-                }
+    match provider::active().chat(&request, &mut |_| {}).await {
+        Ok(result) => {
+            // Extract queries enclosed in quotes using regex
+            let re = regex::Regex::new(r#""([^"]+)""#).unwrap();
+            let queries: Vec<String> = re.captures_iter(&result.content)
+                .map(|cap| cap[1].to_string())
+                .collect();
+            
+            if queries.len() >= 3 {
+                println!("✅ Generated {} search queries", queries.len());
+                return Ok(queries.into_iter().take(3).collect());
             }
         }
         Err(e) => {
@@ -1364,6 +2762,7 @@ async fn generate_search_queries_with_ollama(query: &str, thinking: bool) -> Res
     Ok(vec![query.to_string()])
 }
 
+
 async fn get_search_context(search_query: &str) -> (Vec<String>, String) {
     // Get the cloud service URL from environment or use default
     let cloud_service_url = std::env::var("CLOUD_SERVICE_URL")
@@ -1378,7 +2777,9 @@ async fn get_search_context(search_query: &str) -> (Vec<String>, String) {
     
     println!("🌐 Fetching search context from: {}", cloud_service_url);
     
-    let output = Command::new("curl")
+    let mut curl = Command::new("curl");
+    proxy::apply_to_command(&mut curl);
+    let output = curl
         .args([
             "-s",
             "--connect-timeout", "10",
@@ -1429,51 +2830,24 @@ async fn get_search_context(search_query: &str) -> (Vec<String>, String) {
 }
 
 async fn summarize_with_ollama(context: &str, thinking: bool) -> Result<String, String> {
-    // Add /nothinking by default unless thinking mode is enabled
-    let prompt = if thinking {
-        format!("Summarize the following search results:\n\n{}", context)
-    } else {
-        format!("/nothinking Summarize the following search results:\n\n{}", context)
+    let prompt = format!("Summarize the following search results:\n\n{}", context);
+    
+    let models = settings::current().default_models;
+    let model = models
+        .search
+        .or(models.chat)
+        .ok_or_else(|| "No model is configured for web search; choose a search or chat model in the settings".to_string())?;
+    let request = provider::ChatRequest {
+        think: Some(thinking),
+        ..provider::ChatRequest::prompt(model, prompt)
     };
-    
-    let request_body = json!({
-        "model": "llama2",
-        "messages": [{"role": "user", "content": prompt}],
-        "stream": false
-    });
-    
-    let output = Command::new("curl")
-        .args([
-            "-s",
-            "--connect-timeout", "15",
-            "--max-time", "30",
-            "-H", "Content-Type: application/json",
-            "-X", "POST",
-            "-d", &request_body.to_string(),
-            "http://localhost:11434/api/chat"
-        ])
-        .output();
-
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                let response = String::from_utf8_lossy(&output.stdout);
-                
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(&response) {
-                    if let Some(content) = json.get("message")
-                        .and_then(|m| m.get("content"))
-                        .and_then(|c| c.as_str()) {
-                        return Ok(content.to_string());
-                    }
-                }
-            }
-        }
+    match provider::active().chat(&request, &mut |_| {}).await {
+        Ok(result) => Ok(result.content),
         Err(e) => {
             println!("❌ Failed to summarize with Ollama: {}", e);
+            Err("Failed to summarize search results".to_string())
         }
     }
-    
-    Err("Failed to summarize search results".to_string())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -1485,8 +2859,14 @@ pub fn run() {
         get_platform,
         check_ollama_installation_paths,
         check_ollama_installed,
+        get_ollama_binary_info,
+        get_ollama_version_info,
+        check_ollama_feature,
+        set_ollama_binary_path,
         check_ollama_service_running,
         check_ollama_service_status,
+        check_ollama_port,
+        start_ollama_on_free_port,
         install_ollama_macos,
         install_ollama_windows,
         install_ollama_linux,
@@ -1494,7 +2874,11 @@ pub fn run() {
         list_installed_models,
         start_ollama_service,
         stop_ollama_service,
+        restart_ollama_service,
+        get_ollama_systemd_status,
+        get_ollama_service_logs,
         load_ollama_model,
+        list_running_models,
         unload_ollama_model,
         uninstall_ollama_model,
         scan_for_models,
@@ -1504,6 +2888,81 @@ pub fn run() {
         search_web,
         get_ollama_port_config,
         set_ollama_port_config,
+        list_endpoints,
+        save_endpoint,
+        delete_endpoint,
+        set_active_endpoint,
+        test_endpoint,
+        set_endpoint_credentials,
+        set_endpoint_tls,
+        detect_system_proxy,
+        get_effective_config,
+        chat_stream,
+        check_chat_context,
+        embed_texts,
+        check_provider_health,
+        get_model_capabilities,
+        list_pools,
+        save_pool,
+        delete_pool,
+        set_active_pool,
+        get_pool_status,
+        list_profiles,
+        capture_profile,
+        save_profile,
+        delete_profile,
+        switch_profile,
+        export_profile,
+        import_profile,
+        list_conversations,
+        get_conversation,
+        create_conversation,
+        rename_conversation,
+        delete_conversation,
+        delete_all_conversations,
+        get_conversation_options,
+        set_conversation_options,
+        set_conversation_model,
+        list_messages,
+        add_message,
+        update_message,
+        delete_message,
+        set_message_pinned,
+        edit_message,
+        list_branches,
+        switch_branch,
+        build_chat_messages,
+        search_conversations,
+        export_conversations,
+        preview_conversation_import,
+        import_conversations,
+        import_local_conversations,
+        list_personas,
+        save_persona,
+        delete_persona,
+        start_persona_conversation,
+        export_personas,
+        import_personas,
+        list_prompt_templates,
+        save_prompt_template,
+        delete_prompt_template,
+        get_template_variables,
+        render_prompt_template,
+        list_template_tags,
+        rename_template_tag,
+        delete_template_tag,
+        export_prompt_templates,
+        import_prompt_templates,
+        get_encryption_status,
+        enable_conversation_encryption,
+        disable_conversation_encryption,
+        unlock_conversations,
+        unlock_conversations_with_recovery_key,
+        lock_conversations,
+        change_conversation_passphrase,
+        export_recovery_key,
+        get_settings,
+        update_settings,
         get_ollama_url,
         check_for_updates,
         download_update,
@@ -1512,6 +2971,31 @@ pub fn run() {
         open_downloads_folder
     ])
     .setup(|app| {
+      let config_dir = app.path().app_config_dir()?;
+      if let Err(e) = settings::init(config_dir.clone()) {
+        eprintln!("Failed to load settings, using defaults: {}", e);
+      }
+      if let Err(e) = credentials::init(config_dir.clone()) {
+        eprintln!("Failed to load endpoint credentials: {}", e);
+      }
+      if let Err(e) = templates::init(config_dir) {
+        eprintln!("Failed to load prompt templates: {}", e);
+      }
+      if let Err(e) = conversations::init(app.path().app_data_dir()?) {
+        eprintln!("Failed to open the conversation database: {}", e);
+      }
+
+      // Lock an encrypted conversation store once it has sat unused for the configured time
+      let handle = app.handle().clone();
+      std::thread::spawn(move || loop {
+        std::thread::sleep(AUTO_LOCK_CHECK_INTERVAL);
+        let minutes = settings::current().encryption.auto_lock_minutes;
+        if minutes > 0 && conversations::lock_if_idle(Duration::from_secs(u64::from(minutes) * 60)) {
+          println!("Locked the conversation store after {} idle minutes", minutes);
+          notify_conversations_locked(&handle);
+        }
+      });
+
       if cfg!(debug_assertions) {
         app.handle().plugin(
           tauri_plugin_log::Builder::default()
@@ -1635,7 +3119,7 @@ const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[tauri::command]
 async fn check_for_updates() -> Result<UpdateInfo, String> {
-    let client = reqwest::Client::new();
+    let client = proxy::client()?;
     
     // Get the latest release from GitHub API
     let url = format!("https://api.github.com/repos/{}/releases/latest", GITHUB_REPO);
@@ -1683,7 +3167,7 @@ async fn check_for_updates() -> Result<UpdateInfo, String> {
 
 #[tauri::command]
 async fn download_update(download_url: String) -> Result<String, String> {
-    let client = reqwest::Client::new();
+    let client = proxy::client()?;
     
     // Extract filename from URL
     let filename = download_url
@@ -0,0 +1,202 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::generation::GenerationOptions;
+
+// Marker and layout version of exported persona files
+pub const PERSONA_FILE_FORMAT: &str = "beautifyollama-personas";
+// 2: knowledge files are embedded as documents
+pub const PERSONA_FILE_VERSION: u32 = 2;
+
+// Knowledge files end up in the system prompt, which is never trimmed, so they are kept small
+const MAX_KNOWLEDGE_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KnowledgeSource {
+    // Text files whose contents are added to the system prompt when a conversation starts
+    Files { paths: Vec<String> },
+    // The same, carried in the persona itself; exports turn files into these so they work on other machines
+    Documents { documents: Vec<KnowledgeDocument> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnowledgeDocument {
+    pub name: String,
+    pub content: String,
+}
+
+// A named assistant, e.g. "Rust reviewer": everything a conversation started from it is set up with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Persona {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub model: String,
+    #[serde(default)]
+    pub system_prompt: String,
+    // The system prompt above takes the place of the one in these options
+    #[serde(default)]
+    pub options: GenerationOptions,
+    #[serde(default)]
+    pub knowledge: Option<KnowledgeSource>,
+}
+
+impl Persona {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.trim().is_empty() {
+            return Err("Persona id must not be empty".to_string());
+        }
+        if self.name.trim().is_empty() {
+            return Err("Persona name must not be empty".to_string());
+        }
+        if self.model.trim().is_empty() {
+            return Err(format!("Persona '{}' needs a model", self.name));
+        }
+        self.options
+            .validate()
+            .map_err(|e| format!("Persona '{}': {}", self.name, e))?;
+        match &self.knowledge {
+            Some(KnowledgeSource::Files { paths }) if paths.is_empty() || paths.iter().any(|path| path.trim().is_empty()) => {
+                return Err(format!("Persona '{}': knowledge files need a path each", self.name));
+            }
+            Some(KnowledgeSource::Documents { documents }) => {
+                if documents.is_empty() || documents.iter().any(|document| document.name.trim().is_empty()) {
+                    return Err(format!("Persona '{}': knowledge documents need a name each", self.name));
+                }
+                self.check_knowledge_size(documents)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn check_knowledge_size(&self, documents: &[KnowledgeDocument]) -> Result<(), String> {
+        let size: usize = documents.iter().map(|document| document.name.len() + document.content.len()).sum();
+        if size > MAX_KNOWLEDGE_BYTES {
+            return Err(format!(
+                "Persona '{}': knowledge adds up to {} KB, more than the {} KB allowed",
+                self.name,
+                size / 1024,
+                MAX_KNOWLEDGE_BYTES / 1024
+            ));
+        }
+        Ok(())
+    }
+
+    // The knowledge as documents, reading files as they are now
+    fn knowledge_documents(&self) -> Result<Vec<KnowledgeDocument>, String> {
+        let documents = match &self.knowledge {
+            None => return Ok(Vec::new()),
+            Some(KnowledgeSource::Documents { documents }) => documents.clone(),
+            Some(KnowledgeSource::Files { paths }) => paths
+                .iter()
+                .map(|path| {
+                    let path = Path::new(path);
+                    let content = std::fs::read_to_string(path).map_err(|e| {
+                        format!("Persona '{}': could not read knowledge file {}: {}", self.name, path.display(), e)
+                    })?;
+                    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                    Ok(KnowledgeDocument { name, content })
+                })
+                .collect::<Result<Vec<_>, String>>()?,
+        };
+        self.check_knowledge_size(&documents)?;
+        Ok(documents)
+    }
+
+    // A copy that works on another machine: knowledge files are replaced by their contents
+    fn portable(&self) -> Result<Persona, String> {
+        let knowledge = match &self.knowledge {
+            Some(KnowledgeSource::Files { .. }) => Some(KnowledgeSource::Documents { documents: self.knowledge_documents()? }),
+            knowledge => knowledge.clone(),
+        };
+        Ok(Persona { knowledge, ..self.clone() })
+    }
+
+    // The options a conversation started from this persona gets. Knowledge files are read now,
+    // so later edits to them do not change conversations already started.
+    pub fn conversation_options(&self) -> Result<GenerationOptions, String> {
+        let mut system_prompt = self.system_prompt.trim().to_string();
+        let documents = self.knowledge_documents()?;
+        if !documents.is_empty() {
+            if !system_prompt.is_empty() {
+                system_prompt.push_str("\n\n");
+            }
+            system_prompt.push_str("Reference material:");
+            for document in &documents {
+                system_prompt.push_str(&format!("\n\n--- {} ---\n{}", document.name, document.content.trim()));
+            }
+        }
+
+        Ok(GenerationOptions {
+            system_prompt: Some(system_prompt).filter(|prompt| !prompt.is_empty()),
+            ..self.options.clone()
+        })
+    }
+}
+
+// Version 1 personas could name a "web_search" knowledge source, which never did anything; it becomes no knowledge
+pub fn drop_web_search_knowledge(personas: &mut Value) {
+    for persona in personas.as_array_mut().into_iter().flatten() {
+        if persona.pointer("/knowledge/type").and_then(|v| v.as_str()) == Some("web_search") {
+            persona["knowledge"] = Value::Null;
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PersonaFile {
+    format: String,
+    version: u32,
+    personas: Vec<Persona>,
+}
+
+pub fn export_to(personas: &[Persona], path: &Path) -> Result<(), String> {
+    let file = PersonaFile {
+        format: PERSONA_FILE_FORMAT.to_string(),
+        version: PERSONA_FILE_VERSION,
+        personas: personas.iter().map(Persona::portable).collect::<Result<_, _>>()?,
+    };
+    crate::settings::write_json_atomic(path, &file, false)
+}
+
+// Read exported personas. The caller decides on the final ids.
+pub fn import_from(path: &Path) -> Result<Vec<Persona>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read personas from {}: {}", path.display(), e))?;
+    let mut raw: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("{} is not a persona file: {}", path.display(), e))?;
+    if raw.get("version").and_then(|v| v.as_u64()) == Some(1) {
+        if let Some(personas) = raw.get_mut("personas") {
+            drop_web_search_knowledge(personas);
+        }
+    }
+    let file: PersonaFile = serde_json::from_value(raw)
+        .map_err(|e| format!("{} is not a persona file: {}", path.display(), e))?;
+
+    if file.format != PERSONA_FILE_FORMAT {
+        return Err(format!("{} is not a persona file", path.display()));
+    }
+    if file.version > PERSONA_FILE_VERSION {
+        return Err(format!(
+            "{} was exported by a newer version (persona format {}, this build reads up to {})",
+            path.display(),
+            file.version,
+            PERSONA_FILE_VERSION
+        ));
+    }
+
+    for persona in &file.personas {
+        persona.validate()?;
+        // Only version 1 exports carry paths, which point at files on the machine they came from
+        if let Some(KnowledgeSource::Files { .. }) = persona.knowledge {
+            return Err(format!(
+                "Persona '{}' refers to knowledge files on the machine it was exported from; export it again with this version",
+                persona.name
+            ));
+        }
+    }
+    Ok(file.personas)
+}
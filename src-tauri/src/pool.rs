@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::endpoints::Endpoint;
+use crate::version::OllamaFeature;
+
+// Health and model lists are reused for this long before a member is probed again
+const HEALTH_TTL: Duration = Duration::from_secs(15);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolStrategy {
+    // Fewest requests in flight from this app, preferring members that already have the model loaded
+    #[default]
+    LeastBusy,
+    RoundRobin,
+}
+
+// A group of saved endpoints that model requests are spread across
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointPool {
+    pub id: String,
+    pub name: String,
+    // Endpoint ids, in the order round-robin walks them
+    pub members: Vec<String>,
+    #[serde(default)]
+    pub strategy: PoolStrategy,
+}
+
+impl EndpointPool {
+    pub fn validate(&self, endpoints: &[Endpoint]) -> Result<(), String> {
+        if self.id.trim().is_empty() {
+            return Err("Pool id must not be empty".to_string());
+        }
+        if self.name.trim().is_empty() {
+            return Err("Pool name must not be empty".to_string());
+        }
+        if self.members.is_empty() {
+            return Err(format!("Pool '{}' needs at least one endpoint", self.name));
+        }
+        for (index, member) in self.members.iter().enumerate() {
+            match endpoints.iter().find(|e| &e.id == member) {
+                None => return Err(format!("Pool '{}': endpoint '{}' does not exist", self.name, member)),
+                Some(endpoint) if endpoint.provider != crate::provider::ProviderKind::Ollama => {
+                    return Err(format!("Pool '{}': endpoint '{}' is not an Ollama server", self.name, member));
+                }
+                Some(_) => {}
+            }
+            if self.members[..index].contains(member) {
+                return Err(format!("Pool '{}': endpoint '{}' is listed twice", self.name, member));
+            }
+        }
+        Ok(())
+    }
+
+    fn member_endpoints(&self, endpoints: &[Endpoint]) -> Vec<Endpoint> {
+        self.members
+            .iter()
+            .filter_map(|id| endpoints.iter().find(|e| &e.id == id).cloned())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MemberHealth {
+    checked_at: Instant,
+    healthy: bool,
+    error: Option<String>,
+    models: Vec<String>,
+    loaded_models: Vec<String>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    health: HashMap<String, MemberHealth>,
+    in_flight: HashMap<String, usize>,
+    cursors: HashMap<String, usize>,
+}
+
+static POOL_STATE: OnceLock<Mutex<PoolState>> = OnceLock::new();
+
+fn state() -> std::sync::MutexGuard<'static, PoolState> {
+    POOL_STATE
+        .get_or_init(|| Mutex::new(PoolState::default()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+// Counts a request against a member until the response (including a stream) is dropped
+pub struct InFlight {
+    endpoint_id: String,
+}
+
+impl InFlight {
+    fn start(endpoint_id: &str) -> Self {
+        *state().in_flight.entry(endpoint_id.to_string()).or_insert(0) += 1;
+        InFlight { endpoint_id: endpoint_id.to_string() }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Some(count) = state().in_flight.get_mut(&self.endpoint_id) {
+            *count = count.saturating_sub(1);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberStatus {
+    pub endpoint_id: String,
+    pub name: String,
+    pub base_url: String,
+    pub healthy: bool,
+    pub error: Option<String>,
+    pub models: Vec<String>,
+    pub loaded_models: Vec<String>,
+    pub in_flight: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolStatus {
+    pub pool_id: String,
+    pub name: String,
+    pub strategy: PoolStrategy,
+    pub active: bool,
+    pub members: Vec<MemberStatus>,
+}
+
+// "llama3" and "llama3:latest" name the same model
+fn model_key(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    if name.contains(':') { name } else { format!("{}:latest", name) }
+}
+
+fn has_model(models: &[String], model: &str) -> bool {
+    let wanted = model_key(model);
+    models.iter().any(|m| model_key(m) == wanted)
+}
+
+async fn fetch_json(client: &reqwest::Client, url: &str) -> Result<serde_json::Value, String> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach {}: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("{} answered with {}", url, response.status()));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse {} response: {}", url, e))
+}
+
+async fn probe_member(endpoint: Endpoint) -> MemberHealth {
+    let unhealthy = |error: String| MemberHealth {
+        checked_at: Instant::now(),
+        healthy: false,
+        error: Some(error),
+        models: Vec::new(),
+        loaded_models: Vec::new(),
+    };
+
+    let client = match crate::endpoint_client(&endpoint, Some(PROBE_TIMEOUT)) {
+        Ok(client) => client,
+        Err(e) => return unhealthy(e),
+    };
+    let tags = match fetch_json(&client, &endpoint.api_url("tags")).await {
+        Ok(tags) => tags,
+        Err(e) => return unhealthy(e),
+    };
+    // Older servers have no /api/ps; the member is still usable, just without load information
+    let loaded_models = fetch_json(&client, &endpoint.api_url("ps"))
+        .await
+        .map(|ps| crate::model_names_from_json(&ps))
+        .unwrap_or_default();
+
+    MemberHealth {
+        checked_at: Instant::now(),
+        healthy: true,
+        error: None,
+        models: crate::model_names_from_json(&tags),
+        loaded_models,
+    }
+}
+
+// Probe members whose cached health is missing or older than HEALTH_TTL (all of them with `force`)
+async fn refresh(members: &[Endpoint], force: bool) {
+    let stale: Vec<Endpoint> = {
+        let state = state();
+        members
+            .iter()
+            .filter(|member| {
+                force || state.health.get(&member.id).map_or(true, |h| h.checked_at.elapsed() > HEALTH_TTL)
+            })
+            .cloned()
+            .collect()
+    };
+
+    let probes: Vec<_> = stale
+        .into_iter()
+        .map(|member| (member.id.clone(), tokio::spawn(probe_member(member))))
+        .collect();
+    for (id, probe) in probes {
+        if let Ok(health) = probe.await {
+            state().health.insert(id, health);
+        }
+    }
+}
+
+fn mark_failed(endpoint_id: &str, error: &str) {
+    let mut state = state();
+    if let Some(health) = state.health.get_mut(endpoint_id) {
+        health.healthy = false;
+        health.error = Some(error.to_string());
+        health.checked_at = Instant::now();
+    }
+}
+
+// Healthy members that have `model`, in the order they should be tried
+async fn candidates(pool: &EndpointPool, endpoints: &[Endpoint], model: &str) -> Result<Vec<Endpoint>, String> {
+    let members = pool.member_endpoints(endpoints);
+    refresh(&members, false).await;
+
+    let mut state = state();
+    let healthy: Vec<&Endpoint> = members
+        .iter()
+        .filter(|m| state.health.get(&m.id).is_some_and(|h| h.healthy))
+        .collect();
+    if healthy.is_empty() {
+        return Err(format!("No member of pool '{}' is reachable", pool.name));
+    }
+
+    let mut eligible: Vec<Endpoint> = healthy
+        .into_iter()
+        .filter(|m| state.health.get(&m.id).is_some_and(|h| has_model(&h.models, model)))
+        .cloned()
+        .collect();
+    if eligible.is_empty() {
+        return Err(format!("No healthy member of pool '{}' has the model '{}'", pool.name, model));
+    }
+
+    match pool.strategy {
+        PoolStrategy::LeastBusy => {
+            let busy = |m: &Endpoint| state.in_flight.get(&m.id).copied().unwrap_or(0);
+            let loaded = |m: &Endpoint| state.health.get(&m.id).is_some_and(|h| has_model(&h.loaded_models, model));
+            eligible.sort_by_key(|m| (busy(m), !loaded(m)));
+        }
+        PoolStrategy::RoundRobin => {
+            let cursor = state.cursors.entry(pool.id.clone()).or_insert(0);
+            let start = *cursor % eligible.len();
+            *cursor = cursor.wrapping_add(1);
+            eligible.rotate_left(start);
+        }
+    }
+    Ok(eligible)
+}
+
+// The members whose Ollama is new enough for `features`. Unknown versions are let through, as for single endpoints.
+async fn supporting(pool: &EndpointPool, members: Vec<Endpoint>, features: &[OllamaFeature]) -> Result<Vec<Endpoint>, String> {
+    let mut supported = Vec::new();
+    let mut rejections = Vec::new();
+    for member in members {
+        match crate::require_endpoint_features(&member, features).await {
+            Ok(()) => supported.push(member),
+            Err(e) => rejections.push(format!("{}: {}", member.name, e)),
+        }
+    }
+    if supported.is_empty() {
+        return Err(format!("No member of pool '{}' can handle the request: {}", pool.name, rejections.join("; ")));
+    }
+    Ok(supported)
+}
+
+// A response from the member that accepted the request; the body has not been read yet
+pub struct PooledResponse {
+    pub endpoint: Endpoint,
+    pub response: reqwest::Response,
+    pub in_flight: InFlight,
+}
+
+// The active pool and the saved endpoints, when model requests should be spread across a pool
+pub fn active_pool() -> Option<(EndpointPool, Vec<Endpoint>)> {
+    let settings = crate::settings::current();
+    let id = settings.active_pool.clone()?;
+    let pool = settings.pools.iter().find(|p| p.id == id)?.clone();
+    Some((pool, settings.endpoints))
+}
+
+// Send a model request to the pool. Members are tried in strategy order and the request moves on
+// when a member cannot be reached or answers with a server error, i.e. before any tokens arrive.
+// Once a response is returned the caller owns it, and errors while reading the body are not retried.
+pub async fn send<F>(
+    pool: &EndpointPool,
+    endpoints: &[Endpoint],
+    model: &str,
+    features: &[OllamaFeature],
+    build: F,
+) -> Result<PooledResponse, String>
+where
+    F: Fn(&Endpoint, &reqwest::Client) -> reqwest::RequestBuilder,
+{
+    let mut failures = Vec::new();
+    for endpoint in supporting(pool, candidates(pool, endpoints, model).await?, features).await? {
+        let client = crate::endpoint_client(&endpoint, None)?;
+        let in_flight = InFlight::start(&endpoint.id);
+
+        match build(&endpoint, &client).send().await {
+            Ok(response) if response.status().is_server_error() => {
+                let error = format!("answered with {}", response.status());
+                eprintln!("Pool '{}': {} {}, trying the next member", pool.name, endpoint.name, error);
+                mark_failed(&endpoint.id, &error);
+                failures.push(format!("{}: {}", endpoint.name, error));
+            }
+            Ok(response) => return Ok(PooledResponse { endpoint, response, in_flight }),
+            Err(e) => {
+                eprintln!("Pool '{}': {} failed ({}), trying the next member", pool.name, endpoint.name, e);
+                mark_failed(&endpoint.id, &e.to_string());
+                failures.push(format!("{}: {}", endpoint.name, e));
+            }
+        }
+    }
+    Err(format!("Every member of pool '{}' failed: {}", pool.name, failures.join("; ")))
+}
+
+// Probe every member now and report health, models and load
+pub async fn status(pool: &EndpointPool, endpoints: &[Endpoint], active: bool) -> PoolStatus {
+    let members = pool.member_endpoints(endpoints);
+    refresh(&members, true).await;
+
+    let state = state();
+    let members = members
+        .into_iter()
+        .map(|member| {
+            let health = state.health.get(&member.id);
+            MemberStatus {
+                healthy: health.is_some_and(|h| h.healthy),
+                error: health.and_then(|h| h.error.clone()),
+                models: health.map(|h| h.models.clone()).unwrap_or_default(),
+                loaded_models: health.map(|h| h.loaded_models.clone()).unwrap_or_default(),
+                in_flight: state.in_flight.get(&member.id).copied().unwrap_or(0),
+                base_url: member.base_url(),
+                name: member.name,
+                endpoint_id: member.id,
+            }
+        })
+        .collect();
+
+    PoolStatus {
+        pool_id: pool.id.clone(),
+        name: pool.name.clone(),
+        strategy: pool.strategy,
+        active,
+        members,
+    }
+}
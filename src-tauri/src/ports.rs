@@ -0,0 +1,139 @@
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::process::Command;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+// How many ports after the preferred one are tried before asking the OS for any free port
+const FREE_PORT_SEARCH_RANGE: u16 = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortOwner {
+    pub pid: u32,
+    pub name: Option<String>,
+}
+
+// Helper function to check whether anything is accepting TCP connections on a local port
+pub fn port_in_use(port: u16) -> bool {
+    let address = SocketAddr::from(([127, 0, 0, 1], port));
+    TcpStream::connect_timeout(&address, Duration::from_millis(1000)).is_ok()
+}
+
+// Helper function to check whether the app could bind a local port right now
+fn port_is_free(port: u16) -> bool {
+    TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+// Find a free local port, starting at `preferred` and walking upwards.
+// Falls back to an OS-assigned port when the whole range is taken.
+pub fn find_free_port(preferred: u16) -> Result<u16, String> {
+    let start = preferred.max(1024);
+    let end = start.saturating_add(FREE_PORT_SEARCH_RANGE);
+
+    if let Some(port) = (start..=end).find(|port| port_is_free(*port)) {
+        return Ok(port);
+    }
+
+    TcpListener::bind(("127.0.0.1", 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|e| format!("Failed to find a free port: {}", e))
+}
+
+// Identify the process listening on a local TCP port.
+// Best effort: returns None when the tools are missing or the process belongs to another user.
+pub fn find_port_owner(port: u16) -> Option<PortOwner> {
+    if cfg!(target_os = "windows") {
+        find_port_owner_windows(port)
+    } else if cfg!(target_os = "linux") {
+        find_port_owner_ss(port).or_else(|| find_port_owner_lsof(port))
+    } else {
+        find_port_owner_lsof(port)
+    }
+}
+
+fn find_port_owner_ss(port: u16) -> Option<PortOwner> {
+    let filter = format!("sport = :{}", port);
+    let output = Command::new("ss")
+        .args(["-H", "-l", "-t", "-n", "-p", &filter])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    // Example: LISTEN 0 4096 127.0.0.1:11434 0.0.0.0:* users:(("ollama",pid=1234,fd=3))
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let re = regex::Regex::new(r#"users:\(\("([^"]+)",pid=(\d+)"#).unwrap();
+    let caps = re.captures(&stdout)?;
+
+    Some(PortOwner {
+        pid: caps[2].parse().ok()?,
+        name: Some(caps[1].to_string()),
+    })
+}
+
+fn find_port_owner_lsof(port: u16) -> Option<PortOwner> {
+    let target = format!("-iTCP:{}", port);
+    let output = Command::new("lsof")
+        .args(["-nP", &target, "-sTCP:LISTEN", "-Fpc"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    // -F output is one field per line: "p<pid>" followed by "c<command>"
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut pid = None;
+    let mut name = None;
+    for line in stdout.lines() {
+        if let Some(value) = line.strip_prefix('p') {
+            if pid.is_some() {
+                break;
+            }
+            pid = value.parse::<u32>().ok();
+        } else if let Some(value) = line.strip_prefix('c') {
+            name = Some(value.to_string());
+        }
+    }
+
+    pid.map(|pid| PortOwner { pid, name })
+}
+
+fn find_port_owner_windows(port: u16) -> Option<PortOwner> {
+    let output = Command::new("netstat")
+        .args(["-ano", "-p", "TCP"])
+        .output()
+        .ok()?;
+
+    // Example:   TCP    0.0.0.0:11434    0.0.0.0:0    LISTENING    1234
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let local_suffix = format!(":{}", port);
+    let pid = stdout.lines().find_map(|line| {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() >= 5 && parts[1].ends_with(&local_suffix) && parts[3] == "LISTENING" {
+            parts[4].parse::<u32>().ok()
+        } else {
+            None
+        }
+    })?;
+
+    let filter = format!("PID eq {}", pid);
+    let name = Command::new("tasklist")
+        .args(["/FI", &filter, "/FO", "CSV", "/NH"])
+        .output()
+        .ok()
+        .and_then(|output| {
+            // Example: "ollama.exe","1234","Console","1","45,000 K"
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            stdout
+                .split(',')
+                .next()
+                .map(|field| field.trim().trim_matches('"').to_string())
+                .filter(|name| !name.is_empty() && !name.starts_with("INFO:"))
+        });
+
+    Some(PortOwner { pid, name })
+}
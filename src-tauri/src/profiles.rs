@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::credentials::AuthType;
+use crate::endpoints::Endpoint;
+use crate::settings::{DefaultModels, SearchSettings, Settings};
+
+// Marker and layout version of exported profile files
+pub const PROFILE_FILE_FORMAT: &str = "beautifyollama-profile";
+pub const PROFILE_FILE_VERSION: u32 = 1;
+
+// Server environment variables whose names contain one of these are treated as secrets
+const SECRET_ENV_MARKERS: [&str; 5] = ["KEY", "TOKEN", "SECRET", "PASSWORD", "CREDENTIAL"];
+
+// A named bundle of the settings people switch between, e.g. "Laptop" and "Lab server"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    // A full copy rather than an id, so the profile still works on a machine without that endpoint
+    pub endpoint: Endpoint,
+    #[serde(default)]
+    pub default_models: DefaultModels,
+    #[serde(default)]
+    pub server_env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub search: SearchSettings,
+    #[serde(default)]
+    pub prompt_preset: Option<String>,
+}
+
+impl Profile {
+    // Capture the current settings under a new name
+    pub fn from_settings(id: String, name: String, settings: &Settings) -> Self {
+        Profile {
+            id,
+            name,
+            endpoint: settings.active_endpoint(),
+            default_models: settings.default_models.clone(),
+            server_env: settings.server_env.clone(),
+            search: settings.search.clone(),
+            prompt_preset: settings.prompt_preset.clone(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.trim().is_empty() {
+            return Err("Profile id must not be empty".to_string());
+        }
+        if self.name.trim().is_empty() {
+            return Err("Profile name must not be empty".to_string());
+        }
+        self.endpoint
+            .validate()
+            .map_err(|e| format!("Profile '{}': {}", self.name, e))?;
+        crate::settings::validate_server_env(&self.server_env)
+            .map_err(|e| format!("Profile '{}': {}", self.name, e))
+    }
+
+    // Make this profile's endpoint active and copy its values into the settings
+    pub fn apply(&self, settings: &mut Settings) {
+        let mut endpoint = self.endpoint.clone();
+        // Credentials and insecure TLS stay whatever this machine has configured for the endpoint, as long as
+        // the profile still points at the same server; secrets are never sent to a host they were not set up for
+        let existing = settings
+            .endpoints
+            .iter()
+            .find(|e| e.id == endpoint.id && e.scheme == endpoint.scheme && e.host == endpoint.host && e.port == endpoint.port);
+        endpoint.auth = existing.map(|e| e.auth).unwrap_or_default();
+        endpoint.tls.insecure = existing.is_some_and(|e| e.tls.insecure);
+
+        match settings.endpoints.iter_mut().find(|e| e.id == endpoint.id) {
+            Some(existing) => *existing = endpoint,
+            None => settings.endpoints.push(endpoint),
+        }
+        settings.active_endpoint = self.endpoint.id.clone();
+        // A profile names a single endpoint, so it takes over from any pool
+        settings.active_pool = None;
+        settings.default_models = self.default_models.clone();
+        settings.server_env = self.server_env.clone();
+        settings.search = self.search.clone();
+        settings.prompt_preset = self.prompt_preset.clone();
+        settings.active_profile = Some(self.id.clone());
+    }
+
+    // Copy that is safe to hand to someone else: no auth, no insecure TLS, no secret-looking env vars
+    pub fn portable(&self) -> Self {
+        let mut profile = self.clone();
+        profile.endpoint.auth = AuthType::None;
+        profile.endpoint.tls.insecure = false;
+        profile.server_env.retain(|name, _| {
+            let upper = name.to_uppercase();
+            !SECRET_ENV_MARKERS.iter().any(|marker| upper.contains(marker))
+        });
+        profile
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileFile {
+    format: String,
+    version: u32,
+    profile: Profile,
+}
+
+pub fn export_to(profile: &Profile, path: &Path) -> Result<(), String> {
+    let file = ProfileFile {
+        format: PROFILE_FILE_FORMAT.to_string(),
+        version: PROFILE_FILE_VERSION,
+        profile: profile.portable(),
+    };
+    crate::settings::write_json_atomic(path, &file, false)
+}
+
+// Read an exported profile. The caller decides on the final id.
+pub fn import_from(path: &Path) -> Result<Profile, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read profile from {}: {}", path.display(), e))?;
+    let file: ProfileFile = serde_json::from_str(&text)
+        .map_err(|e| format!("{} is not a profile file: {}", path.display(), e))?;
+
+    if file.format != PROFILE_FILE_FORMAT {
+        return Err(format!("{} is not a profile file", path.display()));
+    }
+    if file.version > PROFILE_FILE_VERSION {
+        return Err(format!(
+            "{} was exported by a newer version (profile format {}, this build reads up to {})",
+            path.display(),
+            file.version,
+            PROFILE_FILE_VERSION
+        ));
+    }
+
+    // Files can be edited by hand, so strip secrets and insecure mode again
+    let profile = file.profile.portable();
+    profile.validate()?;
+    Ok(profile)
+}
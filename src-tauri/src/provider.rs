@@ -0,0 +1,663 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::endpoints::Endpoint;
+use crate::pool::{self, EndpointPool, InFlight};
+use crate::version::OllamaFeature;
+
+// Which API an endpoint speaks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProviderKind {
+    #[default]
+    #[serde(rename = "ollama")]
+    Ollama,
+    // llama.cpp's llama-server, LM Studio and other servers exposing /v1/chat/completions and /v1/models
+    #[serde(rename = "openai_compatible")]
+    OpenAiCompatible,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+    // Kept when older turns are trimmed to fit the context window; never sent to the server
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    // Generation options under Ollama's names (temperature, top_p, seed, num_predict, stop, ...)
+    #[serde(default)]
+    pub options: serde_json::Map<String, Value>,
+    // Ask for (true) or suppress (false) reasoning. Only sent to models that report the
+    // "thinking" capability; None leaves it to the model's default.
+    #[serde(default)]
+    pub think: Option<bool>,
+    // Tool definitions the model may call, in Ollama's /api/chat format
+    #[serde(default)]
+    pub tools: Vec<Value>,
+    // "json", or a JSON schema the reply has to follow
+    #[serde(default)]
+    pub format: Option<Value>,
+    // Stored conversation whose generation options fill in what `options` leaves unset
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+}
+
+impl ChatRequest {
+    // A single user message with default options
+    pub fn prompt(model: String, prompt: String) -> Self {
+        ChatRequest {
+            model,
+            messages: vec![ChatMessage { role: "user".to_string(), content: prompt, pinned: false }],
+            options: serde_json::Map::new(),
+            think: None,
+            tools: Vec::new(),
+            format: None,
+            conversation_id: None,
+        }
+    }
+}
+
+// A piece of a streamed reply; reasoning is kept apart from the answer
+#[derive(Debug, Clone, Copy)]
+pub enum ChatDelta<'a> {
+    Content(&'a str),
+    Thinking(&'a str),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatResult {
+    pub model: String,
+    pub content: String,
+    // Reasoning the model produced before answering, if any
+    pub thinking: Option<String>,
+    // Tool calls the model made, as Ollama reports them
+    pub tool_calls: Vec<Value>,
+    pub done_reason: Option<String>,
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+    // Name of the endpoint that answered, useful when a pool picked it
+    pub served_by: String,
+    // Options the request was sent with, to be stored with the reply so it can be reproduced
+    pub options: serde_json::Map<String, Value>,
+    // System prompt the request was sent with, for the same reason
+    pub system_prompt: Option<String>,
+}
+
+// Context sizes a server reports for a model, in tokens
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelContext {
+    // The longest context the model was trained for
+    pub context_length: Option<u64>,
+    // The num_ctx the model's Modelfile sets, used when a request does not set one
+    pub num_ctx: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderHealth {
+    pub kind: ProviderKind,
+    pub healthy: bool,
+    pub version: Option<String>,
+    pub message: String,
+}
+
+// What the chat, search and model commands need from a model server
+#[async_trait]
+pub trait Provider: Send + Sync {
+    fn kind(&self) -> ProviderKind;
+
+    async fn list_models(&self) -> Result<Vec<String>, String>;
+
+    // Stream a chat reply, handing each piece of text or reasoning to `on_delta` as it arrives
+    async fn chat(&self, request: &ChatRequest, on_delta: &mut (dyn for<'a> FnMut(ChatDelta<'a>) + Send)) -> Result<ChatResult, String>;
+
+    async fn embed(&self, model: &str, input: &[String]) -> Result<Vec<Vec<f32>>, String>;
+
+    // Capabilities the server reports for a model ("completion", "thinking", "tools", ...); empty when unknown
+    async fn capabilities(&self, model: &str) -> Result<Vec<String>, String>;
+
+    async fn model_context(&self, model: &str) -> Result<ModelContext, String>;
+
+    async fn health(&self) -> ProviderHealth;
+}
+
+// The provider for the active pool, or else the active endpoint
+pub fn active() -> Box<dyn Provider> {
+    if let Some((selected, endpoints)) = pool::active_pool() {
+        return Box::new(OllamaProvider { target: OllamaTarget::Pool(selected, endpoints) });
+    }
+    for_endpoint(crate::settings::active_endpoint())
+}
+
+pub fn for_endpoint(endpoint: Endpoint) -> Box<dyn Provider> {
+    match endpoint.provider {
+        ProviderKind::Ollama => Box::new(OllamaProvider { target: OllamaTarget::Endpoint(endpoint) }),
+        ProviderKind::OpenAiCompatible => Box::new(OpenAiProvider { endpoint }),
+    }
+}
+
+// Helper function to call `on_line` for every non-empty line of a streamed body.
+// `on_line` returns false to stop reading early.
+async fn for_each_line<F>(mut response: reqwest::Response, mut on_line: F) -> Result<(), String>
+where
+    F: FnMut(&str) -> Result<bool, String>,
+{
+    let mut buffer: Vec<u8> = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Stream interrupted: {}", e))?
+    {
+        buffer.extend_from_slice(&chunk);
+        while let Some(newline) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            if !line.trim().is_empty() && !on_line(line.trim())? {
+                return Ok(());
+            }
+        }
+    }
+
+    let rest = String::from_utf8_lossy(&buffer);
+    if !rest.trim().is_empty() {
+        on_line(rest.trim())?;
+    }
+    Ok(())
+}
+
+// Helper function to turn a non-success response into an error carrying the server's message
+async fn check_status(response: reqwest::Response, what: &str) -> Result<reqwest::Response, String> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let text = response.text().await.unwrap_or_default();
+    Err(format!("{} returned {}: {}", what, status, text.trim()))
+}
+
+async fn get_json(client: &reqwest::Client, url: &str) -> Result<Value, String> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Request to {} failed: {}", url, e))?;
+    check_status(response, url)
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse {} response: {}", url, e))
+}
+
+fn push_thinking(result: &mut ChatResult, delta: &str) {
+    result.thinking.get_or_insert_with(String::new).push_str(delta);
+}
+
+// Ollama features a chat request relies on, checked against the server that is going to answer it
+fn chat_features(request: &ChatRequest) -> Vec<OllamaFeature> {
+    let mut features = Vec::new();
+    if request.think == Some(true) {
+        features.push(OllamaFeature::Thinking);
+    }
+    if !request.tools.is_empty() {
+        features.push(OllamaFeature::ToolCalls);
+    }
+    // Plain "json" mode is much older than schemas
+    if request.format.as_ref().is_some_and(Value::is_object) {
+        features.push(OllamaFeature::StructuredOutputs);
+    }
+    features
+}
+
+// Role and content only: servers with strict request schemas reject unknown message fields
+fn wire_messages(messages: &[ChatMessage]) -> Vec<Value> {
+    messages
+        .iter()
+        .map(|message| json!({ "role": message.role, "content": message.content }))
+        .collect()
+}
+
+// What /api/show tells about a model
+#[derive(Debug, Clone, Default)]
+struct ModelDetails {
+    capabilities: Vec<String>,
+    context: ModelContext,
+}
+
+impl ModelDetails {
+    fn from_show(json: &Value) -> Self {
+        // Servers before the capabilities list report nothing, which reads as "no thinking"
+        let capabilities = json
+            .get("capabilities")
+            .and_then(|v| v.as_array())
+            .map(|list| list.iter().filter_map(|c| c.as_str()).map(|c| c.to_string()).collect())
+            .unwrap_or_default();
+        // model_info keys are prefixed with the architecture, e.g. "llama.context_length"
+        let context_length = json.get("model_info").and_then(|v| v.as_object()).and_then(|info| {
+            info.iter()
+                .find(|(key, _)| key.ends_with(".context_length"))
+                .and_then(|(_, value)| value.as_u64())
+        });
+        // Modelfile parameters come as "name value" lines
+        let num_ctx = json.get("parameters").and_then(|v| v.as_str()).and_then(|parameters| {
+            parameters.lines().find_map(|line| {
+                let mut parts = line.split_whitespace();
+                match (parts.next(), parts.next()) {
+                    (Some("num_ctx"), Some(value)) => value.parse().ok(),
+                    _ => None,
+                }
+            })
+        });
+        ModelDetails { capabilities, context: ModelContext { context_length, num_ctx } }
+    }
+}
+
+// /api/show details per server and model. Pulling or removing a model and changing the endpoints
+// can change the answer, so those clear the cache (forget_model_details).
+type ModelDetailsCache = HashMap<(String, String), ModelDetails>;
+
+static MODEL_DETAILS: OnceLock<Mutex<ModelDetailsCache>> = OnceLock::new();
+
+fn model_details_cache() -> std::sync::MutexGuard<'static, ModelDetailsCache> {
+    MODEL_DETAILS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn forget_model_details() {
+    model_details_cache().clear();
+}
+
+enum OllamaTarget {
+    Endpoint(Endpoint),
+    Pool(EndpointPool, Vec<Endpoint>),
+}
+
+pub struct OllamaProvider {
+    target: OllamaTarget,
+}
+
+// An accepted reply whose body has not been read yet
+struct OllamaReply {
+    response: reqwest::Response,
+    // Keeps a pool member counted as busy while the body is read
+    _in_flight: Option<InFlight>,
+    served_by: String,
+}
+
+impl OllamaProvider {
+    // POST a model request; pools pick a member new enough for `features` and fail over before the reply starts
+    async fn post(&self, path: &str, model: &str, body: &Value, features: &[OllamaFeature]) -> Result<OllamaReply, String> {
+        let (response, in_flight, served_by) = match &self.target {
+            OllamaTarget::Endpoint(endpoint) => {
+                crate::require_endpoint_features(endpoint, features).await?;
+                let response = crate::endpoint_client(endpoint, None)?
+                    .post(endpoint.api_url(path))
+                    .json(body)
+                    .send()
+                    .await
+                    .map_err(|e| format!("Request to /api/{} failed: {}", path, e))?;
+                (response, None, endpoint.name.clone())
+            }
+            OllamaTarget::Pool(selected, endpoints) => {
+                let pooled = pool::send(selected, endpoints, model, features, |member, client| {
+                    client.post(member.api_url(path)).json(body)
+                })
+                .await?;
+                (pooled.response, Some(pooled.in_flight), pooled.endpoint.name)
+            }
+        };
+        let response = check_status(response, &format!("/api/{}", path)).await?;
+        Ok(OllamaReply { response, _in_flight: in_flight, served_by })
+    }
+
+    fn cache_key(&self) -> String {
+        match &self.target {
+            OllamaTarget::Endpoint(endpoint) => endpoint.base_url(),
+            OllamaTarget::Pool(selected, _) => format!("pool:{}", selected.id),
+        }
+    }
+
+    async fn details(&self, model: &str) -> Result<ModelDetails, String> {
+        let key = (self.cache_key(), model.to_string());
+        if let Some(details) = model_details_cache().get(&key) {
+            return Ok(details.clone());
+        }
+
+        let reply = self.post("show", model, &json!({ "model": model }), &[]).await?;
+        let json: Value = reply.response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse /api/show response: {}", e))?;
+        let details = ModelDetails::from_show(&json);
+
+        model_details_cache().insert(key, details.clone());
+        Ok(details)
+    }
+
+    // The think value to send: only models that can reason accept the field
+    async fn think_field(&self, request: &ChatRequest) -> Option<bool> {
+        let think = request.think?;
+        match self.capabilities(&request.model).await {
+            Ok(capabilities) if capabilities.iter().any(|c| c == "thinking") => Some(think),
+            Ok(_) => None,
+            Err(e) => {
+                eprintln!("Could not read capabilities of {}: {}; sending the request without think", request.model, e);
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for OllamaProvider {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Ollama
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, String> {
+        match &self.target {
+            OllamaTarget::Endpoint(endpoint) => {
+                let client = crate::endpoint_client(endpoint, None)?;
+                let json = get_json(&client, &endpoint.api_url("tags")).await?;
+                Ok(crate::model_names_from_json(&json))
+            }
+            OllamaTarget::Pool(selected, endpoints) => {
+                // Every model at least one healthy member can serve
+                let status = pool::status(selected, endpoints, true).await;
+                let mut models: Vec<String> = status
+                    .members
+                    .into_iter()
+                    .filter(|member| member.healthy)
+                    .flat_map(|member| member.models)
+                    .collect();
+                models.sort();
+                models.dedup();
+                Ok(models)
+            }
+        }
+    }
+
+    async fn chat(&self, request: &ChatRequest, on_delta: &mut (dyn for<'a> FnMut(ChatDelta<'a>) + Send)) -> Result<ChatResult, String> {
+        let mut body = json!({
+            "model": request.model,
+            "messages": wire_messages(&request.messages),
+            "stream": true,
+        });
+        let mut options = request.options.clone();
+        // keep_alive is a request field rather than a model option
+        if let Some(keep_alive) = options.remove("keep_alive") {
+            body["keep_alive"] = keep_alive;
+        }
+        if !options.is_empty() {
+            body["options"] = Value::Object(options);
+        }
+        if let Some(think) = self.think_field(request).await {
+            body["think"] = json!(think);
+        }
+        if !request.tools.is_empty() {
+            body["tools"] = json!(request.tools);
+        }
+        if let Some(format) = &request.format {
+            body["format"] = format.clone();
+        }
+
+        let reply = self.post("chat", &request.model, &body, &chat_features(request)).await?;
+        let mut result = ChatResult {
+            model: request.model.clone(),
+            served_by: reply.served_by.clone(),
+            options: request.options.clone(),
+            ..ChatResult::default()
+        };
+
+        for_each_line(reply.response, |line| {
+            let json: Value = serde_json::from_str(line)
+                .map_err(|e| format!("Failed to parse /api/chat stream: {}", e))?;
+            if let Some(error) = json.get("error").and_then(|v| v.as_str()) {
+                return Err(format!("Ollama reported an error: {}", error));
+            }
+            if let Some(delta) = json.pointer("/message/thinking").and_then(|v| v.as_str()) {
+                if !delta.is_empty() {
+                    push_thinking(&mut result, delta);
+                    on_delta(ChatDelta::Thinking(delta));
+                }
+            }
+            if let Some(delta) = json.pointer("/message/content").and_then(|v| v.as_str()) {
+                if !delta.is_empty() {
+                    result.content.push_str(delta);
+                    on_delta(ChatDelta::Content(delta));
+                }
+            }
+            if let Some(calls) = json.pointer("/message/tool_calls").and_then(|v| v.as_array()) {
+                result.tool_calls.extend(calls.iter().cloned());
+            }
+            if json.get("done").and_then(|v| v.as_bool()).unwrap_or(false) {
+                result.done_reason = json.get("done_reason").and_then(|v| v.as_str()).map(|v| v.to_string());
+                result.prompt_tokens = json.get("prompt_eval_count").and_then(|v| v.as_u64());
+                result.completion_tokens = json.get("eval_count").and_then(|v| v.as_u64());
+                return Ok(false);
+            }
+            Ok(true)
+        })
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn embed(&self, model: &str, input: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let body = json!({ "model": model, "input": input });
+        let reply = self.post("embed", model, &body, &[OllamaFeature::EmbedApi]).await?;
+        let json: Value = reply.response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse /api/embed response: {}", e))?;
+        serde_json::from_value(json.get("embeddings").cloned().unwrap_or(Value::Null))
+            .map_err(|e| format!("Unexpected /api/embed response: {}", e))
+    }
+
+    async fn capabilities(&self, model: &str) -> Result<Vec<String>, String> {
+        Ok(self.details(model).await?.capabilities)
+    }
+
+    async fn model_context(&self, model: &str) -> Result<ModelContext, String> {
+        Ok(self.details(model).await?.context)
+    }
+
+    async fn health(&self) -> ProviderHealth {
+        match &self.target {
+            OllamaTarget::Endpoint(endpoint) => match crate::probe_endpoint_version(endpoint).await {
+                Ok(version) => ProviderHealth {
+                    kind: ProviderKind::Ollama,
+                    healthy: true,
+                    message: format!("Ollama {} at {}", version, endpoint.base_url()),
+                    version: Some(version),
+                },
+                Err(message) => ProviderHealth { kind: ProviderKind::Ollama, healthy: false, version: None, message },
+            },
+            OllamaTarget::Pool(selected, endpoints) => {
+                let status = pool::status(selected, endpoints, true).await;
+                let healthy = status.members.iter().filter(|m| m.healthy).count();
+                ProviderHealth {
+                    kind: ProviderKind::Ollama,
+                    healthy: healthy > 0,
+                    version: None,
+                    message: format!("{} of {} members of pool '{}' are healthy", healthy, status.members.len(), selected.name),
+                }
+            }
+        }
+    }
+}
+
+pub struct OpenAiProvider {
+    endpoint: Endpoint,
+}
+
+impl OpenAiProvider {
+    fn url(&self, path: &str) -> String {
+        format!("{}/v1/{}", self.endpoint.base_url(), path)
+    }
+
+    // Map Ollama-style options onto the OpenAI request body; options without an equivalent are dropped
+    fn apply_options(body: &mut Value, options: &serde_json::Map<String, Value>) {
+        for (name, value) in options {
+            let name = match name.as_str() {
+                "num_predict" => "max_tokens",
+                // Context size and residency are server settings here
+                "num_ctx" | "keep_alive" => continue,
+                other => other,
+            };
+            body[name] = value.clone();
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for OpenAiProvider {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::OpenAiCompatible
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, String> {
+        let client = crate::endpoint_client(&self.endpoint, None)?;
+        let json = get_json(&client, &self.url("models")).await?;
+        Ok(json
+            .get("data")
+            .and_then(|v| v.as_array())
+            .map(|models| {
+                models
+                    .iter()
+                    .filter_map(|m| m.get("id").and_then(|id| id.as_str()))
+                    .map(|id| id.to_string())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn chat(&self, request: &ChatRequest, on_delta: &mut (dyn for<'a> FnMut(ChatDelta<'a>) + Send)) -> Result<ChatResult, String> {
+        if !request.tools.is_empty() || request.format.is_some() {
+            return Err(format!("Tool calls and structured outputs need an Ollama endpoint; '{}' is OpenAI-compatible", self.endpoint.name));
+        }
+        let mut body = json!({
+            "model": request.model,
+            "messages": wire_messages(&request.messages),
+            "stream": true,
+        });
+        Self::apply_options(&mut body, &request.options);
+
+        let response = crate::endpoint_client(&self.endpoint, None)?
+            .post(self.url("chat/completions"))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("Request to /v1/chat/completions failed: {}", e))?;
+        let response = check_status(response, "/v1/chat/completions").await?;
+
+        let mut result = ChatResult {
+            model: request.model.clone(),
+            served_by: self.endpoint.name.clone(),
+            options: request.options.clone(),
+            ..ChatResult::default()
+        };
+
+        // Server-sent events: "data: {...}" lines, terminated by "data: [DONE]"
+        for_each_line(response, |line| {
+            let Some(data) = line.strip_prefix("data:").map(|d| d.trim()) else {
+                return Ok(true);
+            };
+            if data == "[DONE]" {
+                return Ok(false);
+            }
+            let json: Value = serde_json::from_str(data)
+                .map_err(|e| format!("Failed to parse /v1/chat/completions stream: {}", e))?;
+            if let Some(error) = json.get("error") {
+                let message = error.get("message").and_then(|v| v.as_str()).map(|v| v.to_string()).unwrap_or_else(|| error.to_string());
+                return Err(format!("Server reported an error: {}", message));
+            }
+            if let Some(model) = json.get("model").and_then(|v| v.as_str()) {
+                result.model = model.to_string();
+            }
+            // llama-server and several other servers stream reasoning as reasoning_content
+            if let Some(delta) = json.pointer("/choices/0/delta/reasoning_content").and_then(|v| v.as_str()) {
+                if !delta.is_empty() {
+                    push_thinking(&mut result, delta);
+                    on_delta(ChatDelta::Thinking(delta));
+                }
+            }
+            if let Some(delta) = json.pointer("/choices/0/delta/content").and_then(|v| v.as_str()) {
+                if !delta.is_empty() {
+                    result.content.push_str(delta);
+                    on_delta(ChatDelta::Content(delta));
+                }
+            }
+            if let Some(reason) = json.pointer("/choices/0/finish_reason").and_then(|v| v.as_str()) {
+                result.done_reason = Some(reason.to_string());
+            }
+            if let Some(usage) = json.get("usage").filter(|u| u.is_object()) {
+                result.prompt_tokens = usage.get("prompt_tokens").and_then(|v| v.as_u64());
+                result.completion_tokens = usage.get("completion_tokens").and_then(|v| v.as_u64());
+            }
+            Ok(true)
+        })
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn embed(&self, model: &str, input: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let response = crate::endpoint_client(&self.endpoint, None)?
+            .post(self.url("embeddings"))
+            .json(&json!({ "model": model, "input": input }))
+            .send()
+            .await
+            .map_err(|e| format!("Request to /v1/embeddings failed: {}", e))?;
+        let json: Value = check_status(response, "/v1/embeddings")
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse /v1/embeddings response: {}", e))?;
+
+        json.get("data")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| "Unexpected /v1/embeddings response: missing data".to_string())?
+            .iter()
+            .map(|item| {
+                serde_json::from_value(item.get("embedding").cloned().unwrap_or(Value::Null))
+                    .map_err(|e| format!("Unexpected /v1/embeddings response: {}", e))
+            })
+            .collect()
+    }
+
+    async fn capabilities(&self, _model: &str) -> Result<Vec<String>, String> {
+        // /v1/models does not describe capabilities
+        Ok(Vec::new())
+    }
+
+    async fn model_context(&self, _model: &str) -> Result<ModelContext, String> {
+        // The context size is fixed when the server starts and /v1/models does not report it
+        Ok(ModelContext::default())
+    }
+
+    async fn health(&self) -> ProviderHealth {
+        let unhealthy = |message: String| ProviderHealth {
+            kind: ProviderKind::OpenAiCompatible,
+            healthy: false,
+            version: None,
+            message,
+        };
+        match self.list_models().await {
+            Ok(models) => ProviderHealth {
+                kind: ProviderKind::OpenAiCompatible,
+                healthy: true,
+                version: None,
+                message: format!("OpenAI-compatible server at {} with {} model(s)", self.endpoint.base_url(), models.len()),
+            },
+            Err(e) => unhealthy(e),
+        }
+    }
+}
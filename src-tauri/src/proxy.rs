@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::process::Command;
+use serde::{Deserialize, Serialize};
+
+// Variables understood by reqwest, curl and Python's urllib/requests
+const PROXY_ENV_VARS: [&str; 6] = ["HTTP_PROXY", "HTTPS_PROXY", "ALL_PROXY", "http_proxy", "https_proxy", "all_proxy"];
+const NO_PROXY_ENV_VARS: [&str; 2] = ["NO_PROXY", "no_proxy"];
+
+// Variables each scheme's proxy is read from in system mode, in reqwest's order of preference
+const SCHEME_PROXY_ENV_VARS: [(&str, [&str; 4]); 2] = [
+    ("http", ["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]),
+    ("https", ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]),
+];
+
+// Loopback hosts never go through a proxy, so a local Ollama keeps working
+const LOOPBACK_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyMode {
+    // Use HTTP_PROXY / HTTPS_PROXY / ALL_PROXY / NO_PROXY from the environment
+    #[default]
+    System,
+    // Connect directly and ignore any proxy variables
+    Direct,
+    // Send everything through `url`
+    Manual,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxySettings {
+    pub mode: ProxyMode,
+    // http://, https://, socks5:// or socks5h:// URL, used in manual mode
+    pub url: String,
+    // Hosts, domains (".corp.example") or CIDR ranges that bypass the manual proxy
+    pub no_proxy: Vec<String>,
+}
+
+impl ProxySettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.mode != ProxyMode::Manual {
+            return Ok(());
+        }
+        let url = reqwest::Url::parse(self.url.trim())
+            .map_err(|e| format!("Invalid proxy URL '{}': {}", self.url, e))?;
+        if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h") {
+            return Err(format!("Unsupported proxy scheme '{}': use http, https, socks5 or socks5h", url.scheme()));
+        }
+        if url.host_str().is_none() {
+            return Err(format!("Proxy URL '{}' has no host", self.url));
+        }
+        Ok(())
+    }
+
+    fn no_proxy_list(&self) -> String {
+        with_loopback(self.no_proxy.iter().map(String::as_str))
+    }
+}
+
+// A NO_PROXY value: the loopback hosts followed by `entries`
+fn with_loopback<'a>(entries: impl Iterator<Item = &'a str>) -> String {
+    LOOPBACK_HOSTS
+        .iter()
+        .copied()
+        .chain(entries.map(str::trim))
+        .filter(|entry| !entry.is_empty())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn env_value(var: &str) -> Option<String> {
+    std::env::var(var).ok().filter(|value| !value.trim().is_empty())
+}
+
+// NO_PROXY from the environment with the loopback hosts added
+fn env_no_proxy_list() -> String {
+    let env = NO_PROXY_ENV_VARS.iter().find_map(|var| env_value(var)).unwrap_or_default();
+    with_loopback(env.split(','))
+}
+
+// Whether system mode has a proxy to use from the environment
+fn has_env_proxy() -> bool {
+    PROXY_ENV_VARS.iter().any(|var| env_value(var).is_some())
+}
+
+// Shared starting point for every outbound HTTP client, with the configured proxy applied
+pub fn client_builder() -> Result<reqwest::ClientBuilder, String> {
+    let settings = crate::settings::current().proxy;
+    let builder = reqwest::Client::builder();
+
+    match settings.mode {
+        // Without proxy variables reqwest falls back to the Windows and macOS proxy settings on its own
+        ProxyMode::System if !has_env_proxy() => Ok(builder),
+        // Set up the environment's proxies here rather than leaving them to reqwest, so loopback
+        // hosts can be added to NO_PROXY
+        ProxyMode::System => {
+            let no_proxy = env_no_proxy_list();
+            let mut builder = builder;
+            for (scheme, vars) in SCHEME_PROXY_ENV_VARS {
+                let Some((var, url)) = vars.iter().find_map(|var| env_value(var).map(|url| (var, url))) else {
+                    continue;
+                };
+                let proxy = match scheme {
+                    "http" => reqwest::Proxy::http(url.trim()),
+                    _ => reqwest::Proxy::https(url.trim()),
+                }
+                .map_err(|e| format!("Invalid proxy in {}: {}", var, e))?;
+                builder = builder.proxy(proxy.no_proxy(reqwest::NoProxy::from_string(&no_proxy)));
+            }
+            Ok(builder)
+        }
+        ProxyMode::Direct => Ok(builder.no_proxy()),
+        ProxyMode::Manual => {
+            settings.validate()?;
+            let proxy = reqwest::Proxy::all(settings.url.trim())
+                .map_err(|e| format!("Invalid proxy URL '{}': {}", settings.url, e))?
+                .no_proxy(reqwest::NoProxy::from_string(&settings.no_proxy_list()));
+            Ok(builder.proxy(proxy))
+        }
+    }
+}
+
+// Helper function to build a proxied client with default settings
+pub fn client() -> Result<reqwest::Client, String> {
+    client_builder()?
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+// Pass the proxy configuration on to child processes (Python search, curl)
+pub fn apply_to_command(command: &mut Command) {
+    let settings = crate::settings::current().proxy;
+    match settings.mode {
+        ProxyMode::System => {
+            if has_env_proxy() {
+                let no_proxy = env_no_proxy_list();
+                for var in NO_PROXY_ENV_VARS {
+                    command.env(var, &no_proxy);
+                }
+            }
+        }
+        ProxyMode::Direct => {
+            for var in PROXY_ENV_VARS {
+                command.env_remove(var);
+            }
+            for var in NO_PROXY_ENV_VARS {
+                command.env(var, "*");
+            }
+        }
+        ProxyMode::Manual => {
+            let url = settings.url.trim();
+            for var in PROXY_ENV_VARS {
+                command.env(var, url);
+            }
+            let no_proxy = settings.no_proxy_list();
+            for var in NO_PROXY_ENV_VARS {
+                command.env(var, &no_proxy);
+            }
+        }
+    }
+}
+
+// Proxy variables currently set in the environment, i.e. what system mode will use
+pub fn detect_env_proxies() -> BTreeMap<String, String> {
+    PROXY_ENV_VARS
+        .iter()
+        .chain(NO_PROXY_ENV_VARS.iter())
+        .filter_map(|var| {
+            std::env::var(var)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| (var.to_string(), redact_userinfo(&value)))
+        })
+        .collect()
+}
+
+// Hide a password embedded in a proxy URL before showing it
+fn redact_userinfo(value: &str) -> String {
+    match reqwest::Url::parse(value) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some("****"));
+            url.to_string()
+        }
+        _ => value.to_string(),
+    }
+}
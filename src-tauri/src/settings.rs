@@ -0,0 +1,398 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::context::ContextSettings;
+use crate::encryption::EncryptionSettings;
+use crate::endpoints::{self, Endpoint};
+use crate::personas::Persona;
+use crate::pool::EndpointPool;
+use crate::profiles::Profile;
+use crate::proxy::ProxySettings;
+
+// Bump this together with a new step in `migrate` whenever the stored layout changes
+pub const SETTINGS_SCHEMA_VERSION: u32 = 3;
+
+// Event sent to the webview after every successful update
+pub const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DefaultModels {
+    pub chat: Option<String>,
+    // Model the web search script summarizes with; None keeps the script's own default
+    pub search: Option<String>,
+}
+
+// Overrides for the web search script; unset values fall back to its config.json
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchSettings {
+    pub searxng_instances: Vec<String>,
+    pub max_results: Option<u32>,
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub schema_version: u32,
+    pub endpoints: Vec<Endpoint>,
+    pub active_endpoint: String,
+    pub pools: Vec<EndpointPool>,
+    // When set, model requests are spread across this pool instead of going to the active endpoint
+    pub active_pool: Option<String>,
+    pub ollama_binary_path: Option<String>,
+    pub default_models: DefaultModels,
+    // Extra environment for `ollama serve`, e.g. OLLAMA_MODELS or OLLAMA_KEEP_ALIVE
+    pub server_env: BTreeMap<String, String>,
+    pub search: SearchSettings,
+    // System prompt for chat requests that bring none of their own (see apply_conversation_defaults)
+    pub prompt_preset: Option<String>,
+    pub profiles: Vec<Profile>,
+    // Profile applied last with switch_profile
+    pub active_profile: Option<String>,
+    // Proxy used for every outbound request and passed on to the Python search script
+    pub proxy: ProxySettings,
+    // How chat requests are trimmed when they outgrow the model's context
+    pub context: ContextSettings,
+    // Named assistants conversations can be started from
+    pub personas: Vec<Persona>,
+    // Auto-lock of the conversation store once it is encrypted
+    pub encryption: EncryptionSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            schema_version: SETTINGS_SCHEMA_VERSION,
+            endpoints: vec![Endpoint::local(crate::DEFAULT_OLLAMA_PORT)],
+            active_endpoint: endpoints::LOCAL_ENDPOINT_ID.to_string(),
+            pools: Vec::new(),
+            active_pool: None,
+            ollama_binary_path: None,
+            default_models: DefaultModels::default(),
+            server_env: BTreeMap::new(),
+            search: SearchSettings::default(),
+            prompt_preset: None,
+            profiles: Vec::new(),
+            active_profile: None,
+            proxy: ProxySettings::default(),
+            context: ContextSettings::default(),
+            personas: Vec::new(),
+            encryption: EncryptionSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.endpoints.is_empty() {
+            return Err("At least one endpoint must be configured".to_string());
+        }
+        for (index, endpoint) in self.endpoints.iter().enumerate() {
+            endpoint.validate()?;
+            if self.endpoints[..index].iter().any(|other| other.id == endpoint.id) {
+                return Err(format!("Duplicate endpoint id '{}'", endpoint.id));
+            }
+        }
+        if !self.endpoints.iter().any(|e| e.id == self.active_endpoint) {
+            return Err(format!("Active endpoint '{}' does not exist", self.active_endpoint));
+        }
+        for (index, pool) in self.pools.iter().enumerate() {
+            pool.validate(&self.endpoints)?;
+            if self.pools[..index].iter().any(|other| other.id == pool.id) {
+                return Err(format!("Duplicate pool id '{}'", pool.id));
+            }
+        }
+        if let Some(active_pool) = &self.active_pool {
+            if !self.pools.iter().any(|p| &p.id == active_pool) {
+                return Err(format!("Active pool '{}' does not exist", active_pool));
+            }
+        }
+        validate_server_env(&self.server_env)?;
+        for (index, profile) in self.profiles.iter().enumerate() {
+            profile.validate()?;
+            if self.profiles[..index].iter().any(|other| other.id == profile.id) {
+                return Err(format!("Duplicate profile id '{}'", profile.id));
+            }
+        }
+        if let Some(active_profile) = &self.active_profile {
+            if !self.profiles.iter().any(|p| &p.id == active_profile) {
+                return Err(format!("Active profile '{}' does not exist", active_profile));
+            }
+        }
+        self.proxy.validate()?;
+        self.context.validate()?;
+        for (index, persona) in self.personas.iter().enumerate() {
+            persona.validate()?;
+            if self.personas[..index].iter().any(|other| other.id == persona.id) {
+                return Err(format!("Duplicate persona id '{}'", persona.id));
+            }
+        }
+        self.encryption.validate()?;
+        Ok(())
+    }
+
+    pub fn active_endpoint(&self) -> Endpoint {
+        // validate() guarantees the active id exists, but files written by hand are loaded as-is
+        self.endpoints
+            .iter()
+            .find(|e| e.id == self.active_endpoint)
+            .or_else(|| self.endpoints.first())
+            .cloned()
+            .unwrap_or_else(|| Endpoint::local(crate::DEFAULT_OLLAMA_PORT))
+    }
+}
+
+pub fn validate_server_env(env: &BTreeMap<String, String>) -> Result<(), String> {
+    for name in env.keys() {
+        if name.trim().is_empty() || name.contains('=') || name.contains(char::is_whitespace) {
+            return Err(format!("Invalid server environment variable name '{}'", name));
+        }
+    }
+    Ok(())
+}
+
+static SETTINGS: OnceLock<RwLock<Settings>> = OnceLock::new();
+static SETTINGS_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+fn store() -> &'static RwLock<Settings> {
+    SETTINGS.get_or_init(|| RwLock::new(Settings::default()))
+}
+
+// Load the settings file from the app config directory, migrating it to the current schema.
+// Called once from the Tauri setup hook; until then every reader sees the defaults.
+// Changes are only written once a load succeeded, so a file that could not be read is never overwritten.
+pub fn init(config_dir: PathBuf) -> Result<(), String> {
+    let path = config_dir.join(SETTINGS_FILE);
+
+    let settings = if path.exists() {
+        load(&path)?
+    } else {
+        // First run: carry over the port the previous env-var based config used
+        let mut settings = Settings::default();
+        if let Some(port) = std::env::var("OLLAMA_PORT").ok().and_then(|s| s.parse::<u16>().ok()) {
+            settings.endpoints = vec![Endpoint::local(port)];
+        }
+        write_atomic(&path, &settings)?;
+        settings
+    };
+
+    *store().write().unwrap_or_else(|e| e.into_inner()) = settings;
+    let _ = SETTINGS_PATH.set(path);
+    Ok(())
+}
+
+fn load(path: &Path) -> Result<Settings, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read settings from {}: {}", path.display(), e))?;
+
+    let (settings, changed) = match parse(&text) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            // Keep the broken file for inspection and start over with defaults
+            let backup = path.with_extension("json.corrupt");
+            eprintln!("Settings file {} could not be loaded ({}); moving it to {}", path.display(), e, backup.display());
+            std::fs::rename(path, &backup)
+                .map_err(|e| format!("Failed to move {} to {}: {}", path.display(), backup.display(), e))?;
+            (Settings::default(), true)
+        }
+    };
+
+    if changed {
+        write_atomic(path, &settings)?;
+    }
+    Ok(settings)
+}
+
+// Settings from the text of a settings file, and whether migrating changed them
+fn parse(text: &str) -> Result<(Settings, bool), String> {
+    let raw: Value = serde_json::from_str(text).map_err(|e| format!("not valid JSON: {}", e))?;
+    let (migrated, changed) = migrate(raw)?;
+    let settings: Settings = serde_json::from_value(migrated)
+        .map_err(|e| format!("Failed to parse settings: {}", e))?;
+    Ok((settings, changed))
+}
+
+// Upgrade a raw settings document one schema version at a time.
+// Returns the migrated document and whether anything changed.
+pub fn migrate(mut raw: Value) -> Result<(Value, bool), String> {
+    let mut version = raw.get("schema_version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    let original = version;
+
+    if version > SETTINGS_SCHEMA_VERSION {
+        // Written by a newer build; read what we understand and leave the file alone
+        return Ok((raw, false));
+    }
+
+    while version < SETTINGS_SCHEMA_VERSION {
+        let object = raw
+            .as_object_mut()
+            .ok_or_else(|| "Settings file must contain a JSON object".to_string())?;
+
+        match version {
+            0 => {
+                // Unversioned files used "port" for the Ollama port
+                if let Some(port) = object.remove("port") {
+                    object.entry("ollama_port").or_insert(port);
+                }
+            }
+            1 => {
+                // The single local port became the first entry of the endpoint list
+                let port = object
+                    .remove("ollama_port")
+                    .and_then(|v| v.as_u64())
+                    .and_then(|v| u16::try_from(v).ok())
+                    .unwrap_or(crate::DEFAULT_OLLAMA_PORT);
+                let local = serde_json::to_value(Endpoint::local(port))
+                    .map_err(|e| format!("Failed to migrate settings: {}", e))?;
+                object.insert("endpoints".to_string(), Value::Array(vec![local]));
+                object.insert("active_endpoint".to_string(), Value::from(endpoints::LOCAL_ENDPOINT_ID));
+            }
+            2 => {
+                // Personas lost the "web_search" knowledge source
+                if let Some(personas) = object.get_mut("personas") {
+                    crate::personas::drop_web_search_knowledge(personas);
+                }
+            }
+            _ => unreachable!("missing settings migration from version {}", version),
+        }
+
+        version += 1;
+        object.insert("schema_version".to_string(), Value::from(version));
+    }
+
+    Ok((raw, version != original))
+}
+
+fn write_atomic(path: &Path, settings: &Settings) -> Result<(), String> {
+    write_json_atomic(path, settings, false)
+}
+
+// Write JSON through a temporary file and rename it into place so a crash never leaves half a file.
+// `private` restricts the file to the current user on Unix (used for credentials).
+pub fn write_json_atomic<T: Serialize>(path: &Path, value: &T, private: bool) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory {}: {}", parent.display(), e))?;
+    }
+
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+
+    let tmp_path = path.with_extension("json.tmp");
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    let mut file = options
+        .open(&tmp_path)
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    file.write_all(json.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+
+    std::fs::rename(&tmp_path, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+// Settings as stored in settings.json, without command-line or environment overrides
+pub fn stored() -> Settings {
+    store().read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+// Snapshot of the effective settings: the stored ones with overrides applied (see config.rs)
+pub fn current() -> Settings {
+    let mut settings = stored();
+    crate::config::apply_overrides(&mut settings);
+    settings
+}
+
+// The endpoint every Ollama request should go to
+pub fn active_endpoint() -> Endpoint {
+    current().active_endpoint()
+}
+
+pub fn settings_path() -> Option<PathBuf> {
+    SETTINGS_PATH.get().cloned()
+}
+
+// Apply a change to the stored settings, validate it and persist it. Nothing is stored if validation fails.
+// Returns the effective settings afterwards.
+pub fn update<F>(apply: F) -> Result<Settings, String>
+where
+    F: FnOnce(&mut Settings),
+{
+    try_update(|settings| {
+        apply(settings);
+        Ok(())
+    })
+}
+
+// Like update, for changes that can fail part way; an error leaves the stored settings untouched
+fn try_update<F>(apply: F) -> Result<Settings, String>
+where
+    F: FnOnce(&mut Settings) -> Result<(), String>,
+{
+    let mut guard = store().write().unwrap_or_else(|e| e.into_inner());
+    let mut updated = guard.clone();
+    apply(&mut updated)?;
+    updated.schema_version = SETTINGS_SCHEMA_VERSION;
+    updated.validate()?;
+
+    if let Some(path) = SETTINGS_PATH.get() {
+        write_atomic(path, &updated)?;
+    }
+
+    *guard = updated.clone();
+    drop(guard);
+
+    crate::config::apply_overrides(&mut updated);
+    Ok(updated)
+}
+
+// Merge a partial JSON object from the frontend into the current settings
+pub fn update_from_patch(patch: Value) -> Result<Settings, String> {
+    let patch = match patch {
+        Value::Object(map) => map,
+        _ => return Err("Settings update must be a JSON object".to_string()),
+    };
+
+    // Merge into the stored settings so overrides never end up in the file. This happens under the
+    // write lock, so a change saved meanwhile by another command is not overwritten with a stale copy.
+    try_update(|settings| {
+        let mut merged = serde_json::to_value(&*settings)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        if let Value::Object(object) = &mut merged {
+            for (key, value) in patch {
+                if key != "schema_version" {
+                    object.insert(key, value);
+                }
+            }
+        }
+
+        let mut patched: Settings = serde_json::from_value(merged)
+            .map_err(|e| format!("Invalid settings: {}", e))?;
+        // Insecure mode is only switched on through set_endpoint_tls, which asks for confirmation;
+        // a patch may turn it off but never on
+        for endpoint in &mut patched.endpoints {
+            endpoint.tls.insecure &= settings
+                .endpoints
+                .iter()
+                .any(|e| e.id == endpoint.id && e.tls.insecure);
+        }
+        *settings = patched;
+        Ok(())
+    })
+}
@@ -0,0 +1,182 @@
+use std::process::Command;
+use serde::{Deserialize, Serialize};
+
+// Unit name created by the official Linux install script (https://ollama.com/install.sh)
+pub const OLLAMA_UNIT: &str = "ollama.service";
+
+// Default number of journal lines returned when the caller does not ask for a specific amount
+pub const DEFAULT_JOURNAL_LINES: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SystemdScope {
+    System,
+    User,
+}
+
+impl SystemdScope {
+    // Extra arguments systemctl/journalctl need to talk to this scope's manager
+    fn scope_args(self) -> &'static [&'static str] {
+        match self {
+            SystemdScope::System => &[],
+            SystemdScope::User => &["--user"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemdUnitStatus {
+    pub unit: String,
+    pub scope: SystemdScope,
+    pub load_state: String,
+    pub active_state: String,
+    pub sub_state: String,
+    pub unit_file_state: String,
+    pub main_pid: Option<u32>,
+    pub fragment_path: Option<String>,
+}
+
+impl SystemdUnitStatus {
+    pub fn is_active(&self) -> bool {
+        self.active_state == "active" || self.active_state == "activating"
+    }
+}
+
+// Helper function to check whether systemd is the running init system
+pub fn systemd_available() -> bool {
+    if !cfg!(target_os = "linux") {
+        return false;
+    }
+
+    // sd_booted() checks for this directory as well
+    std::path::Path::new("/run/systemd/system").exists()
+}
+
+// Query systemctl for the properties of a unit in the given scope.
+// Returns None when the unit is not known to that manager.
+pub fn unit_status(scope: SystemdScope, unit: &str) -> Option<SystemdUnitStatus> {
+    let output = Command::new("systemctl")
+        .args(scope.scope_args())
+        .args([
+            "show",
+            unit,
+            "--no-pager",
+            "--property=LoadState,ActiveState,SubState,UnitFileState,MainPID,FragmentPath",
+        ])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut status = SystemdUnitStatus {
+        unit: unit.to_string(),
+        scope,
+        load_state: String::new(),
+        active_state: String::new(),
+        sub_state: String::new(),
+        unit_file_state: String::new(),
+        main_pid: None,
+        fragment_path: None,
+    };
+
+    for line in stdout.lines() {
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim();
+            match key {
+                "LoadState" => status.load_state = value.to_string(),
+                "ActiveState" => status.active_state = value.to_string(),
+                "SubState" => status.sub_state = value.to_string(),
+                "UnitFileState" => status.unit_file_state = value.to_string(),
+                "MainPID" => status.main_pid = value.parse::<u32>().ok().filter(|pid| *pid != 0),
+                "FragmentPath" if !value.is_empty() => status.fragment_path = Some(value.to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    // systemctl show succeeds for unknown units too, reporting LoadState=not-found
+    if status.load_state == "loaded" {
+        Some(status)
+    } else {
+        None
+    }
+}
+
+// Detect the Ollama unit, preferring the system-wide unit installed by the official script
+// and falling back to a per-user unit.
+pub fn detect_ollama_unit() -> Option<SystemdUnitStatus> {
+    if !systemd_available() {
+        return None;
+    }
+
+    unit_status(SystemdScope::System, OLLAMA_UNIT)
+        .or_else(|| unit_status(SystemdScope::User, OLLAMA_UNIT))
+}
+
+// Run a systemctl verb (start, stop, restart) against a unit.
+// System units need elevated privileges, so a failed attempt is retried through pkexec.
+pub fn run_unit_action(scope: SystemdScope, unit: &str, action: &str) -> Result<String, String> {
+    let output = Command::new("systemctl")
+        .args(scope.scope_args())
+        .args([action, unit])
+        .output()
+        .map_err(|e| format!("Failed to run systemctl {} {}: {}", action, unit, e))?;
+
+    if output.status.success() {
+        return Ok(format!("systemctl {} {} succeeded", action, unit));
+    }
+
+    let error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+
+    if scope == SystemdScope::System {
+        let elevated = Command::new("pkexec")
+            .args(["systemctl", action, unit])
+            .output();
+
+        match elevated {
+            Ok(elevated) if elevated.status.success() => {
+                return Ok(format!("pkexec systemctl {} {} succeeded", action, unit));
+            }
+            Ok(elevated) => {
+                let elevated_error = String::from_utf8_lossy(&elevated.stderr);
+                return Err(format!(
+                    "systemctl {} {} failed: {}. Retrying with pkexec failed: {}",
+                    action, unit, error, elevated_error.trim()
+                ));
+            }
+            Err(e) => {
+                return Err(format!(
+                    "systemctl {} {} failed: {}. pkexec is not available: {}",
+                    action, unit, error, e
+                ));
+            }
+        }
+    }
+
+    Err(format!("systemctl --user {} {} failed: {}", action, unit, error))
+}
+
+// Read the most recent journal lines for a unit
+pub fn journal_lines(scope: SystemdScope, unit: &str, lines: usize) -> Result<Vec<String>, String> {
+    let line_count = lines.to_string();
+    let output = Command::new("journalctl")
+        .args(scope.scope_args())
+        .args(["-u", unit, "-n", &line_count, "--no-pager", "-o", "short-iso"])
+        .output()
+        .map_err(|e| format!("Failed to run journalctl: {}", e))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("journalctl failed for {}: {}", unit, error.trim()));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .lines()
+        .filter(|line| !line.starts_with("-- "))
+        .map(|line| line.to_string())
+        .collect())
+}
@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+// Marker and layout version of the library file, which is also how the store is kept on disk
+pub const TEMPLATE_FILE_FORMAT: &str = "beautifyollama-prompt-templates";
+pub const TEMPLATE_FILE_VERSION: u32 = 1;
+
+const TEMPLATES_FILE: &str = "prompt_templates.json";
+
+// Filled in at render time; templates cannot declare variables with these names.
+// The webview passes selection and clipboard since only it can read them.
+const BUILTIN_VARIABLES: [&str; 7] = ["date", "time", "datetime", "weekday", "os", "selection", "clipboard"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateVariable {
+    pub name: String,
+    #[serde(default)]
+    pub default: Option<String>,
+    // Shown next to the input when the template is used
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+// A reusable prompt; `{{name}}` in the body is replaced by a variable's value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub body: String,
+    // Declared variables with their defaults; placeholders not declared here have no default
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TemplateFile {
+    format: String,
+    version: u32,
+    templates: Vec<PromptTemplate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderedPrompt {
+    pub text: String,
+    // Variables without a value or default; their placeholders are left in the text
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LibraryImport {
+    pub added: usize,
+    // Templates replaced by a newer copy with the same id
+    pub updated: usize,
+    // Same id and not newer than the local copy
+    pub unchanged: usize,
+    pub warnings: Vec<String>,
+}
+
+static TEMPLATES: OnceLock<RwLock<Vec<PromptTemplate>>> = OnceLock::new();
+static TEMPLATES_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+fn store() -> &'static RwLock<Vec<PromptTemplate>> {
+    TEMPLATES.get_or_init(|| RwLock::new(Vec::new()))
+}
+
+fn placeholder_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_.-]*)\s*\}\}").unwrap())
+}
+
+fn valid_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+// Trimmed, without empty entries or case-insensitive duplicates
+fn clean_tags(tags: &[String]) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|tag| tag.trim()).filter(|tag| !tag.is_empty()) {
+        if !cleaned.iter().any(|existing| existing.eq_ignore_ascii_case(tag)) {
+            cleaned.push(tag.to_string());
+        }
+    }
+    cleaned
+}
+
+impl PromptTemplate {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Template name must not be empty".to_string());
+        }
+        if self.body.trim().is_empty() {
+            return Err(format!("Template '{}' has an empty body", self.name));
+        }
+        for (index, variable) in self.variables.iter().enumerate() {
+            if !valid_variable_name(&variable.name) {
+                return Err(format!("Template '{}': invalid variable name '{}'", self.name, variable.name));
+            }
+            if BUILTIN_VARIABLES.contains(&variable.name.as_str()) {
+                return Err(format!("Template '{}': '{}' is a built-in variable", self.name, variable.name));
+            }
+            if self.variables[..index].iter().any(|other| other.name == variable.name) {
+                return Err(format!("Template '{}': variable '{}' is declared twice", self.name, variable.name));
+            }
+        }
+        Ok(())
+    }
+
+    // Names of the placeholders in the body, in order of first use
+    pub fn placeholders(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for captures in placeholder_pattern().captures_iter(&self.body) {
+            if !names.iter().any(|name| name == &captures[1]) {
+                names.push(captures[1].to_string());
+            }
+        }
+        names
+    }
+}
+
+fn builtin_values(selection: Option<String>, clipboard: Option<String>) -> HashMap<String, String> {
+    let now = chrono::Local::now();
+    let os = match std::env::consts::OS {
+        "macos" => "macOS",
+        "windows" => "Windows",
+        "linux" => "Linux",
+        other => other,
+    };
+    HashMap::from([
+        ("date".to_string(), now.format("%Y-%m-%d").to_string()),
+        ("time".to_string(), now.format("%H:%M").to_string()),
+        ("datetime".to_string(), now.to_rfc3339_opts(chrono::SecondsFormat::Secs, false)),
+        ("weekday".to_string(), now.format("%A").to_string()),
+        ("os".to_string(), os.to_string()),
+        ("selection".to_string(), selection.unwrap_or_default()),
+        ("clipboard".to_string(), clipboard.unwrap_or_default()),
+    ])
+}
+
+// Fill in a template: given values first, then declared defaults, then built-ins
+fn render_template(
+    template: &PromptTemplate,
+    values: &HashMap<String, String>,
+    selection: Option<String>,
+    clipboard: Option<String>,
+) -> RenderedPrompt {
+    let builtins = builtin_values(selection, clipboard);
+    let mut missing: Vec<String> = Vec::new();
+    let text = placeholder_pattern().replace_all(&template.body, |captures: &Captures| {
+        let name = &captures[1];
+        let value = values
+            .get(name)
+            .or_else(|| template.variables.iter().find(|v| v.name == name).and_then(|v| v.default.as_ref()))
+            .or_else(|| builtins.get(name));
+        match value {
+            Some(value) => value.clone(),
+            None => {
+                if !missing.iter().any(|m| m == name) {
+                    missing.push(name.to_string());
+                }
+                captures[0].to_string()
+            }
+        }
+    });
+    RenderedPrompt { text: text.into_owned(), missing }
+}
+
+fn parse_file(path: &Path) -> Result<TemplateFile, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read prompt templates from {}: {}", path.display(), e))?;
+    let file: TemplateFile = serde_json::from_str(&text)
+        .map_err(|e| format!("{} is not a prompt template library: {}", path.display(), e))?;
+    if file.format != TEMPLATE_FILE_FORMAT {
+        return Err(format!("{} is not a prompt template library", path.display()));
+    }
+    Ok(file)
+}
+
+fn check_version(file: &TemplateFile, path: &Path) -> Result<(), String> {
+    if file.version > TEMPLATE_FILE_VERSION {
+        return Err(format!(
+            "{} was written by a newer version (template format {}, this build reads up to {})",
+            path.display(),
+            file.version,
+            TEMPLATE_FILE_VERSION
+        ));
+    }
+    Ok(())
+}
+
+fn write_file(path: &Path, templates: Vec<PromptTemplate>) -> Result<(), String> {
+    let file = TemplateFile {
+        format: TEMPLATE_FILE_FORMAT.to_string(),
+        version: TEMPLATE_FILE_VERSION,
+        templates,
+    };
+    crate::settings::write_json_atomic(path, &file, false)
+}
+
+// Load the template store from the app config directory. Called once from the Tauri setup hook.
+pub fn init(config_dir: PathBuf) -> Result<(), String> {
+    let path = config_dir.join(TEMPLATES_FILE);
+    if path.exists() {
+        let templates = match parse_file(&path) {
+            Ok(file) => {
+                // Leave a newer build's file alone; changes made now are not saved
+                check_version(&file, &path)?;
+                file.templates
+            }
+            Err(e) => {
+                // Keep the broken file for inspection and start over empty
+                let backup = path.with_extension("json.corrupt");
+                eprintln!("{}; moving it to {}", e, backup.display());
+                let _ = std::fs::rename(&path, &backup);
+                Vec::new()
+            }
+        };
+        *store().write().unwrap_or_else(|e| e.into_inner()) = templates;
+    }
+    let _ = TEMPLATES_PATH.set(path);
+    Ok(())
+}
+
+// Change the templates and persist them; nothing changes when `apply` fails
+fn update<T, F>(apply: F) -> Result<T, String>
+where
+    F: FnOnce(&mut Vec<PromptTemplate>) -> Result<T, String>,
+{
+    let mut guard = store().write().unwrap_or_else(|e| e.into_inner());
+    let mut updated = guard.clone();
+    let result = apply(&mut updated)?;
+    if let Some(path) = TEMPLATES_PATH.get() {
+        write_file(path, updated.clone())?;
+    }
+    *guard = updated;
+    Ok(result)
+}
+
+// Templates sorted by name, optionally only those with `tag`
+pub fn list(tag: Option<&str>) -> Vec<PromptTemplate> {
+    let mut templates: Vec<PromptTemplate> = store()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .filter(|template| tag.map_or(true, |tag| template.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))))
+        .cloned()
+        .collect();
+    templates.sort_by_key(|template| template.name.to_lowercase());
+    templates
+}
+
+pub fn get(id: &str) -> Result<PromptTemplate, String> {
+    store()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find(|template| template.id == id)
+        .cloned()
+        .ok_or_else(|| format!("Prompt template '{}' does not exist", id))
+}
+
+// Add a template, or replace the one with the same id. An empty id gets a new one.
+pub fn save(mut template: PromptTemplate) -> Result<PromptTemplate, String> {
+    template.tags = clean_tags(&template.tags);
+    template.validate()?;
+    let now = crate::conversations::now_millis();
+    update(|templates| {
+        match templates.iter_mut().find(|existing| !template.id.is_empty() && existing.id == template.id) {
+            Some(existing) => {
+                template.created_at = existing.created_at;
+                template.updated_at = now;
+                *existing = template.clone();
+            }
+            None => {
+                if template.id.trim().is_empty() {
+                    template.id = uuid::Uuid::new_v4().to_string();
+                }
+                template.created_at = now;
+                template.updated_at = now;
+                templates.push(template.clone());
+            }
+        }
+        Ok(template)
+    })
+}
+
+pub fn delete(id: &str) -> Result<(), String> {
+    update(|templates| {
+        let before = templates.len();
+        templates.retain(|template| template.id != id);
+        if templates.len() == before {
+            return Err(format!("Prompt template '{}' does not exist", id));
+        }
+        Ok(())
+    })
+}
+
+// What the template asks for: declared variables, then undeclared placeholders, built-ins left out
+pub fn variables(id: &str) -> Result<Vec<TemplateVariable>, String> {
+    let template = get(id)?;
+    let mut variables = template.variables.clone();
+    for name in template.placeholders() {
+        if !BUILTIN_VARIABLES.contains(&name.as_str()) && !variables.iter().any(|v| v.name == name) {
+            variables.push(TemplateVariable { name, default: None, description: None });
+        }
+    }
+    Ok(variables)
+}
+
+pub fn render(
+    id: &str,
+    values: &HashMap<String, String>,
+    selection: Option<String>,
+    clipboard: Option<String>,
+) -> Result<RenderedPrompt, String> {
+    Ok(render_template(&get(id)?, values, selection, clipboard))
+}
+
+// Every tag in use with the number of templates carrying it
+pub fn tags() -> Vec<TagCount> {
+    let mut counts: Vec<TagCount> = Vec::new();
+    for tag in store().read().unwrap_or_else(|e| e.into_inner()).iter().flat_map(|t| t.tags.iter()) {
+        match counts.iter_mut().find(|count| count.tag.eq_ignore_ascii_case(tag)) {
+            Some(count) => count.count += 1,
+            None => counts.push(TagCount { tag: tag.clone(), count: 1 }),
+        }
+    }
+    counts.sort_by_key(|count| count.tag.to_lowercase());
+    counts
+}
+
+// Rename a tag everywhere, merging it into `to` where both are present. Returns the templates changed.
+pub fn rename_tag(from: &str, to: &str) -> Result<usize, String> {
+    let to = to.trim();
+    if to.is_empty() {
+        return Err("Tag must not be empty".to_string());
+    }
+    let now = crate::conversations::now_millis();
+    update(|templates| {
+        let mut changed = 0;
+        for template in templates.iter_mut().filter(|t| t.tags.iter().any(|tag| tag.eq_ignore_ascii_case(from))) {
+            let renamed: Vec<String> = template
+                .tags
+                .iter()
+                .map(|tag| if tag.eq_ignore_ascii_case(from) { to.to_string() } else { tag.clone() })
+                .collect();
+            template.tags = clean_tags(&renamed);
+            template.updated_at = now;
+            changed += 1;
+        }
+        Ok(changed)
+    })
+}
+
+// Remove a tag from every template. Returns the templates changed.
+pub fn delete_tag(tag: &str) -> Result<usize, String> {
+    let now = crate::conversations::now_millis();
+    update(|templates| {
+        let mut changed = 0;
+        for template in templates.iter_mut() {
+            let before = template.tags.len();
+            template.tags.retain(|t| !t.eq_ignore_ascii_case(tag));
+            if template.tags.len() != before {
+                template.updated_at = now;
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    })
+}
+
+// Write the given templates (all of them without ids) to a library file to share
+pub fn export_to(ids: Option<&[String]>, path: &Path) -> Result<usize, String> {
+    let templates: Vec<PromptTemplate> = list(None)
+        .into_iter()
+        .filter(|template| ids.map_or(true, |ids| ids.contains(&template.id)))
+        .collect();
+    if let Some(ids) = ids {
+        if let Some(unknown) = ids.iter().find(|id| !templates.iter().any(|t| &t.id == *id)) {
+            return Err(format!("Prompt template '{}' does not exist", unknown));
+        }
+    }
+    let count = templates.len();
+    write_file(path, templates)?;
+    Ok(count)
+}
+
+// Merge a library file. Templates with a known id replace the local copy only when they are newer.
+pub fn import_from(path: &Path) -> Result<LibraryImport, String> {
+    let file = parse_file(path)?;
+    check_version(&file, path)?;
+    let now = crate::conversations::now_millis();
+    update(|templates| {
+        let mut result = LibraryImport::default();
+        for mut template in file.templates {
+            template.tags = clean_tags(&template.tags);
+            if let Err(e) = template.validate() {
+                result.warnings.push(format!("Skipped: {}", e));
+                continue;
+            }
+            if template.created_at == 0 {
+                template.created_at = now;
+            }
+            if template.updated_at == 0 {
+                template.updated_at = template.created_at;
+            }
+            match templates.iter_mut().find(|existing| !template.id.is_empty() && existing.id == template.id) {
+                Some(existing) if template.updated_at > existing.updated_at => {
+                    *existing = template;
+                    result.updated += 1;
+                }
+                Some(_) => result.unchanged += 1,
+                None => {
+                    if template.id.trim().is_empty() {
+                        template.id = uuid::Uuid::new_v4().to_string();
+                    }
+                    templates.push(template);
+                    result.added += 1;
+                }
+            }
+        }
+        Ok(result)
+    })
+}
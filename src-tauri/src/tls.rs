@@ -0,0 +1,246 @@
+use std::io::BufReader;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, RootCertStore, ServerName};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Per-endpoint trust settings for HTTPS endpoints; ignored for plain http
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsOptions {
+    // PEM file with extra CA certificates to trust on top of the system store
+    pub ca_bundle_path: Option<String>,
+    // SHA-256 fingerprints of accepted server certificates. A match is trusted on its own,
+    // so self-signed certificates can be pinned; anything else is rejected.
+    pub pinned_sha256: Vec<String>,
+    // Accept any certificate. Only turned on through set_endpoint_tls with explicit confirmation.
+    pub insecure: bool,
+}
+
+impl TlsOptions {
+    pub fn is_default(&self) -> bool {
+        *self == TlsOptions::default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(path) = &self.ca_bundle_path {
+            if path.trim().is_empty() {
+                return Err("CA bundle path must not be empty".to_string());
+            }
+        }
+        for pin in &self.pinned_sha256 {
+            normalize_fingerprint(pin)?;
+        }
+        Ok(())
+    }
+}
+
+// Accept "AB:CD:..." as shown by browsers and openssl as well as plain hex
+pub fn normalize_fingerprint(raw: &str) -> Result<String, String> {
+    let hex: String = raw
+        .chars()
+        .filter(|c| !matches!(c, ':' | ' ' | '-'))
+        .collect::<String>()
+        .to_lowercase();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("'{}' is not a SHA-256 fingerprint (expected 64 hex digits)", raw.trim()));
+    }
+    Ok(hex)
+}
+
+// Colon-separated uppercase hex, the format openssl prints
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub serial: String,
+    pub not_before: String,
+    pub not_after: String,
+    pub subject_alt_names: Vec<String>,
+    pub sha256_fingerprint: String,
+    pub self_signed: bool,
+    pub currently_valid: bool,
+}
+
+// Helper function to turn a DER certificate into something the endpoint dialog can show
+pub fn describe_certificate(der: &[u8]) -> CertificateInfo {
+    let sha256_fingerprint = fingerprint(der);
+    let cert = match x509_parser::parse_x509_certificate(der) {
+        Ok((_, cert)) => cert,
+        Err(e) => {
+            return CertificateInfo {
+                subject: format!("Unparseable certificate: {}", e),
+                issuer: String::new(),
+                serial: String::new(),
+                not_before: String::new(),
+                not_after: String::new(),
+                subject_alt_names: Vec::new(),
+                sha256_fingerprint,
+                self_signed: false,
+                currently_valid: false,
+            }
+        }
+    };
+
+    let subject_alt_names = match cert.subject_alternative_name() {
+        Ok(Some(extension)) => extension
+            .value
+            .general_names
+            .iter()
+            .filter_map(|name| match name {
+                x509_parser::extensions::GeneralName::DNSName(dns) => Some(dns.to_string()),
+                x509_parser::extensions::GeneralName::IPAddress(ip) => match ip.len() {
+                    4 => Some(std::net::Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3]).to_string()),
+                    16 => <[u8; 16]>::try_from(*ip).ok().map(|octets| std::net::Ipv6Addr::from(octets).to_string()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let validity = cert.validity();
+    CertificateInfo {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        serial: cert.raw_serial_as_string(),
+        not_before: validity.not_before.to_rfc2822().unwrap_or_else(|_| validity.not_before.to_string()),
+        not_after: validity.not_after.to_rfc2822().unwrap_or_else(|_| validity.not_after.to_string()),
+        subject_alt_names,
+        sha256_fingerprint,
+        self_signed: cert.subject() == cert.issuer(),
+        currently_valid: validity.is_valid(),
+    }
+}
+
+// What the verifier saw during a handshake, collected for the endpoint test
+#[derive(Debug, Default)]
+pub struct HandshakeObservation {
+    pub certificate: Option<Vec<u8>>,
+    pub rejection: Option<String>,
+}
+
+struct EndpointVerifier {
+    webpki: WebPkiVerifier,
+    pins: Vec<String>,
+    insecure: bool,
+    observation: Option<Arc<Mutex<HandshakeObservation>>>,
+}
+
+impl EndpointVerifier {
+    fn check(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if !self.pins.is_empty() {
+            let presented = normalize_fingerprint(&fingerprint(&end_entity.0)).unwrap_or_default();
+            if self.pins.contains(&presented) {
+                return Ok(ServerCertVerified::assertion());
+            }
+            return Err(rustls::Error::General(format!(
+                "certificate fingerprint {} does not match any pinned fingerprint",
+                fingerprint(&end_entity.0)
+            )));
+        }
+        if self.insecure {
+            return Ok(ServerCertVerified::assertion());
+        }
+        self.webpki
+            .verify_server_cert(end_entity, intermediates, server_name, &mut std::iter::empty(), ocsp_response, now)
+    }
+}
+
+impl ServerCertVerifier for EndpointVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let result = self.check(end_entity, intermediates, server_name, ocsp_response, now);
+        if let Some(observation) = &self.observation {
+            let mut observation = observation.lock().unwrap_or_else(|e| e.into_inner());
+            observation.certificate = Some(end_entity.0.clone());
+            observation.rejection = result.as_ref().err().map(|e| e.to_string());
+        }
+        result
+    }
+}
+
+// The system store is read once; on some platforms loading it takes a noticeable moment
+static NATIVE_ROOTS: OnceLock<Vec<Vec<u8>>> = OnceLock::new();
+
+fn native_roots() -> &'static [Vec<u8>] {
+    NATIVE_ROOTS.get_or_init(|| match rustls_native_certs::load_native_certs() {
+        Ok(certs) => certs.into_iter().map(|cert| cert.0).collect(),
+        Err(e) => {
+            eprintln!("Failed to load system CA certificates: {}", e);
+            Vec::new()
+        }
+    })
+}
+
+fn load_ca_bundle(path: &str) -> Result<Vec<Vec<u8>>, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open CA bundle {}: {}", path, e))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .map_err(|e| format!("Failed to read CA bundle {}: {}", path, e))?;
+    if certs.is_empty() {
+        return Err(format!("No PEM certificates found in CA bundle {}", path));
+    }
+    Ok(certs)
+}
+
+// Build the rustls configuration for an endpoint's TLS options.
+// Pass an observation to record the presented certificate and any rejection reason.
+pub fn client_config(
+    options: &TlsOptions,
+    observation: Option<Arc<Mutex<HandshakeObservation>>>,
+) -> Result<ClientConfig, String> {
+    options.validate()?;
+
+    let mut roots = RootCertStore::empty();
+    roots.add_parsable_certificates(native_roots());
+    if let Some(path) = &options.ca_bundle_path {
+        let (added, _) = roots.add_parsable_certificates(&load_ca_bundle(path.trim())?);
+        if added == 0 {
+            return Err(format!("CA bundle {} contains no usable certificates", path));
+        }
+    }
+
+    let verifier = EndpointVerifier {
+        webpki: WebPkiVerifier::new(roots, None),
+        pins: options
+            .pinned_sha256
+            .iter()
+            .map(|pin| normalize_fingerprint(pin))
+            .collect::<Result<Vec<_>, _>>()?,
+        insecure: options.insecure,
+        observation,
+    };
+
+    let mut config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(config)
+}
@@ -0,0 +1,117 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+// Ollama features that only exist from a certain server version onwards.
+// Each one is checked on the command path that uses it; add a check with any new entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OllamaFeature {
+    Thinking,
+    ToolCalls,
+    StructuredOutputs,
+    EmbedApi,
+    PsApi,
+}
+
+impl OllamaFeature {
+    pub const ALL: [OllamaFeature; 5] = [
+        OllamaFeature::Thinking,
+        OllamaFeature::ToolCalls,
+        OllamaFeature::StructuredOutputs,
+        OllamaFeature::EmbedApi,
+        OllamaFeature::PsApi,
+    ];
+
+    // Minimum Ollama release that ships the feature
+    pub fn min_version(self) -> Version {
+        match self {
+            OllamaFeature::Thinking => Version::new(0, 9, 0),
+            OllamaFeature::ToolCalls => Version::new(0, 3, 0),
+            OllamaFeature::StructuredOutputs => Version::new(0, 5, 0),
+            OllamaFeature::EmbedApi => Version::new(0, 3, 4),
+            OllamaFeature::PsApi => Version::new(0, 1, 38),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OllamaFeature::Thinking => "Thinking",
+            OllamaFeature::ToolCalls => "Tool calls",
+            OllamaFeature::StructuredOutputs => "Structured outputs",
+            OllamaFeature::EmbedApi => "The /api/embed endpoint",
+            OllamaFeature::PsApi => "The /api/ps endpoint",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureSupport {
+    pub feature: OllamaFeature,
+    pub min_version: String,
+    // None when the Ollama version could not be determined
+    pub supported: Option<bool>,
+}
+
+// Pull the first semver-looking token out of strings such as
+// "ollama version is 0.5.7" or "Warning: client version is 0.6.0-rc1"
+pub fn parse_version(raw: &str) -> Option<Version> {
+    let re = regex::Regex::new(r"\d+\.\d+\.\d+(?:-[0-9A-Za-z.-]+)?").unwrap();
+    re.find(raw).and_then(|m| Version::parse(m.as_str()).ok())
+}
+
+// Parse `ollama --version` output into (server, client) versions.
+// When no server is reachable the CLI only prints the client version, prefixed with a warning.
+pub fn parse_cli_output(output: &str) -> (Option<Version>, Option<Version>) {
+    let mut server = None;
+    let mut client = None;
+
+    for line in output.lines() {
+        let line = line.trim();
+        if line.contains("client version is") {
+            client = parse_version(line);
+        } else if line.contains("version is") {
+            server = parse_version(line);
+        }
+    }
+
+    // Older releases print a single "ollama version x.y.z" line for both
+    if server.is_none() && client.is_none() {
+        client = parse_version(output);
+    }
+
+    let client = client.or_else(|| server.clone());
+    (server, client)
+}
+
+// Compare a detected version against the feature table.
+// Pre-releases of the minimum version count as supported.
+pub fn supports(version: &Version, feature: OllamaFeature) -> bool {
+    let min = feature.min_version();
+    let release = Version::new(version.major, version.minor, version.patch);
+    release >= min
+}
+
+pub fn feature_table(version: Option<&Version>) -> Vec<FeatureSupport> {
+    OllamaFeature::ALL
+        .iter()
+        .map(|feature| FeatureSupport {
+            feature: *feature,
+            min_version: feature.min_version().to_string(),
+            supported: version.map(|v| supports(v, *feature)),
+        })
+        .collect()
+}
+
+// Helper function to build the error returned when an install is too old for a feature
+pub fn check_feature(version: Option<&Version>, feature: OllamaFeature) -> Result<(), String> {
+    match version {
+        Some(version) if !supports(version, feature) => Err(format!(
+            "{} requires Ollama >= {} (found {}). Please update Ollama.",
+            feature.label(),
+            feature.min_version(),
+            version
+        )),
+        // Unknown versions are let through; the request itself will report any problem
+        _ => Ok(()),
+    }
+}
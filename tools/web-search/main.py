@@ -5,8 +5,12 @@ Powered by Ollama and SearxNG for intelligent information retrieval
 """
 
 import ollama
+import httpx
 import requests
+import functools
+import hashlib
 import json
+import ssl
 import time
 import sys
 import os
@@ -47,6 +51,92 @@ def load_config():
 
 CONFIG = load_config()
 
+# Model server used for query generation and summaries; set from --provider / --base-url
+PROVIDER = {
+    'kind': 'ollama',
+    'base_url': None,
+    'api_key': os.environ.get('OPENAI_API_KEY'),
+}
+
+# Credentials and TLS settings of the model server endpoint, passed by the app as JSON
+ENDPOINT_HEADERS: Dict[str, str] = json.loads(os.environ.get('BEAUTIFYOLLAMA_ENDPOINT_HEADERS') or '{}')
+ENDPOINT_TLS: Dict = json.loads(os.environ.get('BEAUTIFYOLLAMA_ENDPOINT_TLS') or '{}')
+
+class PinnedSSLSocket(ssl.SSLSocket):
+    """TLS socket that only completes the handshake with a certificate pinned for the endpoint"""
+    def do_handshake(self, *args, **kwargs):
+        super().do_handshake(*args, **kwargs)
+        presented = hashlib.sha256(self.getpeercert(binary_form=True) or b'').hexdigest()
+        if presented not in ENDPOINT_TLS.get('pinned_sha256', []):
+            raise ssl.SSLError(f'certificate fingerprint {presented} does not match any pinned fingerprint')
+
+def endpoint_verify():
+    """The verify argument for clients of the model server, checked in the app's order: pins, insecure mode, CA bundle"""
+    if ENDPOINT_TLS.get('pinned_sha256'):
+        context = ssl.create_default_context()
+        context.check_hostname = False
+        context.verify_mode = ssl.CERT_NONE
+        context.sslsocket_class = PinnedSSLSocket
+        return context
+    if ENDPOINT_TLS.get('insecure'):
+        return False
+    if ENDPOINT_TLS.get('ca_bundle_path'):
+        context = ssl.create_default_context()
+        context.load_verify_locations(ENDPOINT_TLS['ca_bundle_path'])
+        return context
+    return True
+
+@functools.lru_cache(maxsize=None)
+def ollama_client() -> ollama.Client:
+    """Ollama client for OLLAMA_HOST that sends the endpoint's credentials"""
+    return ollama.Client(headers=ENDPOINT_HEADERS, verify=endpoint_verify())
+
+@functools.lru_cache(maxsize=None)
+def openai_client() -> httpx.Client:
+    """HTTP client for OpenAI-compatible servers that sends the endpoint's credentials"""
+    headers = dict(ENDPOINT_HEADERS)
+    if PROVIDER['api_key'] and 'authorization' not in {name.lower() for name in headers}:
+        headers['Authorization'] = f"Bearer {PROVIDER['api_key']}"
+    return httpx.Client(headers=headers, verify=endpoint_verify())
+
+# Models checked for the "thinking" capability, so /api/show runs once per model
+THINKING_SUPPORT: Dict[str, bool] = {}
+
+def supports_thinking(model: str) -> bool:
+    """Whether the Ollama model reports the "thinking" capability"""
+    if model not in THINKING_SUPPORT:
+        try:
+            capabilities = ollama_client().show(model).get('capabilities') or []
+        except Exception:
+            capabilities = []
+        THINKING_SUPPORT[model] = 'thinking' in capabilities
+    return THINKING_SUPPORT[model]
+
+def think_argument(model: str, thinking: bool) -> Dict:
+    """The think argument for ollama.chat; models without the capability reject it, so they get none"""
+    if PROVIDER['kind'] == 'ollama' and supports_thinking(model):
+        return {'think': thinking}
+    return {}
+
+def provider_chat(model: str, messages: List[Dict], thinking: bool = False) -> str:
+    """Single non-streaming chat call through the configured provider"""
+    if PROVIDER['kind'] == 'openai_compatible':
+        response = openai_client().post(
+            f"{PROVIDER['base_url']}/v1/chat/completions",
+            json={'model': model, 'messages': messages, 'stream': False},
+            timeout=120
+        )
+        response.raise_for_status()
+        return response.json()['choices'][0]['message']['content']
+    return ollama_client().chat(model=model, messages=messages, **think_argument(model, thinking))['message']['content']
+
+def provider_check():
+    """Raise if the configured provider cannot be reached"""
+    if PROVIDER['kind'] == 'openai_compatible':
+        openai_client().get(f"{PROVIDER['base_url']}/v1/models", timeout=10).raise_for_status()
+    else:
+        ollama_client().list()
+
 class Colors:
     """ANSI color codes for beautiful terminal output"""
     BLUE = '\033[94m'
@@ -94,23 +184,8 @@ class WebSearchAssistant:
         """Get response from Ollama model with error handling and thinking mode control"""
         for attempt in range(max_retries):
             try:
-                # Create conversation with thinking mode setting first
-                messages = []
-                
-                # First message: Set the thinking mode
-                if thinking:
-                    messages.append({'role': 'user', 'content': '/set think'})
-                    messages.append({'role': 'assistant', 'content': "Set 'think' mode."})
-                else:
-                    messages.append({'role': 'user', 'content': '/set nothink'})
-                    messages.append({'role': 'assistant', 'content': "Set 'nothink' mode."})
-                
-                # Second message: Send the actual user message
-                messages.append({'role': 'user', 'content': message})
-                
-                # Get the response to the actual message
-                response = ollama.chat(model=model, messages=messages)
-                return response['message']['content']
+                # Reasoning is switched with the think argument, so the prompt stays as written
+                return provider_chat(model, [{'role': 'user', 'content': message}], thinking)
             except Exception as e:
                 print(f"{Colors.RED}⚠️  Attempt {attempt + 1} failed: {str(e)}{Colors.END}")
                 if attempt < max_retries - 1:
@@ -124,7 +199,7 @@ class WebSearchAssistant:
         response = ""
         try:
             print(f"{Colors.GREEN}🤖 Assistant:{Colors.END}", end=" ")
-            for chunk in ollama.chat(model=model, messages=[{
+            for chunk in ollama_client().chat(model=model, messages=[{
                 'role': 'user',
                 'content': message,
             }], stream=True):
@@ -320,25 +395,14 @@ Instructions:
 
         print(f"\n{Colors.PURPLE}{'='*60}{Colors.END}")
         
-        # Set thinking mode and get response
         try:
-            # Create conversation with thinking mode setting first
-            messages = []
-            
-            # First message: Set the thinking mode
-            if thinking:
-                messages.append({'role': 'user', 'content': '/set think'})
-                messages.append({'role': 'assistant', 'content': "Set 'think' mode."})
-            else:
-                messages.append({'role': 'user', 'content': '/set nothink'})
-                messages.append({'role': 'assistant', 'content': "Set 'nothink' mode."})
-            
-            # Second message: Send the actual prompt
-            messages.append({'role': 'user', 'content': prompt})
-            
-            # Stream the response for the actual prompt
+            # Stream the response; reasoning comes back separately from the answer
             print(f"{Colors.GREEN}🤖 Assistant:{Colors.END}", end=" ")
-            for chunk in ollama.chat(model=CONFIG['model'], messages=messages, stream=True):
+            messages = [{'role': 'user', 'content': prompt}]
+            for chunk in ollama_client().chat(model=CONFIG['model'], messages=messages, stream=True, **think_argument(CONFIG['model'], thinking)):
+                thinking_chunk = chunk['message'].get('thinking')
+                if thinking_chunk:
+                    print(f"{Colors.PURPLE}{thinking_chunk}{Colors.END}", end='', flush=True)
                 content_chunk = chunk['message']['content']
                 print(content_chunk, end='', flush=True)
                 time.sleep(CONFIG.get('streaming_delay', 0.02))
@@ -550,12 +614,27 @@ def main():
     parser.add_argument('--config', action='store_true', help='Show current configuration and exit')
     parser.add_argument('--json', action='store_true', help='Output results in JSON format (for API integration)')
     parser.add_argument('--thinking', action='store_true', help='Enable thinking mode')
+    parser.add_argument('--instances', help='Comma-separated SearxNG instances to use instead of config.json')
+    parser.add_argument('--max-results', type=int, help=f'Maximum search results (default: {CONFIG["max_results"]})')
+    parser.add_argument('--timeout', type=int, help=f'Search request timeout in seconds (default: {CONFIG["timeout"]})')
+    parser.add_argument('--provider', choices=['ollama', 'openai_compatible'], default='ollama', help='Model server API (default: ollama)')
+    parser.add_argument('--base-url', help='Server URL for the openai_compatible provider, e.g. http://localhost:8080')
     
     args = parser.parse_args()
     
     # Update config with command line arguments if explicitly provided
     if args.model != CONFIG['model']:  # Only update if user explicitly provided a different model
         CONFIG['model'] = args.model
+    if args.instances:
+        CONFIG['searxng_instances'] = [i.strip() for i in args.instances.split(',') if i.strip()]
+    if args.max_results is not None:
+        CONFIG['max_results'] = args.max_results
+    if args.timeout is not None:
+        CONFIG['timeout'] = args.timeout
+    PROVIDER['kind'] = args.provider
+    PROVIDER['base_url'] = (args.base_url or '').rstrip('/')
+    if args.provider == 'openai_compatible' and not PROVIDER['base_url']:
+        parser.error('--base-url is required with --provider openai_compatible')
     
     assistant = WebSearchAssistant()
     
@@ -569,13 +648,13 @@ def main():
     
     # JSON mode for API integration (no banner, colors, or interactive prompts)
     if args.json:
-        # Check if Ollama is available
+        # Check if the model server is available
         try:
-            ollama.list()
+            provider_check()
         except Exception as e:
             result = {
                 'success': False,
-                'error': f'Cannot connect to Ollama: {str(e)}',
+                'error': f'Cannot connect to {"Ollama" if args.provider == "ollama" else args.base_url}: {str(e)}',
                 'user_query': args.query or ''
             }
             print(json.dumps(result))
@@ -600,7 +679,7 @@ def main():
     
     # Check if Ollama is available
     try:
-        ollama.list()
+        ollama_client().list()
         print(f"{Colors.GREEN}✅ Ollama connection successful{Colors.END}")
         print(f"{Colors.BLUE}🤖 Using model: {Colors.BOLD}{CONFIG['model']}{Colors.END}")
     except Exception as e: